@@ -5,29 +5,36 @@
 use crate::{
     context::Context,
     data::{
-        Channel, Crate, CrateName, CrateType, D, Date, DocBackend, Identity, PlusPrefixedToolchain,
-        V, Version, VersionTriple,
+        Channel, Crate, CrateName, CrateType, D, Date, DocBackend, ExternCrate, Identity,
+        PlusPrefixedToolchain, V, Version, VersionTriple,
     },
     diagnostic::{EmittedError, debug, error},
     error::Result,
     fmt,
     source::SourcePath,
-    utility::{default, small_fixed_map::SmallKey},
+    utility::{default, jobs, paint, small_fixed_map::SmallKey},
 };
 use anstyle::AnsiColor;
 use command::Command;
 use std::{
     borrow::Cow,
     cmp::Ordering,
-    ffi::OsStr,
+    env,
+    ffi::{OsStr, OsString},
     io::{self, Write as _},
     path::{Path, PathBuf},
-    process::ExitStatusError,
+    process,
     string::FromUtf8Error,
+    time::SystemTime,
 };
 
 mod command;
 mod environment;
+mod ice;
+#[cfg(test)]
+mod test;
+
+pub(crate) use ice::Ice;
 
 pub(crate) fn perform(
     e_opts: &EngineOptions<'_>,
@@ -36,6 +43,49 @@ pub(crate) fn perform(
     imply_u_opts: ImplyUnstableOptions,
     cx: Context<'_>,
 ) -> Result<()> {
+    let mut cmd = prepare(e_opts, krate, opts, imply_u_opts, cx)?;
+    configure_color(&mut cmd, e_opts.engine(), opts, cx)?;
+    let start = SystemTime::now();
+    let (status, stderr) = cmd.execute_keeping_stderr()?;
+
+    let ice = match stderr {
+        // The stderr output contains the path to the ICE report. Prefer it over looking for the most
+        // recent report in the current directory which might belong to a concurrent job.
+        Some(stderr) => Ice::detect(status, &String::from_utf8_lossy(&stderr)),
+        None => Ice::recover(status, start),
+    };
+    if let Some(ice) = ice {
+        let engine = e_opts.engine().name();
+        let it = error(fmt!("{engine} encountered an internal compiler error"));
+        return Err(ice.annotate(it).note(fmt!("{status}")).done().into());
+    }
+
+    status.exit_ok().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Like [`perform`] but capture the output of the engine.
+///
+/// Unlike [`perform`], this doesn't consider an unsuccessful exit status to be an error.
+/// It's the responsibility of the caller to inspect it.
+pub(crate) fn perform_capturing_output(
+    e_opts: &EngineOptions<'_>,
+    krate: Crate<'_>,
+    opts: &Options<'_>,
+    imply_u_opts: ImplyUnstableOptions,
+    cx: Context<'_>,
+) -> Result<process::Output> {
+    let cmd = prepare(e_opts, krate, opts, imply_u_opts, cx)?;
+    Ok(cmd.execute_capturing_output()?)
+}
+
+fn prepare<'cx>(
+    e_opts: &EngineOptions<'_>,
+    krate: Crate<'_>,
+    opts: &Options<'_>,
+    imply_u_opts: ImplyUnstableOptions,
+    cx: Context<'cx>,
+) -> Result<Command<'cx>> {
     let engine = e_opts.engine();
 
     let mut cmd = engine
@@ -43,19 +93,25 @@ pub(crate) fn perform(
         .map_err(|error| error.emit(engine))?;
     configure_early(&mut cmd, e_opts, krate, opts, cx)?;
     configure_late(&mut cmd, engine, opts, cx)?;
+    // The engine needs to be able to load dynamically linked proc macros.
+    configure_library_paths(&mut cmd, &library_paths(opts, cx)?)?;
 
     if let ImplyUnstableOptions::Yes = imply_u_opts
-        && match probe_identity(opts) {
-            Identity::True => engine.version(cx).is_ok_and(|v| v.channel.allows_unstable()),
-            Identity::Stable => false,
-            Identity::Nightly => true,
-        }
+        && allows_unstable_options(engine, opts, cx)
     {
         cmd.arg("-Zunstable-options");
     }
 
-    cmd.execute()?.exit_ok().map_err(io::Error::other)?;
-    Ok(())
+    Ok(cmd)
+}
+
+/// Whether the engine accepts unstable options (taking into account the identity it assumes).
+pub(crate) fn allows_unstable_options(engine: Engine, opts: &Options<'_>, cx: Context<'_>) -> bool {
+    match probe_identity(opts) {
+        Identity::True => engine.version(cx).is_ok_and(|v| v.channel.allows_unstable()),
+        Identity::Stable => false,
+        Identity::Nightly => true,
+    }
 }
 
 /// Don't call this directly! Use [`EngineKind::path`] instead.
@@ -340,7 +396,7 @@ fn configure_early<'cx>(
 
     match krate.path {
         Some(SourcePath::Regular(path)) => cmd.arg(path),
-        Some(path @ SourcePath::Stdin) => {
+        Some(path @ (SourcePath::Virtual { .. } | SourcePath::Stdin)) => {
             if let Some(file) = cx.map().get(path) {
                 cmd.feed(file.contents);
             }
@@ -432,6 +488,48 @@ fn configure_early<'cx>(
     Ok(())
 }
 
+/// Make the engine colorize its output like we do if its output gets buffered.
+///
+/// Otherwise, it would write to a pipe and thus not colorize anything.
+fn configure_color(
+    cmd: &mut Command<'_>,
+    engine: Engine,
+    opts: &Options<'_>,
+    cx: Context<'_>,
+) -> Result<()> {
+    if !jobs::buffered()
+        || !paint::colorize(&io::stderr())
+        // Respect the user's choice.
+        || cmd.get_args().any(|arg| arg.as_encoded_bytes().starts_with(b"--color"))
+    {
+        return Ok(());
+    }
+
+    let supported = match engine {
+        // 1.0.0, possibly earlier
+        Engine::Rustc => true,
+        // This bound is conservative. We don't want to fail just because of colors.
+        Engine::Rustdoc => select_by_version(
+            &[Candidate {
+                key: (),
+                version: Some(V!(1, 50, 0)),
+                date: Some(D!(2020, 11, 19)),
+                stable: true,
+            }],
+            engine,
+            "--color",
+            opts,
+            cx,
+        )?
+        .is_ok(),
+    };
+    if supported {
+        cmd.arg("--color=always");
+    }
+
+    Ok(())
+}
+
 /// Configure the engine invocation with options that it doesn't need early
 /// (i.e., during certain print requests).
 fn configure_late(
@@ -449,15 +547,31 @@ fn configure_late(
     // in order to find `#![crate_name]` (ruled by T-lang).
 
     // FIXME: Only add this when requested by `operate`.
-    cmd.arg("-Lcrate=.");
+    cmd.arg(search_path_flag("crate", opts.b_opts.search_path()));
+    for path in &opts.b_opts.dependency_search_paths {
+        cmd.arg(search_path_flag("dependency", path));
+    }
 
-    if !opts.b_opts.extern_crates.is_empty() {
-        for ext in &opts.b_opts.extern_crates {
-            cmd.arg("--extern");
-            cmd.arg(ext);
+    if let Some(out_dir) = &opts.b_opts.out_dir {
+        cmd.arg("--out-dir");
+        match engine {
+            Engine::Rustc => cmd.arg(out_dir),
+            Engine::Rustdoc => cmd.arg(out_dir.join("doc")),
         }
     }
 
+    for ext in &opts.b_opts.extern_crates {
+        cmd.arg("--extern");
+        cmd.arg(render_extern_crate(ext));
+    }
+
+    // Rustdoc doesn't link anything (doctests aside), so don't bother.
+    if let Engine::Rustc = engine
+        && opts.b_opts.prefer_dynamic
+    {
+        cmd.arg("-Cprefer-dynamic");
+    }
+
     // The crate name can't depend on any cfgs, it's fine to skip this.
     // In the past this wasn't really the case but since 1.83
     // `#[cfg_attr(…, crate_name = "…")]` is a hard error (if the spec holds).
@@ -467,6 +581,41 @@ fn configure_late(
         cmd.arg(cfg);
     }
 
+    // We don't bother with the older syntax (`names(…)`, `values(…)`). Since the specs get passed
+    // implicitly (and don't affect the build otherwise), we omit them if they're unsupported.
+    if !opts.b_opts.check_cfgs.is_empty()
+        && let Ok(stable) = select_by_version(
+            &[
+                // <rust-lang/rust#123501>
+                Candidate {
+                    key: true,
+                    version: Some(V!(1, 80, 0)),
+                    date: Some(D!(2024, 05, 04)),
+                    stable: true,
+                },
+                // <rust-lang/rust#111072>
+                Candidate {
+                    key: false,
+                    version: Some(V!(1, 75, 0)),
+                    date: Some(D!(2023, 10, 14)),
+                    stable: false,
+                },
+            ],
+            engine,
+            "--check-cfg",
+            opts,
+            cx,
+        )?
+    {
+        if !stable {
+            cmd.arg("-Zunstable-options");
+        }
+        for spec in &opts.b_opts.check_cfgs {
+            cmd.arg("--check-cfg");
+            cmd.arg(spec);
+        }
+    }
+
     for feature in &opts.b_opts.unstable_features {
         register_crate_attr(cmd, format_args!("feature({feature})"));
     }
@@ -505,10 +654,22 @@ fn configure_late(
         cmd.arg("-Zdeduplicate-diagnostics=no");
     }
 
+    if opts.b_opts.json_diagnostics {
+        cmd.arg("--error-format=json");
+    }
+
+    if opts.b_opts.ui_testing {
+        cmd.arg("-Zui-testing");
+    }
+
     if opts.b_opts.no_backtrace {
         cmd.env("RUST_BACKTRACE", Some("0"));
     }
 
+    if opts.b_opts.no_ice_reports {
+        cmd.env("RUSTC_ICE", Some("0"));
+    }
+
     // The logging output would just get thrown away.
     if let Some(filter) = &opts.b_opts.log {
         cmd.env(engine.logging_env_var(), Some(filter));
@@ -517,6 +678,36 @@ fn configure_late(
     Ok(())
 }
 
+/// Render the argument of `--extern` (`[MODIFIERS:]NAME[=PATH]`).
+fn render_extern_crate(ext: &ExternCrate) -> OsString {
+    let mut modifiers = Vec::new();
+    if ext.modifiers.private {
+        modifiers.push("priv");
+    }
+    if ext.modifiers.no_prelude {
+        modifiers.push("noprelude");
+    }
+
+    let mut arg = OsString::new();
+    if !modifiers.is_empty() {
+        arg.push(modifiers.join(","));
+        arg.push(":");
+    }
+    arg.push(&ext.name);
+    if let Some(path) = &ext.path {
+        arg.push("=");
+        arg.push(path);
+    }
+    arg
+}
+
+/// Render a search path flag (`-LKIND=PATH`).
+fn search_path_flag(kind: &str, path: &Path) -> OsString {
+    let mut flag = OsString::from(format!("-L{kind}="));
+    flag.push(path);
+    flag
+}
+
 fn configure_v_opts(cmd: &mut Command<'_>, v_opts: &VerbatimOptions<'_>) {
     v_opts.variables.iter().for_each(|&(key, value)| cmd.env(key, value));
     // FIXME: This comment is out of context now
@@ -875,14 +1066,249 @@ fn configure_forced_identity(
     Ok(())
 }
 
+/// The paths the dynamic linker needs to search if the crates were linked dynamically.
+pub(crate) fn library_paths(opts: &Options<'_>, cx: Context<'_>) -> Result<Vec<PathBuf>> {
+    if !opts.b_opts.prefer_dynamic {
+        return Ok(Vec::new());
+    }
+
+    let libdir = target_libdir(cx).map_err(QueryTargetLibdirError::emit)?;
+    // The dynamic standard library and the dynamic dependencies respectively.
+    Ok(vec![libdir, opts.b_opts.search_path().to_owned()])
+}
+
+fn target_libdir(cx: Context<'_>) -> Result<PathBuf, QueryTargetLibdirError> {
+    crate::context::invoke!(cx.query_target_libdir(()))
+}
+
+/// Don't call this directly! Use [`target_libdir`] instead.
+fn query_target_libdir((): (), cx: Context<'_>) -> Result<PathBuf, QueryTargetLibdirError> {
+    use QueryTargetLibdirError as Error;
+
+    let mut cmd = Engine::Rustc
+        .command(cx, AddRuntimeLibraryPath::IfAncientVersion)
+        .map_err(Error::EnginePathError)?;
+
+    cmd.arg("--print=target-libdir");
+
+    let mut output = cmd.execute_capturing_output().map_err(|_| Error::RustcSpawnFailure)?;
+
+    if !output.status.success() {
+        return Err(Error::RustcFailure);
+    }
+
+    output.stdout.truncate_ascii_end();
+    let path = String::from_utf8(output.stdout).map_err(|_| Error::InvalidUtf8)?;
+
+    Ok(PathBuf::from(path))
+}
+
+#[derive(Clone)]
+pub(crate) enum QueryTargetLibdirError {
+    EnginePathError(QueryEnginePathError),
+    RustcSpawnFailure,
+    RustcFailure,
+    InvalidUtf8,
+}
+
+impl QueryTargetLibdirError {
+    fn emit(self) -> EmittedError {
+        let error = error(fmt!("failed to obtain the target library directory from rustc"));
+
+        match self {
+            Self::EnginePathError(error) => return error.emit(Engine::Rustc),
+            Self::RustcSpawnFailure => error.note(fmt!("failed to execute `rustc`")),
+            Self::RustcFailure => error.note(fmt!("`rustc` exited unsuccessfully")),
+            Self::InvalidUtf8 => error.note(fmt!("`rustc` provided a path that isn't valid UTF-8")),
+        }
+        .done()
+    }
+}
+
+/// Obtain information about the given target or about the default one if absent.
+pub(crate) fn target_info(target: Option<&str>, cx: Context<'_>) -> Result<TargetInfo> {
+    Ok(match target {
+        None => crate::context::invoke!(cx.query_target_info(())),
+        // FIXME: Cache this, too, once queries support keys that aren't `SmallKey`s.
+        Some(target) => compute_target_info(Some(target), cx),
+    }
+    .map_err(QueryTargetInfoError::emit)?)
+}
+
+/// Don't call this directly! Use [`target_info`] instead.
+fn query_target_info((): (), cx: Context<'_>) -> Result<TargetInfo, QueryTargetInfoError> {
+    compute_target_info(None, cx)
+}
+
+fn compute_target_info(
+    target: Option<&str>,
+    cx: Context<'_>,
+) -> Result<TargetInfo, QueryTargetInfoError> {
+    use QueryTargetInfoError as Error;
+
+    let query = |argument| {
+        let mut cmd = Engine::Rustc
+            .command(cx, AddRuntimeLibraryPath::IfAncientVersion)
+            .map_err(Error::EnginePathError)?;
+        cmd.arg(argument);
+        if let Some(target) = target {
+            cmd.arg("--target");
+            cmd.arg(target);
+        }
+
+        let output = cmd.execute_capturing_output().map_err(|_| Error::RustcSpawnFailure)?;
+        if !output.status.success() {
+            return Err(Error::RustcFailure);
+        }
+        String::from_utf8(output.stdout).map_err(|_| Error::Malformed)
+    };
+
+    let triple = match target {
+        // Rustc identifies custom targets by the file stem of their specification.
+        Some(target) => match target.strip_suffix(".json") {
+            Some(path) => path.rsplit(['/', std::path::MAIN_SEPARATOR]).next().unwrap_or(path),
+            None => target,
+        }
+        .to_owned(),
+        None => query("-vV")?
+            .lines()
+            .find_map(|line| line.strip_prefix("host: "))
+            .ok_or(Error::Malformed)?
+            .to_owned(),
+    };
+
+    TargetInfo::parse(triple, &query("--print=cfg")?).ok_or(Error::Malformed)
+}
+
+/// The target specified via `--target` among the given verbatim arguments if there is one.
+pub(crate) fn explicit_target<'a>(arguments: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let mut arguments = arguments.into_iter();
+    let mut target = None;
+
+    while let Some(argument) = arguments.next() {
+        if argument == "--target" {
+            target = arguments.next();
+        } else if let Some(value) = argument.strip_prefix("--target=") {
+            target = Some(value);
+        }
+    }
+
+    target
+}
+
+/// The properties of a target.
+#[derive(Clone)]
+pub(crate) struct TargetInfo {
+    pub(crate) triple: String,
+    cfgs: Vec<(String, Option<String>)>,
+}
+
+impl TargetInfo {
+    /// Create the target info from the output of `--print=cfg`.
+    pub(crate) fn parse(triple: String, cfgs: &str) -> Option<Self> {
+        let cfgs = cfgs
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((name, value)) => {
+                    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+                    Some((name.to_owned(), Some(value.to_owned())))
+                }
+                None => Some((line.to_owned(), None)),
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self { triple, cfgs })
+    }
+
+    /// The values of the given cfg, e.g., `unix` and `wasm` for `target_family`.
+    pub(crate) fn cfg(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.cfgs
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .filter_map(|(_, value)| value.as_deref())
+    }
+
+    /// Whether the target supports dynamic linking.
+    ///
+    /// This approximates the `dynamic-linking` property of the target specification
+    /// since the latter can only be obtained on nightly.
+    pub(crate) fn supports_dynamic_linking(&self) -> bool {
+        let has = |name, value| self.cfg(name).any(|actual| actual == value);
+        !has("target_family", "wasm") && !has("target_os", "none") && !has("target_os", "uefi")
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum QueryTargetInfoError {
+    EnginePathError(QueryEnginePathError),
+    RustcSpawnFailure,
+    RustcFailure,
+    Malformed,
+}
+
+impl QueryTargetInfoError {
+    fn emit(self) -> EmittedError {
+        let error = error(fmt!("failed to obtain information about the target from rustc"));
+
+        match self {
+            Self::EnginePathError(error) => return error.emit(Engine::Rustc),
+            Self::RustcSpawnFailure => error.note(fmt!("failed to execute `rustc`")),
+            Self::RustcFailure => error.note(fmt!("`rustc` exited unsuccessfully")),
+            Self::Malformed => error.note(fmt!("`rustc` provided malformed output")),
+        }
+        .done()
+    }
+}
+
 pub(crate) fn run(
     program: impl AsRef<OsStr>,
     v_opts: &VerbatimOptions<'_>,
+    library_paths: &[PathBuf],
     cx: Context<'_>,
-) -> io::Result<Result<(), ExitStatusError>> {
+) -> io::Result<process::ExitStatus> {
     let mut cmd = Command::new(program, cx);
+    configure_library_paths(&mut cmd, library_paths)?;
     configure_v_opts(&mut cmd, v_opts);
-    cmd.execute().map(|status| status.exit_ok())
+    cmd.execute()
+}
+
+/// Like [`run`] but capture the output of the program.
+///
+/// Unlike [`run`], this doesn't let the program read from our stdin and it disables backtraces
+/// by default since both would render the output non-deterministic.
+pub(crate) fn run_capturing_output(
+    program: impl AsRef<OsStr>,
+    v_opts: &VerbatimOptions<'_>,
+    library_paths: &[PathBuf],
+    cx: Context<'_>,
+) -> io::Result<process::Output> {
+    let mut cmd = Command::new(program, cx);
+    cmd.env("RUST_BACKTRACE", Some("0"));
+    configure_library_paths(&mut cmd, library_paths)?;
+    configure_v_opts(&mut cmd, v_opts);
+    cmd.execute_capturing_output()
+}
+
+/// The environment variable containing the search path of the dynamic linker (like in compiletest).
+pub(crate) const LIBRARY_PATH_VAR: &str = if cfg!(windows) {
+    "PATH"
+} else if cfg!(target_vendor = "apple") {
+    "DYLD_LIBRARY_PATH"
+} else {
+    "LD_LIBRARY_PATH"
+};
+
+/// Prepend the given paths to the search path of the dynamic linker.
+fn configure_library_paths(cmd: &mut Command<'_>, paths: &[PathBuf]) -> io::Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let existing = env::var_os(LIBRARY_PATH_VAR);
+    let paths = paths.iter().cloned().chain(existing.iter().flat_map(env::split_paths));
+    cmd.env(LIBRARY_PATH_VAR, Some(env::join_paths(paths).map_err(io::Error::other)?));
+
+    Ok(())
 }
 
 pub(crate) fn open(path: &Path, cx: Context<'_>) -> io::Result<()> {
@@ -907,6 +1333,7 @@ pub(crate) fn probe_identity(opts: &Options<'_>) -> Identity {
 }
 
 /// Engine-specific build options.
+#[derive(Clone)]
 pub(crate) enum EngineOptions<'a> {
     Rustc(CompileOptions),
     Rustdoc(DocOptions<'a>),
@@ -1016,6 +1443,7 @@ mod palette {
     pub(super) const ARGUMENT: AnsiColor = AnsiColor::Green;
 }
 
+#[derive(Clone)]
 #[derive_const(Default)]
 pub(crate) struct CompileOptions {
     pub(crate) check_only: bool,
@@ -1067,8 +1495,10 @@ pub(crate) enum Theme {
 #[allow(clippy::struct_excessive_bools)] // not worth to address
 pub(crate) struct BuildOptions {
     pub(crate) cfgs: Vec<String>,
+    /// The specifications of expected cfgs (`--check-cfg`).
+    pub(crate) check_cfgs: Vec<String>,
     pub(crate) unstable_features: Vec<String>,
-    pub(crate) extern_crates: Vec<String>,
+    pub(crate) extern_crates: Vec<ExternCrate>,
     pub(crate) suppress_lints: bool,
     pub(crate) internals: bool,
     pub(crate) next_solver: bool,
@@ -1076,6 +1506,33 @@ pub(crate) struct BuildOptions {
     pub(crate) no_dedupe: bool,
     pub(crate) log: Option<String>,
     pub(crate) no_backtrace: bool,
+    /// Whether to emit diagnostics in JSON (e.g., for checking them against test annotations).
+    pub(crate) json_diagnostics: bool,
+    /// Whether to render diagnostics in a normalized form suitable for UI tests (`-Zui-testing`).
+    pub(crate) ui_testing: bool,
+    /// Whether to suppress the creation of ICE report files (`rustc-ice-*.txt`).
+    pub(crate) no_ice_reports: bool,
+    /// Whether to link dependencies dynamically (`-Cprefer-dynamic`) if possible.
+    pub(crate) prefer_dynamic: bool,
+    /// The directory to place the artifacts in instead of the working directory.
+    pub(crate) out_dir: Option<PathBuf>,
+    /// The directories to search for the dependencies of dependencies in (`-Ldependency`).
+    pub(crate) dependency_search_paths: Vec<PathBuf>,
+}
+
+impl BuildOptions {
+    /// The path to the given artifact relative to the working directory.
+    pub(crate) fn artifact_path(&self, artifact: impl AsRef<Path>) -> PathBuf {
+        match &self.out_dir {
+            Some(out_dir) => out_dir.join(artifact),
+            None => artifact.as_ref().to_owned(),
+        }
+    }
+
+    /// The directory to search for dependencies in, namely the output directory.
+    pub(crate) fn search_path(&self) -> &Path {
+        self.out_dir.as_deref().unwrap_or(Path::new("."))
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -2,6 +2,7 @@ use super::palette;
 use crate::{
     context::Context,
     diagnostic::{self, debug},
+    utility::jobs,
 };
 use anstyle::Effects;
 use std::{
@@ -41,6 +42,10 @@ impl<'cx> Command<'cx> {
         debug_assert!(previous.is_none());
     }
 
+    pub(super) fn get_args(&self) -> impl Iterator<Item = &OsStr> {
+        self.raw.get_args()
+    }
+
     fn spawn_with_feed(&mut self, feed: &str) -> io::Result<process::Child> {
         self.raw.stdin(process::Stdio::piped());
         let mut child = self.raw.spawn()?;
@@ -50,6 +55,39 @@ impl<'cx> Command<'cx> {
 
     pub(crate) fn execute_capturing_output(mut self) -> io::Result<process::Output> {
         self.log();
+        self.output()
+    }
+
+    pub(crate) fn execute(self) -> io::Result<process::ExitStatus> {
+        self.execute_keeping_stderr().map(|(status, _)| status)
+    }
+
+    /// Like [`Self::execute`] but also return the stderr output if it had to be buffered.
+    pub(crate) fn execute_keeping_stderr(
+        mut self,
+    ) -> io::Result<(process::ExitStatus, Option<Vec<u8>>)> {
+        self.log();
+
+        // The output of the command would otherwise interleave with the output of other jobs.
+        if jobs::buffered() {
+            // Unlike `execute_capturing_output`, let the program read from our stdin.
+            if self.feed.is_none() {
+                self.raw.stdin(process::Stdio::inherit());
+            }
+            let output = self.output()?;
+            jobs::write(jobs::Stream::Stdout, &output.stdout)?;
+            jobs::write(jobs::Stream::Stderr, &output.stderr)?;
+            return Ok((output.status, Some(output.stderr)));
+        }
+
+        let status = match self.feed {
+            Some(feed) => self.spawn_with_feed(feed)?.wait(),
+            None => self.raw.status(),
+        }?;
+        Ok((status, None))
+    }
+
+    fn output(&mut self) -> io::Result<process::Output> {
         match self.feed {
             Some(feed) => {
                 self.raw.stdout(process::Stdio::piped());
@@ -60,14 +98,6 @@ impl<'cx> Command<'cx> {
         }
     }
 
-    pub(crate) fn execute(mut self) -> io::Result<process::ExitStatus> {
-        self.log();
-        match self.feed {
-            Some(feed) => self.spawn_with_feed(feed)?.wait(),
-            None => self.raw.status(),
-        }
-    }
-
     fn log(&self) {
         if self.cx.opts().dbg_opts.verbose {
             #[rustfmt::skip]
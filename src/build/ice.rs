@@ -0,0 +1,130 @@
+//! The detection of internal compiler errors (ICEs).
+
+use crate::diagnostic::{Diagnostic, fmt};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::SystemTime,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The exit code of rust{,do}c after it panicked.
+const EXIT_CODE: i32 = 101;
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) struct Ice {
+    message: Option<String>,
+    query_stack: Vec<String>,
+    report: Option<PathBuf>,
+}
+
+impl Ice {
+    /// Detect an ICE given the exit status and the captured stderr output of the engine.
+    pub(crate) fn detect(status: ExitStatus, stderr: &str) -> Option<Self> {
+        if status.code() != Some(EXIT_CODE) || !has_banner(stderr) {
+            return None;
+        }
+
+        let mut ice = parse(stderr);
+        if ice.message.is_none()
+            && let Some(report) = &ice.report
+            && let Ok(contents) = fs::read_to_string(report)
+        {
+            ice.message = parse(&contents).message;
+        }
+        Some(ice)
+    }
+
+    /// Detect an ICE given the exit status of an engine whose output wasn't captured.
+    ///
+    /// We can't inspect the stderr output, so we fall back to the ICE report written to the
+    /// current directory (if any) since the given point in time.
+    pub(crate) fn recover(status: ExitStatus, since: SystemTime) -> Option<Self> {
+        if status.code() != Some(EXIT_CODE) {
+            return None;
+        }
+
+        let Some(report) = find_report(since) else {
+            return Some(Self { message: None, query_stack: Vec::new(), report: None });
+        };
+
+        let contents = fs::read_to_string(&report).unwrap_or_default();
+        Some(Self { report: Some(report), ..parse(&contents) })
+    }
+
+    pub(crate) fn annotate(&self, it: Diagnostic) -> Diagnostic {
+        let it = match &self.message {
+            Some(message) => it.note(fmt!("panic message: {message}")),
+            None => it,
+        };
+        let it = self.query_stack.iter().fold(it, |it, query| it.note(fmt!("in query {query}")));
+        match &self.report {
+            Some(report) => it.note(fmt!("report written to `{}`", report.display())),
+            None => it,
+        }
+    }
+}
+
+fn has_banner(stderr: &str) -> bool {
+    stderr.contains("internal compiler error")
+        || stderr.contains("the compiler unexpectedly panicked")
+}
+
+fn parse(text: &str) -> Ice {
+    let mut lines = text.lines();
+    let mut message = None;
+    let mut panic_message = None;
+    let mut query_stack = Vec::new();
+    let mut report = None;
+
+    while let Some(line) = lines.next() {
+        // The banner may contain an error code, e.g., `internal compiler error[E0308]: …`.
+        if let Some(rest) = line.strip_prefix("error: internal compiler error")
+            && let Some((_, rest)) = rest.split_once(": ")
+        {
+            message.get_or_insert_with(|| rest.trim().to_owned());
+        } else if line.starts_with("thread '") && line.contains(" panicked at ") {
+            // The payload of `bug!()`s is opaque, the actual message is contained in the banner.
+            if let Some(next) = lines.next()
+                && next != "Box<dyn Any>"
+                && !next.is_empty()
+            {
+                panic_message.get_or_insert_with(|| next.trim().to_owned());
+            }
+        } else if line == "query stack during panic:" {
+            query_stack.extend(
+                lines
+                    .by_ref()
+                    .take_while(|&line| line != "end of query stack")
+                    .map(|line| line.split_once(' ').map_or(line, |(_, query)| query).to_owned()),
+            );
+        } else if let Some(rest) =
+            line.split_once("please attach the file at `").map(|(_, rest)| rest)
+            && let Some((path, _)) = rest.split_once('`')
+        {
+            report = Some(PathBuf::from(path));
+        }
+    }
+
+    Ice { message: message.or(panic_message), query_stack, report }
+}
+
+/// Find the most recent ICE report in the current directory written since the given point in time.
+fn find_report(since: SystemTime) -> Option<PathBuf> {
+    fs::read_dir(env::current_dir().ok()?)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| is_report(&entry.path()))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|&(modified, _)| modified >= since)
+        .max_by_key(|&(modified, _)| modified)
+        .map(|(_, path)| path)
+}
+
+fn is_report(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "txt")
+        && path.file_name().is_some_and(|name| name.as_encoded_bytes().starts_with(b"rustc-ice-"))
+}
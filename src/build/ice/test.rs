@@ -0,0 +1,66 @@
+use super::*;
+
+#[test]
+fn bug() {
+    let stderr = "\
+error: internal compiler error: compiler/rustc_middle/src/ty/mod.rs:12:34: unexpected type
+
+thread 'rustc' (1234) panicked at compiler/rustc_middle/src/util/bug.rs:36:26:
+Box<dyn Any>
+stack backtrace:
+   0: std::panicking::begin_panic::<rustc_errors::ExplicitBug>
+
+note: please attach the file at `/tmp/rustc-ice-2025-01-01T00_00_00-1234.txt` to your bug report
+
+query stack during panic:
+#0 [typeck] type-checking `main`
+#1 [analysis] running analysis passes on this crate
+end of query stack
+";
+
+    assert_eq!(
+        parse(stderr),
+        Ice {
+            message: Some("compiler/rustc_middle/src/ty/mod.rs:12:34: unexpected type".into()),
+            query_stack: vec![
+                "[typeck] type-checking `main`".into(),
+                "[analysis] running analysis passes on this crate".into(),
+            ],
+            report: Some("/tmp/rustc-ice-2025-01-01T00_00_00-1234.txt".into()),
+        }
+    );
+}
+
+#[test]
+fn panic() {
+    let stderr = "\
+thread 'rustc' panicked at compiler/rustc_resolve/src/lib.rs:1:2:
+called `Option::unwrap()` on a `None` value
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+error: the compiler unexpectedly panicked. this is a bug.
+";
+
+    assert!(has_banner(stderr));
+    assert_eq!(
+        parse(stderr),
+        Ice {
+            message: Some("called `Option::unwrap()` on a `None` value".into()),
+            query_stack: Vec::new(),
+            report: None,
+        }
+    );
+}
+
+#[test]
+fn error_code() {
+    assert_eq!(
+        parse("error: internal compiler error[E0308]: mismatched types\n").message.as_deref(),
+        Some("mismatched types")
+    );
+}
+
+#[test]
+fn no_banner() {
+    assert!(!has_banner("error[E0308]: mismatched types\n"));
+}
@@ -0,0 +1,21 @@
+use super::render_extern_crate;
+use crate::data::{ExternCrate, ExternModifiers};
+
+#[test]
+fn render_extern_crates() {
+    let krate = |path: Option<&str>, private, no_prelude| ExternCrate {
+        name: "dep".into(),
+        path: path.map(Into::into),
+        modifiers: ExternModifiers { private, no_prelude },
+    };
+
+    for (krate, expected) in [
+        (krate(None, false, false), "dep"),
+        (krate(Some("out/libdep.rlib"), false, false), "dep=out/libdep.rlib"),
+        (krate(Some("out/libdep.rlib"), true, false), "priv:dep=out/libdep.rlib"),
+        (krate(None, false, true), "noprelude:dep"),
+        (krate(Some("out/libdep.rlib"), true, true), "priv,noprelude:dep=out/libdep.rlib"),
+    ] {
+        assert_eq!(render_extern_crate(&krate), expected);
+    }
+}
@@ -0,0 +1,694 @@
+//! Checking the output of rust{,do}c against compiletest-style test expectations.
+
+use crate::{
+    build::Ice,
+    context::Context,
+    diagnostic::{self, error, fmt, info},
+    directive::{
+        DocCheck, HtmlDocCkDirective, InstantiatedDirectives, JsonDocCkDirective, Mode, Outcome,
+        Phase, Rustfix, Scope,
+    },
+    error::{Error, Result},
+    operate::Bless,
+    source::{LocalSpan, SourceFile, SourcePath, Span, Spanned, mask},
+    utility::jobs,
+};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{self, ExitStatus},
+};
+
+mod annotation;
+pub(crate) mod condition;
+mod diff;
+mod htmldocck;
+mod json;
+mod jsondocck;
+mod normalize;
+mod pattern;
+mod rustfix;
+#[cfg(test)]
+mod test;
+
+/// A checker for a single test, i.e., a single revision of a test file.
+///
+/// It keeps track of the number of failed checks; each check emits its own diagnostics.
+/// When blessing, checks never fail but update the expectation files instead.
+pub(crate) struct Checker<'a> {
+    path: &'a Path,
+    absolute_path: PathBuf,
+    revision: Option<&'a str>,
+    bless: Bless,
+    mode: Option<Spanned<Mode>>,
+    failure_status: Option<Spanned<i32>>,
+    check_run_results: bool,
+    should_ice: Option<Span>,
+    rustfix: Option<Rustfix>,
+    /// The suggestions found in the output of the compiler.
+    suggestions: Vec<json::Suggestion>,
+    /// The bodies of the inline crates which get compiled separately.
+    inline_bodies: Vec<Span>,
+    scope: Scope,
+    normalizer: normalize::Normalizer,
+    matcher: pattern::Matcher,
+    failures: usize,
+}
+
+impl<'a> Checker<'a> {
+    #[allow(clippy::too_many_arguments)] // not worth to address
+    pub(crate) fn new(
+        path: SourcePath<'a>,
+        revision: Option<&'a str>,
+        bless: Bless,
+        directives: &InstantiatedDirectives<'_>,
+        scope: Scope,
+        // The directory the artifacts are placed in if it's not the working directory.
+        out_dir: Option<&Path>,
+        // Whether the engine normalizes the gutter of diagnostics itself (`-Zui-testing`).
+        ui_testing: bool,
+        cx: Context<'_>,
+    ) -> Result<Self> {
+        let SourcePath::Regular(path) = path else {
+            return Err(error(fmt!("cannot check the output of a crate read from STDIN"))
+                .note(fmt!("its test expectations would have to be stored next to the source file"))
+                .done()
+                .into());
+        };
+
+        let absolute_path = std::path::absolute(path)?;
+        let build_dir = match out_dir {
+            Some(out_dir) => std::path::absolute(out_dir)?,
+            None => env::current_dir()?,
+        };
+        let normalizer = normalize::Normalizer::new(
+            &absolute_path,
+            &build_dir,
+            &directives.normalizations,
+            ui_testing,
+            cx,
+        )?;
+        let matcher = pattern::Matcher::new(&directives.patterns, cx)?;
+
+        Ok(Self {
+            path,
+            absolute_path,
+            revision,
+            bless,
+            mode: directives.mode,
+            failure_status: directives.failure_status,
+            check_run_results: directives.check_run_results,
+            should_ice: directives.should_ice,
+            rustfix: directives.rustfix,
+            suggestions: Vec::new(),
+            inline_bodies: directives.inline_crates.iter().map(|krate| krate.body).collect(),
+            scope,
+            normalizer,
+            matcher,
+            failures: 0,
+        })
+    }
+
+    /// The absolute path to the test file.
+    ///
+    /// Like compiletest, we pass absolute paths to the engine since they're observable in the
+    /// output and since normalization relies on them (`$DIR`).
+    pub(crate) fn absolute_path(&self) -> &Path {
+        &self.absolute_path
+    }
+
+    /// Check the diagnostics found in the JSON output against the inline annotations.
+    ///
+    /// Returns the rendered diagnostics which are to be compared against the stderr expectation.
+    pub(crate) fn check_diagnostics(&mut self, stderr: &str, cx: Context<'_>) -> Result<String> {
+        let file = cx.map().read(Spanned::sham(SourcePath::Regular(self.path)), cx)?;
+        let json::Output { rendered, diagnostics, suggestions } =
+            json::process(stderr, &self.absolute_path);
+        self.suggestions = suggestions;
+        // The annotations inside of inline crates don't apply to the principal crate.
+        let contents = mask(file.contents, |index| self.is_inline(index, file));
+        let (annotations, errors) = annotation::parse(&contents, self.revision);
+
+        for error in errors {
+            self.failures += 1;
+            let (message, span) = match error {
+                annotation::Error::DanglingFollowUp(span) => {
+                    ("follow-up annotation without a preceding annotation", span)
+                }
+                annotation::Error::LineOutOfBounds(span) => {
+                    ("annotation refers to a line before the start of the file", span)
+                }
+                annotation::Error::InvalidKind(span) => ("annotation without a valid kind", span),
+            };
+            let it = self::error(fmt!("{message}")).highlight(span.global(file), cx);
+            match error {
+                annotation::Error::InvalidKind(_) => {
+                    it.note(fmt!("expected one of `ERROR`, `WARN`, `NOTE`, `HELP` or `SUGGESTION`"))
+                }
+                _ => it,
+            }
+            .done();
+        }
+
+        // Like in compiletest, tests that solely rely on error patterns don't need to be annotated.
+        if annotations.is_empty() && self.matcher.expects_errors() {
+            return Ok(rendered);
+        }
+
+        // Like in compiletest, notes, helps and suggestions only need to be annotated
+        // if the test file expects at least one diagnostic of the same kind.
+        let expects = |kind| annotations.iter().any(|annotation| annotation.kind == kind);
+        let (notes, helps, suggestions) =
+            (expects(Kind::Note), expects(Kind::Help), expects(Kind::Suggestion));
+
+        let mut found = vec![false; annotations.len()];
+
+        for diagnostic in &diagnostics {
+            let index = (0..annotations.len()).find(|&index| {
+                let annotation = annotations[index];
+                !found[index]
+                    && annotation.line == diagnostic.line
+                    && annotation.kind == diagnostic.kind
+                    && diagnostic.message.contains(annotation.message)
+            });
+
+            if let Some(index) = index {
+                found[index] = true;
+                continue;
+            }
+
+            let required = match diagnostic.kind {
+                Kind::Error | Kind::Warning => true,
+                Kind::Note => notes,
+                Kind::Help => helps,
+                Kind::Suggestion => suggestions,
+            };
+            if !required {
+                continue;
+            }
+
+            self.failures += 1;
+            let it = error(fmt!("unexpected {}: {}", diagnostic.kind.name(), diagnostic.message));
+            match diagnostic.span {
+                Some(span) => it.highlight(first_line(span, file).global(file), cx),
+                None => it.note(fmt!("it doesn't point into the test file")),
+            }
+            .done();
+        }
+
+        for (annotation, _) in annotations.iter().zip(found).filter(|&(_, found)| !found) {
+            self.failures += 1;
+            error(fmt!("expected {} not found: {}", annotation.kind.name(), annotation.message))
+                .highlight(annotation.span.global(file), cx)
+                .done();
+        }
+
+        Ok(rendered)
+    }
+
+    /// Check the output of the compiler against the error patterns and the forbidden output.
+    ///
+    /// Like in compiletest, the patterns of run tests apply to the output of the binary instead.
+    pub(crate) fn check_output(&mut self, stdout: &str, stderr: &str, cx: Context<'_>) {
+        if !self.runs() {
+            self.failures += self.matcher.check(&format!("{stdout}{stderr}"), "compiler", cx);
+        }
+    }
+
+    /// Apply the suggestions to the test file and compare the result against the `.fixed` file.
+    ///
+    /// Returns the path to the fixed file if it's meant to be compiled. Like in compiletest,
+    /// the `.fixed` file must not exist unless the test file declares `run-rustfix`.
+    pub(crate) fn check_fix(&mut self, cx: Context<'_>) -> Result<Option<PathBuf>> {
+        let fixed = match self.rustfix {
+            Some(rustfix) => {
+                let file = cx.map().read(Spanned::sham(SourcePath::Regular(self.path)), cx)?;
+                // Strip the inline crates since they get compiled separately. We keep the line
+                // breaks, so the lines of the fixed file correspond to the ones of the test file.
+                let bodies = self.inline_bodies.iter().map(|body| {
+                    let range = body.local(file).range();
+                    let lines = file.contents[range.clone()].matches('\n').count();
+                    json::Replacement { range, text: "\n".repeat(lines), machine_applicable: true }
+                });
+                self.suggestions.push(json::Suggestion { solutions: vec![bodies.collect()] });
+                match rustfix::apply(file.contents, &self.suggestions, rustfix) {
+                    Ok(fixed) => fixed,
+                    Err(rustfix::Conflict(range)) => {
+                        self.failures += 1;
+                        let span = LocalSpan::new(
+                            range.start.try_into().unwrap(),
+                            range.end.try_into().unwrap(),
+                        );
+                        error(fmt!("failed to apply the suggestions"))
+                            .highlight(first_line(span, file).global(file), cx)
+                            .note(fmt!("this suggestion overlaps with another one"))
+                            .done();
+                        return Ok(None);
+                    }
+                }
+            }
+            None => String::new(),
+        };
+
+        let failures = self.failures;
+        self.compare_against(Expectation::Fixed, &fixed)?;
+
+        Ok((self.rustfix.is_some() && self.failures == failures)
+            .then(|| self.expectation_path(Expectation::Fixed)))
+    }
+
+    /// Whether the given byte position of the test file lies inside of an inline crate.
+    fn is_inline(&self, index: usize, file: SourceFile<'_>) -> bool {
+        self.inline_bodies.iter().any(|body| body.local(file).range().contains(&index))
+    }
+
+    /// Check that the fixed file compiled successfully.
+    pub(crate) fn check_fixed_compilation(&mut self, output: &process::Output) -> Result<()> {
+        if output.status.success() {
+            return Ok(());
+        }
+
+        self.failures += 1;
+        error(fmt!("the fixed source code failed to compile"))
+            .note(fmt!("{}", output.status))
+            .done();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        jobs::write(
+            jobs::Stream::Stderr,
+            json::process(&stderr, Path::new("")).rendered.as_bytes(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Check the exit status of the compiler against the pass/fail mode.
+    ///
+    /// An internal compiler error is only ever acceptable if the test file declares `should-ice`.
+    /// Returns whether the compilation was successful.
+    pub(crate) fn check_compilation(
+        &mut self,
+        status: ExitStatus,
+        stderr: &str,
+        cx: Context<'_>,
+    ) -> bool {
+        match (Ice::detect(status, stderr), self.should_ice) {
+            (Some(_), Some(_)) => return false,
+            (Some(ice), None) => {
+                self.failures += 1;
+                ice.annotate(error(fmt!("the test caused an internal compiler error")))
+                    .help(fmt!("if this is expected, consider adding `//@ should-ice`"))
+                    .done();
+                return false;
+            }
+            (None, Some(span)) => {
+                self.failures += 1;
+                error(fmt!("the test was expected to cause an internal compiler error"))
+                    .highlight(span, cx)
+                    .note(fmt!("{status}"))
+                    .done();
+                return false;
+            }
+            (None, None) => {}
+        }
+
+        let outcome = match self.mode.map(|mode| mode.bare) {
+            // The binary has to be built successfully before it can fail.
+            Some(Mode { phase: Phase::Run, .. }) => Outcome::Pass,
+            Some(mode) => mode.outcome,
+            // Like in compiletest, ui tests are expected to fail by default unlike rustdoc tests.
+            None => match self.scope {
+                Scope::Base => Outcome::Fail,
+                Scope::HtmlDocCk | Scope::JsonDocCk => Outcome::Pass,
+            },
+        };
+
+        match (outcome, status.success()) {
+            (Outcome::Pass, true) => {}
+            (Outcome::Fail, false) => self.check_failure_status(status, cx),
+            (Outcome::Pass, false) => {
+                self.failures += 1;
+                let it = error(fmt!("the test failed to compile but it was expected to pass"));
+                match self.mode {
+                    Some(mode) => it.highlight(mode.span, cx),
+                    None => it,
+                }
+                .note(fmt!("{status}"))
+                .done();
+            }
+            (Outcome::Fail, true) => {
+                self.failures += 1;
+                let it = error(fmt!("the test compiled successfully but it was expected to fail"));
+                match self.mode {
+                    Some(mode) => it.highlight(mode.span, cx),
+                    None => it
+                        .note(fmt!("ui tests are expected to fail to compile by default"))
+                        .help(fmt!("consider adding a pass mode like `//@ check-pass`")),
+                }
+                .done();
+            }
+        }
+
+        status.success()
+    }
+
+    /// Check the run of the built binary against the pass/fail mode and against the run results.
+    ///
+    /// The latter only get checked if the test file requests it via `check-run-results`.
+    pub(crate) fn check_execution(
+        &mut self,
+        output: &process::Output,
+        cx: Context<'_>,
+    ) -> Result<()> {
+        let status = output.status;
+        let outcome = self.mode.map_or(Outcome::Pass, |mode| mode.bare.outcome);
+        let failures = self.failures;
+
+        let message = match (outcome, status.success()) {
+            (Outcome::Pass, true) => None,
+            (Outcome::Fail, false) => {
+                self.check_failure_status(status, cx);
+                None
+            }
+            (Outcome::Pass, false) => Some("the test binary failed but it was expected to succeed"),
+            (Outcome::Fail, true) => Some("the test binary succeeded but it was expected to fail"),
+        };
+
+        if let Some(message) = message {
+            self.failures += 1;
+            let it = error(fmt!("{message}"));
+            match self.mode {
+                Some(mode) => it.highlight(mode.span, cx),
+                None => it,
+            }
+            .note(fmt!("{status}"))
+            .done();
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        if self.runs() {
+            self.failures += self.matcher.check(&format!("{stdout}{stderr}"), "test binary", cx);
+        }
+
+        if self.check_run_results {
+            self.compare_output(Origin::Binary, Stream::Stdout, &stdout)?;
+            self.compare_output(Origin::Binary, Stream::Stderr, &stderr)?;
+        } else if self.failures != failures {
+            // Otherwise the output of the failing binary would be lost.
+            jobs::write(jobs::Stream::Stderr, &output.stderr)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the generated HTML documentation found in the given directory against the checks.
+    pub(crate) fn check_html(
+        &mut self,
+        checks: &[DocCheck<'_, HtmlDocCkDirective>],
+        root: &Path,
+        cx: Context<'_>,
+    ) -> Result<()> {
+        let mut cache = htmldocck::Cache::new(root);
+
+        for check in checks {
+            let reasons = match htmldocck::check(check, &mut cache) {
+                htmldocck::Verdict::Pass => continue,
+                htmldocck::Verdict::Fail(reasons) => reasons,
+                htmldocck::Verdict::Snapshot { name, actual, kind } => {
+                    self.compare_against(Expectation::Snapshot(&name, kind), &actual)?;
+                    continue;
+                }
+            };
+
+            self.failures += 1;
+            let it = error(fmt!("htmldocck check failed")).highlight(check.span, cx);
+            reasons.into_iter().fold(it, |it, reason| it.note(fmt!("{reason}"))).done();
+        }
+
+        Ok(())
+    }
+
+    /// Check the JSON output of rustdoc found at the given path against the checks.
+    pub(crate) fn check_json(
+        &mut self,
+        checks: &[DocCheck<'_, JsonDocCkDirective>],
+        path: &Path,
+        cx: Context<'_>,
+    ) {
+        if checks.is_empty() {
+            return;
+        }
+
+        let mut cache = match jsondocck::Cache::load(path) {
+            Ok(cache) => cache,
+            Err(reason) => {
+                self.failures += 1;
+                error(fmt!("failed to load the JSON output of rustdoc"))
+                    .note(fmt!("{reason}"))
+                    .done();
+                return;
+            }
+        };
+
+        for check in checks {
+            if let Err(reason) = jsondocck::check(check, &mut cache) {
+                self.failures += 1;
+                error(fmt!("jsondocck check failed"))
+                    .highlight(check.span, cx)
+                    .note(fmt!("{reason}"))
+                    .done();
+            }
+        }
+    }
+
+    fn runs(&self) -> bool {
+        matches!(self.mode, Some(Spanned { bare: Mode { phase: Phase::Run, .. }, .. }))
+    }
+
+    /// Check the exit code of an expected failure against the `failure-status` if present.
+    fn check_failure_status(&mut self, status: ExitStatus, cx: Context<'_>) {
+        let Some(expected) = self.failure_status else { return };
+        if status.code() == Some(expected.bare) {
+            return;
+        }
+
+        self.failures += 1;
+        error(fmt!("the test exited with an unexpected status"))
+            .highlight(expected.span, cx)
+            .note(fmt!("expected exit status: {}", expected.bare))
+            .note(fmt!("{status}"))
+            .done();
+    }
+
+    /// Compare the given output of the compiler against the corresponding expectation file.
+    pub(crate) fn compare(&mut self, stream: Stream, actual: &str) -> Result<()> {
+        self.compare_output(Origin::Compiler, stream, actual)
+    }
+
+    /// Compare the given output against the corresponding expectation file after normalizing it.
+    fn compare_output(&mut self, origin: Origin, stream: Stream, actual: &str) -> Result<()> {
+        let actual = self.normalizer.normalize(origin, stream, actual);
+        self.compare_against(Expectation::Output(origin, stream), &actual)
+    }
+
+    /// Compare the given string against the corresponding expectation file.
+    ///
+    /// Like in compiletest, a missing expectation file is equivalent to an empty one.
+    fn compare_against(&mut self, expectation: Expectation<'_>, actual: &str) -> Result<()> {
+        let path = self.expectation_path(expectation);
+
+        let expected = match fs::read_to_string(&path) {
+            Ok(expected) => Some(expected),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(io_error("read", &path, &error)),
+        };
+
+        // Like in htmldocck, element snapshots are compared structurally.
+        // We don't bless matching snapshots lest we overwrite wildcards.
+        let mismatch = match (expectation, &expected) {
+            (Expectation::Snapshot(_, htmldocck::SnapshotKind::Element), Some(expected)) => {
+                match htmldocck::compare_snapshots(expected, actual) {
+                    Some(mismatch) => Some(mismatch),
+                    None => return Ok(()),
+                }
+            }
+            _ => None,
+        };
+
+        if let Bless::Yes = self.bless {
+            return bless(&path, expected, actual);
+        }
+
+        if mismatch.is_none() && expected.as_deref().unwrap_or_default().lines().eq(actual.lines())
+        {
+            return Ok(());
+        }
+
+        self.failures += 1;
+
+        let it = error(fmt!("the {} does not match the expectation", expectation.name()));
+        let it = match &mismatch {
+            Some(mismatch) => it.note(fmt!("{}", mismatch.reason)),
+            None => it,
+        };
+        match expected {
+            Some(_) => it.note(fmt!("expected as per `{}`", path.display())),
+            None => it.note(fmt!("expected to be empty since `{}` does not exist", path.display())),
+        }
+        .help(fmt!("rerun with `-.` (`--bless`) to update the expectation"))
+        .done();
+
+        let (expected, actual) = match &mismatch {
+            Some(mismatch) => (mismatch.expected.as_str(), mismatch.actual.as_str()),
+            None => (expected.as_deref().unwrap_or_default(), actual),
+        };
+        let mut p = diagnostic::painter();
+        diff::render(expected, actual, &mut p)?;
+
+        Ok(())
+    }
+
+    /// Conclude the test, failing if any of the previous checks failed.
+    pub(crate) fn finish(self) -> Result<()> {
+        if self.failures == 0 {
+            return Ok(());
+        }
+
+        let it = error(fmt!("test `{}` failed", self.path.display()));
+        let it = match self.revision {
+            Some(revision) => it.note(fmt!("in revision `{revision}`")),
+            None => it,
+        };
+        Err(it.done().into())
+    }
+
+    /// The path to the expectation file, mirroring compiletest's naming scheme.
+    ///
+    /// That's `test.stderr` for `test.rs` and `test.rev.stderr` if revision `rev` is active.
+    /// Like in htmldocck, snapshot `name` is stored in `test.name.html` regardless of the revision.
+    fn expectation_path(&self, expectation: Expectation<'_>) -> PathBuf {
+        let mut name = self.path.file_stem().unwrap_or_default().to_owned();
+        match expectation {
+            Expectation::Snapshot(snapshot, _) => {
+                name.push(".");
+                name.push(snapshot);
+            }
+            _ => {
+                if let Some(revision) = self.revision {
+                    name.push(".");
+                    name.push(revision);
+                }
+            }
+        }
+        name.push(".");
+        name.push(expectation.extension());
+        self.path.with_file_name(name)
+    }
+}
+
+/// Restrict the span to its first line since highlights can't span multiple lines.
+fn first_line(span: LocalSpan, file: SourceFile<'_>) -> LocalSpan {
+    let Some(source) = file.contents.get(span.range()) else { return LocalSpan::empty(0) };
+    match source.find('\n') {
+        Some(index) => LocalSpan::with_len(span.start, index.try_into().unwrap()),
+        None => span,
+    }
+}
+
+/// Update the given expectation file to match the actual output.
+///
+/// Like in compiletest, empty expectation files get removed instead of being updated.
+fn bless(path: &Path, expected: Option<String>, actual: &str) -> Result<()> {
+    let action = match expected {
+        None if actual.is_empty() => return Ok(()),
+        Some(_) if actual.is_empty() => {
+            fs::remove_file(path).map_err(|error| io_error("remove", path, &error))?;
+            "removed"
+        }
+        Some(expected) if expected == actual => return Ok(()),
+        expected => {
+            fs::write(path, actual).map_err(|error| io_error("write", path, &error))?;
+            if expected.is_some() { "updated" } else { "created" }
+        }
+    };
+
+    info(fmt!("{action} `{}`", path.display())).done();
+
+    Ok(())
+}
+
+fn io_error(action: &str, path: &Path, error: &io::Error) -> Error {
+    self::error(fmt!("failed to {action} `{}`", path.display())).note(fmt!("{error}")).done().into()
+}
+
+/// The kind of diagnostic.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+enum Kind {
+    Error,
+    Warning,
+    Note,
+    Help,
+    Suggestion,
+}
+
+impl Kind {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+            Self::Help => "help",
+            Self::Suggestion => "suggestion",
+        }
+    }
+}
+
+/// The kind of expectation file.
+#[derive(Clone, Copy)]
+enum Expectation<'a> {
+    Output(Origin, Stream),
+    /// The test file after applying the suggestions.
+    Fixed,
+    /// A part of the generated HTML documentation (htmldocck).
+    Snapshot(&'a str, htmldocck::SnapshotKind),
+}
+
+impl Expectation<'_> {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Output(Origin::Compiler, Stream::Stderr) => "stderr output",
+            Self::Output(Origin::Compiler, Stream::Stdout) => "stdout output",
+            Self::Output(Origin::Binary, Stream::Stderr) => "stderr output of the test binary",
+            Self::Output(Origin::Binary, Stream::Stdout) => "stdout output of the test binary",
+            Self::Fixed => "fixed source code",
+            Self::Snapshot(..) => "snapshot",
+        }
+    }
+
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Output(Origin::Compiler, Stream::Stderr) => "stderr",
+            Self::Output(Origin::Compiler, Stream::Stdout) => "stdout",
+            Self::Output(Origin::Binary, Stream::Stderr) => "run.stderr",
+            Self::Output(Origin::Binary, Stream::Stdout) => "run.stdout",
+            Self::Fixed => "fixed",
+            Self::Snapshot(..) => "html",
+        }
+    }
+}
+
+/// The producer of some output.
+#[derive(Clone, Copy)]
+enum Origin {
+    Compiler,
+    Binary,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+pub(crate) enum Stream {
+    Stderr,
+    Stdout,
+}
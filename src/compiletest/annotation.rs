@@ -0,0 +1,129 @@
+//! The parser of compiletest's inline diagnostic annotations (e.g., `//~^ ERROR message`).
+
+use super::Kind;
+use crate::source::LocalSpan;
+
+#[cfg(test)]
+mod test;
+
+/// Parse all annotations that apply to the given revision.
+///
+/// Annotations conditional on other revisions are skipped entirely. In particular, follow-up
+/// annotations (`//~|`) can't refer back to them, just like in compiletest.
+pub(super) fn parse<'src>(
+    source: &'src str,
+    revision: Option<&str>,
+) -> (Vec<Annotation<'src>>, Vec<Error>) {
+    let mut annotations = Vec::new();
+    let mut errors = Vec::new();
+    let mut previous_line = None;
+    let mut offset = 0;
+
+    for (index, line) in source.split('\n').enumerate() {
+        let line_number = index + 1;
+
+        if let Some(annotation) = parse_line(line, offset, line_number, previous_line, revision) {
+            match annotation {
+                Ok(annotation) => {
+                    if !annotation.follows {
+                        previous_line = annotation.line;
+                    }
+                    annotations.push(annotation);
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        offset += line.len() + 1;
+    }
+
+    (annotations, errors)
+}
+
+fn parse_line<'src>(
+    line: &'src str,
+    offset: usize,
+    line_number: usize,
+    previous_line: Option<usize>,
+    revision: Option<&str>,
+) -> Option<Result<Annotation<'src>, Error>> {
+    let (start, revisions, rest) = line.match_indices("//").find_map(|(start, _)| {
+        let rest = &line[start + 2..];
+        let (revisions, rest) = match rest.strip_prefix('[') {
+            Some(rest) => {
+                let (revisions, rest) = rest.split_once(']')?;
+                (Some(revisions), rest)
+            }
+            None => (None, rest),
+        };
+        Some((start, revisions, rest.strip_prefix('~')?))
+    })?;
+
+    if let Some(revisions) = revisions
+        && !revision.is_some_and(|revision| {
+            revisions.split(',').any(|candidate| candidate.trim() == revision)
+        })
+    {
+        return None;
+    }
+
+    let span = LocalSpan::new(
+        (offset + start).try_into().unwrap(),
+        (offset + line.trim_end().len()).try_into().unwrap(),
+    );
+
+    let (target, follows, rest) = match rest.chars().next() {
+        Some('|') => match previous_line {
+            Some(line) => (Some(line), true, &rest[1..]),
+            None => return Some(Err(Error::DanglingFollowUp(span))),
+        },
+        Some('?') => (None, false, &rest[1..]),
+        Some('^') => {
+            let count = rest.len() - rest.trim_start_matches('^').len();
+            match line_number.checked_sub(count).filter(|&line| line > 0) {
+                Some(line) => (Some(line), false, &rest[count..]),
+                None => return Some(Err(Error::LineOutOfBounds(span))),
+            }
+        }
+        Some('v') => {
+            let count = rest.len() - rest.trim_start_matches('v').len();
+            (Some(line_number + count), false, &rest[count..])
+        }
+        _ => (Some(line_number), false, rest),
+    };
+
+    let rest = rest.trim_start();
+    let kind = rest.split(|char: char| !char.is_ascii_alphabetic()).next().unwrap_or_default();
+    let message = rest[kind.len()..].strip_prefix(':').unwrap_or(&rest[kind.len()..]).trim();
+
+    let kind = match kind {
+        "ERROR" | "error" => Kind::Error,
+        "WARN" | "WARNING" | "warn" | "warning" => Kind::Warning,
+        "NOTE" | "note" => Kind::Note,
+        "HELP" | "help" => Kind::Help,
+        "SUGGESTION" | "suggestion" => Kind::Suggestion,
+        _ => return Some(Err(Error::InvalidKind(span))),
+    };
+
+    Some(Ok(Annotation { line: target, follows, kind, message, span }))
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(super) struct Annotation<'src> {
+    /// The line the expected diagnostic points to or `None` if it doesn't have a location.
+    pub(super) line: Option<usize>,
+    follows: bool,
+    pub(super) kind: Kind,
+    pub(super) message: &'src str,
+    /// The span of the annotation itself.
+    pub(super) span: LocalSpan,
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(super) enum Error {
+    DanglingFollowUp(LocalSpan),
+    LineOutOfBounds(LocalSpan),
+    InvalidKind(LocalSpan),
+}
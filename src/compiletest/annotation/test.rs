@@ -0,0 +1,86 @@
+use super::*;
+
+fn span(start: u32, end: u32) -> LocalSpan {
+    LocalSpan::new(start, end)
+}
+
+fn annotation(line: Option<usize>, kind: Kind, message: &str, span: LocalSpan) -> Annotation<'_> {
+    Annotation { line, follows: false, kind, message, span }
+}
+
+#[test]
+fn same_line() {
+    assert_eq!(
+        parse("let x: () = 0; //~ ERROR mismatched types", None),
+        (vec![annotation(Some(1), Kind::Error, "mismatched types", span(15, 41))], vec![])
+    );
+}
+
+#[test]
+fn preceding_lines() {
+    let source = "fn f() {}\n\n//~^^ WARN unused\n";
+    assert_eq!(
+        parse(source, None),
+        (vec![annotation(Some(1), Kind::Warning, "unused", span(11, 28))], vec![])
+    );
+}
+
+#[test]
+fn following_line() {
+    assert_eq!(
+        parse("//~v HELP: consider\nfn f() {}", None),
+        (vec![annotation(Some(2), Kind::Help, "consider", span(0, 19))], vec![])
+    );
+}
+
+#[test]
+fn follow_up() {
+    let source = "f(); //~ ERROR first\n//~| NOTE second\n";
+    let (annotations, errors) = parse(source, None);
+    assert_eq!(errors, vec![]);
+    assert_eq!(
+        annotations.iter().map(|annotation| (annotation.line, annotation.kind)).collect::<Vec<_>>(),
+        vec![(Some(1), Kind::Error), (Some(1), Kind::Note)]
+    );
+}
+
+#[test]
+fn dangling_follow_up() {
+    assert_eq!(
+        parse("//~| ERROR oops", None),
+        (vec![], vec![Error::DanglingFollowUp(span(0, 15))])
+    );
+}
+
+#[test]
+fn unlocated() {
+    assert_eq!(
+        parse("//~? ERROR no location", None),
+        (vec![annotation(None, Kind::Error, "no location", span(0, 22))], vec![])
+    );
+}
+
+#[test]
+fn out_of_bounds() {
+    assert_eq!(parse("//~^ ERROR oops", None), (vec![], vec![Error::LineOutOfBounds(span(0, 15))]));
+}
+
+#[test]
+fn invalid_kind() {
+    assert_eq!(parse("f(); //~ oops", None), (vec![], vec![Error::InvalidKind(span(5, 13))]));
+}
+
+#[test]
+fn conditional() {
+    let source = "f(); //[a,b]~ ERROR one\ng(); //[c]~ ERROR two\n";
+    assert_eq!(
+        parse(source, Some("b")),
+        (vec![annotation(Some(1), Kind::Error, "one", span(5, 23))], vec![])
+    );
+    assert_eq!(parse(source, None), (vec![], vec![]));
+}
+
+#[test]
+fn regular_comment() {
+    assert_eq!(parse("// ~ ERROR\n//[rev] ERROR", None), (vec![], vec![]));
+}
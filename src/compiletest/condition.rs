@@ -0,0 +1,147 @@
+//! The evaluation of `ignore-*`, `only-*` and `needs-*` conditions.
+
+use crate::{
+    build::{Engine, TargetInfo},
+    context::Context,
+    data::Channel,
+    diagnostic::{error, fmt},
+    directive::{Capability, Condition, Target},
+    error::Result,
+    source::{Span, Spanned},
+    utility::{Conjunction, ListingExt as _},
+};
+
+#[cfg(test)]
+mod test;
+
+/// The architectures that support inline assembly without any experimental features.
+const ASM_SUPPORTED_ARCHES: &[&str] = &[
+    "x86",
+    "x86_64",
+    "arm",
+    "aarch64",
+    "arm64ec",
+    "riscv32",
+    "riscv64",
+    "loongarch32",
+    "loongarch64",
+    "s390x",
+];
+
+/// The reason why a test gets ignored.
+pub(crate) struct Ignored {
+    /// The span of the condition responsible.
+    pub(crate) span: Span,
+    pub(crate) note: String,
+}
+
+/// Find the first condition under which the test gets ignored if there is one.
+///
+/// The conditions are evaluated against the given target or against the default one if absent.
+pub(crate) fn evaluate(
+    conditions: &[Spanned<Condition<'_>>],
+    target: Option<&str>,
+    cx: Context<'_>,
+) -> Result<Option<Ignored>> {
+    if conditions.is_empty() {
+        return Ok(None);
+    }
+
+    evaluate_against(conditions, &crate::build::target_info(target, cx)?, cx)
+}
+
+fn evaluate_against(
+    conditions: &[Spanned<Condition<'_>>],
+    info: &TargetInfo,
+    cx: Context<'_>,
+) -> Result<Option<Ignored>> {
+    for &Spanned { span, bare: condition } in conditions {
+        let ignored = match condition {
+            Condition::Ignore(target) => {
+                let (matches, note) = matches(target, info, cx)?;
+                matches.then_some(note)
+            }
+            Condition::Only(target) => {
+                let (matches, note) = matches(target, info, cx)?;
+                (!matches).then_some(note)
+            }
+            Condition::Needs(capability) => {
+                let (supported, note) = supports(capability, info);
+                (!supported).then_some(note)
+            }
+        };
+
+        if let Some(note) = ignored {
+            return Ok(Some(Ignored { span, note }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Whether the target matches together with a description of the actual target.
+fn matches(target: Target<'_>, info: &TargetInfo, cx: Context<'_>) -> Result<(bool, String)> {
+    let cfg = |name, description: &str| {
+        let values: Vec<_> = info.cfg(name).collect();
+        let note = match *values.as_slice() {
+            [] => format!("the target has no {description}"),
+            [value] => format!("the {description} of the target is `{value}`"),
+            ref values => format!(
+                "the {} of the target are {}",
+                match description.strip_suffix('y') {
+                    Some(stem) => format!("{stem}ies"),
+                    None => format!("{description}s"),
+                },
+                values.iter().map(|value| format!("`{value}`")).list(Conjunction::And)
+            ),
+        };
+        move |expected: &str| (values.contains(&expected), note)
+    };
+
+    Ok(match target {
+        Target::Any => (true, "the test is ignored unconditionally".into()),
+        Target::Os(os) => cfg("target_os", "operating system")(os),
+        Target::Family(family) => cfg("target_family", "family")(family),
+        Target::Arch(arch) => cfg("target_arch", "architecture")(arch),
+        Target::Env(env) => cfg("target_env", "environment")(env),
+        Target::Vendor(vendor) => cfg("target_vendor", "vendor")(vendor),
+        Target::PointerWidth(width) => cfg("target_pointer_width", "pointer width")(width),
+        Target::Endian(endian) => cfg("target_endian", "endianness")(endian),
+        Target::Channel(channel) => {
+            let version = Engine::Rustc.version(cx).map_err(|cause| {
+                error(fmt!("failed to retrieve the version of the underlying `rustc`"))
+                    // FIXME: Don't use the short description, use the proper one once we have one
+                    .note(fmt!("caused by: {}", cause.short_desc()))
+                    .note(fmt!("required in order to evaluate the release channel"))
+                    .done()
+            })?;
+            let actual = match version.channel {
+                Channel::Stable => "stable",
+                Channel::Beta { .. } => "beta",
+                Channel::Nightly => "nightly",
+                Channel::Dev => "dev",
+            };
+            (channel == actual, format!("the release channel of the toolchain is `{actual}`"))
+        }
+        Target::Triple(triple) => {
+            (triple == info.triple, format!("the target is `{}`", info.triple))
+        }
+    })
+}
+
+/// Whether the target supports the capability together with a description of the shortcoming.
+fn supports(capability: Capability, info: &TargetInfo) -> (bool, String) {
+    match capability {
+        Capability::Unwind => (
+            info.cfg("panic").any(|strategy| strategy == "unwind"),
+            "the target doesn't unwind on panic (the panic strategy is `abort`)".into(),
+        ),
+        Capability::DynamicLinking => {
+            (info.supports_dynamic_linking(), "the target doesn't support dynamic linking".into())
+        }
+        Capability::AsmSupport => (
+            info.cfg("target_arch").any(|arch| ASM_SUPPORTED_ARCHES.contains(&arch)),
+            "the target doesn't support inline assembly".into(),
+        ),
+    }
+}
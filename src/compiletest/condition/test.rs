@@ -0,0 +1,193 @@
+use super::{evaluate, evaluate_against, matches, supports};
+use crate::{
+    build::{DebugOptions, TargetInfo},
+    context,
+    directive::{Capability, Condition, Target},
+    source::{Span, Spanned},
+};
+use std::num::NonZero;
+
+const LINUX: &str = r#"panic="unwind"
+target_arch="x86_64"
+target_endian="little"
+target_env="gnu"
+target_family="unix"
+target_os="linux"
+target_pointer_width="64"
+target_vendor="unknown"
+unix
+"#;
+
+const WASI: &str = r#"panic="abort"
+target_arch="wasm32"
+target_family="unix"
+target_family="wasm"
+target_os="wasi"
+"#;
+
+const BARE_METAL: &str = r#"panic="abort"
+target_arch="riscv32"
+target_os="none"
+"#;
+
+fn info(triple: &str, cfgs: &str) -> TargetInfo {
+    TargetInfo::parse(triple.into(), cfgs).unwrap()
+}
+
+fn linux() -> TargetInfo {
+    info("x86_64-unknown-linux-gnu", LINUX)
+}
+
+fn spanned<T>(start: u32, bare: T) -> Spanned<T> {
+    Spanned::new(Span { start, end: start + 1 }, bare)
+}
+
+macro cx() {
+    context::new!(context::Options {
+        toolchain: None,
+        dbg_opts: DebugOptions { verbose: false },
+        jobs: NonZero::<usize>::MIN,
+    })
+}
+
+#[test]
+fn target_info_malformed() {
+    assert!(TargetInfo::parse("x".into(), "target_os=linux").is_none());
+    assert!(TargetInfo::parse("x".into(), "target_os=\"linux").is_none());
+}
+
+#[test]
+fn target_matches() {
+    let cx = cx!();
+    let matches = |target, info: &TargetInfo| matches(target, info, cx).ok().unwrap();
+
+    assert_eq!(
+        matches(Target::Os("linux"), &linux()),
+        (true, "the operating system of the target is `linux`".into())
+    );
+    assert_eq!(
+        matches(Target::Arch("aarch64"), &linux()),
+        (false, "the architecture of the target is `x86_64`".into())
+    );
+    assert!(matches(Target::Env("gnu"), &linux()).0);
+    assert!(matches(Target::Vendor("unknown"), &linux()).0);
+    assert!(matches(Target::PointerWidth("64"), &linux()).0);
+    assert!(!matches(Target::Endian("big"), &linux()).0);
+    assert_eq!(
+        matches(Target::Triple("x86_64-unknown-linux-gnu"), &linux()),
+        (true, "the target is `x86_64-unknown-linux-gnu`".into())
+    );
+    assert!(matches(Target::Any, &linux()).0);
+}
+
+#[test]
+fn target_matches_multi_valued_cfg() {
+    let cx = cx!();
+    let wasi = info("wasm32-wasip1", WASI);
+
+    let (matches_unix, note) = matches(Target::Family("unix"), &wasi, cx).ok().unwrap();
+    assert!(matches_unix);
+    assert_eq!(note, "the families of the target are `unix` and `wasm`");
+    assert!(matches(Target::Family("wasm"), &wasi, cx).ok().unwrap().0);
+    assert!(!matches(Target::Family("windows"), &wasi, cx).ok().unwrap().0);
+}
+
+#[test]
+fn target_matches_absent_cfg() {
+    let cx = cx!();
+    let bare_metal = info("riscv32i-unknown-none-elf", BARE_METAL);
+
+    assert_eq!(
+        matches(Target::Env("gnu"), &bare_metal, cx).ok().unwrap(),
+        (false, "the target has no environment".into())
+    );
+}
+
+#[test]
+fn target_supports() {
+    assert!(supports(Capability::Unwind, &linux()).0);
+    assert!(supports(Capability::DynamicLinking, &linux()).0);
+    assert!(supports(Capability::AsmSupport, &linux()).0);
+
+    let wasi = info("wasm32-wasip1", WASI);
+    assert!(!supports(Capability::Unwind, &wasi).0);
+    assert!(!supports(Capability::DynamicLinking, &wasi).0);
+    assert!(!supports(Capability::AsmSupport, &wasi).0);
+
+    let bare_metal = info("riscv32i-unknown-none-elf", BARE_METAL);
+    assert!(!supports(Capability::DynamicLinking, &bare_metal).0);
+    assert!(supports(Capability::AsmSupport, &bare_metal).0);
+}
+
+#[test]
+fn first_ignoring_condition_wins() {
+    let cx = cx!();
+    let conditions = [
+        spanned(1, Condition::Only(Target::Os("linux"))),
+        spanned(2, Condition::Ignore(Target::Arch("wasm32"))),
+        spanned(3, Condition::Ignore(Target::PointerWidth("64"))),
+        spanned(4, Condition::Needs(Capability::DynamicLinking)),
+    ];
+
+    let ignored = evaluate_against(&conditions, &linux(), cx).ok().unwrap().unwrap();
+    assert_eq!(ignored.span, Span { start: 3, end: 4 });
+    assert_eq!(ignored.note, "the pointer width of the target is `64`");
+}
+
+#[test]
+fn unmet_requirements_ignore() {
+    let cx = cx!();
+    let wasi = info("wasm32-wasip1", WASI);
+    let conditions = [
+        spanned(1, Condition::Only(Target::Family("wasm"))),
+        spanned(2, Condition::Needs(Capability::Unwind)),
+    ];
+
+    let ignored = evaluate_against(&conditions, &wasi, cx).ok().unwrap().unwrap();
+    assert_eq!(ignored.span, Span { start: 2, end: 3 });
+
+    let conditions = [spanned(1, Condition::Only(Target::Os("linux")))];
+    let ignored = evaluate_against(&conditions, &wasi, cx).ok().unwrap().unwrap();
+    assert_eq!(ignored.note, "the operating system of the target is `wasi`");
+}
+
+#[test]
+fn met_conditions_dont_ignore() {
+    let cx = cx!();
+    let conditions = [
+        spanned(1, Condition::Only(Target::Family("unix"))),
+        spanned(2, Condition::Ignore(Target::Os("windows"))),
+        spanned(3, Condition::Needs(Capability::Unwind)),
+    ];
+
+    assert!(evaluate_against(&conditions, &linux(), cx).ok().unwrap().is_none());
+}
+
+#[test]
+fn no_conditions_dont_require_target_info() {
+    // There's no toolchain to query, so this would fail otherwise.
+    let cx = context::new!(context::Options {
+        toolchain: Some(crate::data::PlusPrefixedToolchain::new("+/nonexistent".into()).unwrap()),
+        dbg_opts: DebugOptions { verbose: false },
+        jobs: NonZero::<usize>::MIN,
+    });
+
+    assert!(evaluate(&[], Some("wasm32-wasip1"), cx).ok().unwrap().is_none());
+}
+
+#[test]
+#[cfg(unix)]
+fn channel_matches() {
+    let toolchain = crate::utility::testing::FakeToolchain::new(LINUX);
+    let cx = context::new!(context::Options {
+        toolchain: Some(toolchain.toolchain()),
+        dbg_opts: DebugOptions { verbose: false },
+        jobs: NonZero::<usize>::MIN,
+    });
+
+    assert_eq!(
+        matches(Target::Channel("nightly"), &linux(), cx).ok().unwrap(),
+        (true, "the release channel of the toolchain is `nightly`".into())
+    );
+    assert!(!matches(Target::Channel("stable"), &linux(), cx).ok().unwrap().0);
+}
@@ -0,0 +1,109 @@
+//! A line-based diff for presenting mismatches between test expectations and actual output.
+
+use crate::utility::paint::Painter;
+use anstyle::AnsiColor;
+use std::io::{self, Write as _};
+
+#[cfg(test)]
+mod test;
+
+/// The number of unchanged lines to show around changed ones.
+const CONTEXT: usize = 3;
+
+pub(super) fn render(
+    expected: &str,
+    actual: &str,
+    p: &mut Painter<impl io::Write>,
+) -> io::Result<()> {
+    let lines = diff(expected, actual);
+
+    let mut visible = vec![false; lines.len()];
+    for (index, _) in lines.iter().enumerate().filter(|(_, line)| !matches!(line, Line::Same(_))) {
+        let end = (index + CONTEXT).min(lines.len() - 1);
+        visible[index.saturating_sub(CONTEXT)..=end].fill(true);
+    }
+
+    let (mut old, mut new) = (1, 1);
+
+    for (index, line) in lines.into_iter().enumerate() {
+        if visible[index] {
+            if index == 0 || !visible[index - 1] {
+                p.with(AnsiColor::Cyan, |p| writeln!(p, "@@ -{old} +{new} @@"))?;
+            }
+
+            match line {
+                Line::Same(line) => writeln!(p, " {line}")?,
+                Line::Removed(line) => p.with(AnsiColor::Red, |p| writeln!(p, "-{line}"))?,
+                Line::Added(line) => p.with(AnsiColor::Green, |p| writeln!(p, "+{line}"))?,
+            }
+        }
+
+        match line {
+            Line::Same(_) => (old, new) = (old + 1, new + 1),
+            Line::Removed(_) => old += 1,
+            Line::Added(_) => new += 1,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Compute the line-based diff between two strings via their longest common subsequence.
+fn diff<'a>(expected: &'a str, actual: &'a str) -> Vec<Line<'a>> {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+
+    // Trimming the common prefix and suffix keeps the quadratic part below small in practice.
+    let prefix = expected.iter().zip(&actual).take_while(|(e, a)| e == a).count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(e, a)| e == a)
+        .count();
+
+    let old = &expected[prefix..expected.len() - suffix];
+    let new = &actual[prefix..actual.len() - suffix];
+
+    // `lengths[i * width + j]` is the length of the LCS of `old[i..]` and `new[j..]`.
+    let width = new.len() + 1;
+    let mut lengths = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(expected.len().max(actual.len()));
+    lines.extend(expected[..prefix].iter().map(|&line| Line::Same(line)));
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len()
+            && (j == new.len() || lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    lines.extend(expected[expected.len() - suffix..].iter().map(|&line| Line::Same(line)));
+    lines
+}
@@ -0,0 +1,52 @@
+use super::{Line, diff, render};
+use crate::utility::paint::Painter;
+use Line::{Added as A, Removed as R, Same as S};
+
+#[test]
+fn identical() {
+    assert_eq!(diff("a\nb\nc\n", "a\nb\nc\n"), [S("a"), S("b"), S("c")]);
+    assert_eq!(diff("", ""), []);
+}
+
+#[test]
+fn insertions_only() {
+    assert_eq!(diff("a\nc\n", "a\nb\nc\nd\n"), [S("a"), A("b"), S("c"), A("d")]);
+    assert_eq!(diff("", "a\nb\n"), [A("a"), A("b")]);
+}
+
+#[test]
+fn deletions_only() {
+    assert_eq!(diff("a\nb\nc\nd\n", "b\nd\n"), [R("a"), S("b"), R("c"), S("d")]);
+    assert_eq!(diff("a\nb\n", ""), [R("a"), R("b")]);
+}
+
+#[test]
+fn interleaved_changes() {
+    assert_eq!(
+        diff("a\nb\nc\nd\ne\n", "a\nx\nc\ne\ny\n"),
+        [S("a"), R("b"), A("x"), S("c"), R("d"), S("e"), A("y")]
+    );
+    // The longest common subsequence is `b c` here, not `a`.
+    assert_eq!(diff("a\nb\nc\n", "b\nc\na\n"), [R("a"), S("b"), S("c"), A("a")]);
+}
+
+#[test]
+fn missing_trailing_line_break() {
+    assert_eq!(diff("a\nb\n", "a\nb"), [S("a"), S("b")]);
+    assert_eq!(diff("a\nb", "a\nc\n"), [S("a"), R("b"), A("c")]);
+}
+
+#[test]
+fn hunks_have_limited_context() {
+    let expected = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+    let actual = "1\ntwo\n3\n4\n5\n6\n7\n8\n9\n10\neleven\n12\n";
+
+    let mut output = Vec::new();
+    render(expected, actual, &mut Painter::new(Vec::new(), |_| &mut output)).unwrap();
+    let output = anstream::adapter::strip_str(str::from_utf8(&output).unwrap()).to_string();
+
+    assert_eq!(
+        output,
+        "@@ -1 +1 @@\n 1\n-2\n+two\n 3\n 4\n 5\n@@ -8 +8 @@\n 8\n 9\n 10\n-11\n+eleven\n 12\n"
+    );
+}
@@ -0,0 +1,475 @@
+//! An engine for htmldocck directives akin to `src/etc/htmldocck.py`.
+
+use crate::directive::{DocCheck, HtmlDocCkDirective, Polarity};
+use html::{Document, Id};
+use regex_lite::Regex;
+use std::{
+    collections::{BTreeSet, HashMap},
+    env, fs,
+    path::{Component, Path, PathBuf},
+};
+
+mod html;
+#[cfg(test)]
+mod test;
+mod xpath;
+
+/// The result of a single check.
+pub(super) enum Verdict {
+    Pass,
+    /// The check failed for the given reasons.
+    Fail(Vec<String>),
+    /// The check needs to be compared against the snapshot of the given name.
+    Snapshot {
+        name: String,
+        actual: String,
+        kind: SnapshotKind,
+    },
+}
+
+/// The documentation under test, loaded lazily.
+pub(super) struct Cache {
+    root: PathBuf,
+    /// The URL `{{channel}}` stands for.
+    channel: String,
+    /// The path of the previous check which `-` refers to.
+    last_path: Option<PathBuf>,
+    files: HashMap<PathBuf, String>,
+    trees: HashMap<PathBuf, Document>,
+}
+
+impl Cache {
+    pub(super) fn new(root: &Path) -> Self {
+        let channel = env::var("DOC_RUST_LANG_ORG_CHANNEL")
+            .unwrap_or_else(|_| "https://doc.rust-lang.org/nightly".into());
+        Self {
+            root: root.to_owned(),
+            channel,
+            last_path: None,
+            files: HashMap::new(),
+            trees: HashMap::new(),
+        }
+    }
+
+    fn resolve(&mut self, path: &str) -> Result<PathBuf, String> {
+        if path != "-" {
+            let path: PathBuf =
+                Path::new(path).components().filter(|&part| part != Component::CurDir).collect();
+            self.last_path = Some(path.clone());
+            return Ok(path);
+        }
+        self.last_path.clone().ok_or_else(|| "there's no previous path `-` could refer to".into())
+    }
+
+    fn file(&mut self, path: &Path) -> Result<&str, String> {
+        if !self.files.contains_key(path) {
+            let contents = fs::read_to_string(self.root.join(path))
+                .map_err(|_| format!("file `{}` does not exist", path.display()))?;
+            self.files.insert(path.to_owned(), contents);
+        }
+        Ok(&self.files[path])
+    }
+
+    fn tree(&mut self, path: &Path) -> Result<&Document, String> {
+        if !self.trees.contains_key(path) {
+            let tree = Document::parse(self.file(path)?).map_err(|error| {
+                format!("failed to parse `{}` as HTML: {error}", path.display())
+            })?;
+            self.trees.insert(path.to_owned(), tree);
+        }
+        Ok(&self.trees[path])
+    }
+
+    /// Parse an absolute `XPath` like htmldocck does.
+    fn xpath(&self, source: &str) -> Result<xpath::Path, String> {
+        let source = source.replace("{{channel}}", &self.channel);
+        let source = match source.strip_prefix("//") {
+            Some(_) => format!(".{source}"),
+            None if source.starts_with(".//") => source,
+            None => return Err(format!("XPath `{source}` is not absolute")),
+        };
+        xpath::Path::parse(&source)
+    }
+
+    fn matcher(&self, pattern: &str, kind: Kind) -> Result<Matcher, String> {
+        let pattern = pattern.replace("{{channel}}", &self.channel);
+        Ok(if pattern.is_empty() {
+            Matcher::Any
+        } else {
+            match kind {
+                Kind::Substring => Matcher::Substring(normalize_whitespace(&pattern)),
+                Kind::Regex => {
+                    Matcher::Regex(Regex::new(&pattern).map_err(|error| {
+                        format!("invalid regular expression `{pattern}`: {error}")
+                    })?)
+                }
+            }
+        })
+    }
+}
+
+pub(super) fn check(check: &DocCheck<'_, HtmlDocCkDirective>, cache: &mut Cache) -> Verdict {
+    evaluate(check, cache).unwrap_or_else(|reason| Verdict::Fail(vec![reason]))
+}
+
+fn evaluate(
+    &DocCheck { directive, polarity, arguments, .. }: &DocCheck<'_, HtmlDocCkDirective>,
+    cache: &mut Cache,
+) -> Result<Verdict, String> {
+    let Some(arguments) = shlex::split(arguments) else {
+        return Err("the arguments are not properly quoted".into());
+    };
+    let arguments: Vec<_> = arguments.iter().map(String::as_str).collect();
+    let verdict = |holds: bool, positive: &dyn Fn() -> String, negative: &dyn Fn() -> String| match (
+        holds, polarity,
+    ) {
+        (true, Polarity::Positive) | (false, Polarity::Negative) => Verdict::Pass,
+        (false, Polarity::Positive) => Verdict::Fail(vec![positive()]),
+        (true, Polarity::Negative) => Verdict::Fail(vec![negative()]),
+    };
+
+    match (directive, arguments.as_slice()) {
+        (HtmlDocCkDirective::Has, &[path]) => {
+            let path = cache.resolve(path)?;
+            let holds = cache.file(&path).is_ok();
+            Ok(verdict(holds, &|| format!("file `{}` does not exist", path.display()), &|| {
+                format!("file `{}` exists", path.display())
+            }))
+        }
+        (HtmlDocCkDirective::HasRaw | HtmlDocCkDirective::MatchesRaw, &[path, pattern]) => {
+            let kind = Kind::of(directive);
+            let matcher = cache.matcher(pattern, kind)?;
+            let path = cache.resolve(path)?;
+            let holds = matcher.matches(cache.file(&path)?);
+            Ok(verdict(
+                holds,
+                &|| format!("{} `{pattern}` not found in `{}`", kind.name(), path.display()),
+                &|| {
+                    format!(
+                        "{} `{pattern}` unexpectedly found in `{}`",
+                        kind.name(),
+                        path.display()
+                    )
+                },
+            ))
+        }
+        (HtmlDocCkDirective::Has | HtmlDocCkDirective::Matches, &[path, xpath, pattern]) => {
+            let kind = Kind::of(directive);
+            let path = cache.resolve(path)?;
+            let holds = count_matches(cache, &path, xpath, pattern, kind, true)? != 0;
+            Ok(verdict(
+                holds,
+                &|| format!("no match for `{xpath}` and {} `{pattern}`", kind.name()),
+                &|| format!("unexpected match for `{xpath}` and {} `{pattern}`", kind.name()),
+            ))
+        }
+        (HtmlDocCkDirective::Count, &[path, xpath, count]) => {
+            let expected = parse_count(count)?;
+            let xpath = cache.xpath(xpath)?;
+            let path = cache.resolve(path)?;
+            let actual = xpath.select(cache.tree(&path)?, Document::ROOT).len();
+            Ok(verdict(
+                actual == expected,
+                &|| format!("expected {expected} occurrences but found {actual}"),
+                &|| format!("unexpectedly found {actual} occurrences"),
+            ))
+        }
+        (HtmlDocCkDirective::Count, &[path, xpath, text, count]) => {
+            let expected = parse_count(count)?;
+            let path = cache.resolve(path)?;
+            let actual = count_matches(cache, &path, xpath, text, Kind::Substring, false)?;
+            Ok(verdict(
+                actual == expected,
+                &|| format!("expected {expected} occurrences but found {actual}"),
+                &|| format!("unexpectedly found {actual} occurrences"),
+            ))
+        }
+        (HtmlDocCkDirective::Files, &[folder, entries]) => {
+            if let Polarity::Negative = polarity {
+                return Err("`files` cannot be negated".into());
+            }
+            check_files(cache, folder, entries)
+        }
+        (HtmlDocCkDirective::HasDir, &[path]) => {
+            let path = cache.resolve(path)?;
+            let holds = cache.root.join(&path).is_dir();
+            Ok(verdict(
+                holds,
+                &|| format!("directory `{}` does not exist", path.display()),
+                &|| format!("directory `{}` exists", path.display()),
+            ))
+        }
+        (HtmlDocCkDirective::Snapshot, &[name, path, xpath]) => {
+            if let Polarity::Negative = polarity {
+                return Err("`snapshot` cannot be negated".into());
+            }
+            let (xpath, kind) = match xpath.strip_suffix("/text()") {
+                Some(xpath) => (xpath, SnapshotKind::Text),
+                None => (xpath, SnapshotKind::Element),
+            };
+            let xpath = cache.xpath(xpath)?;
+            let path = cache.resolve(path)?;
+            let channel = cache.channel.clone();
+            let tree = cache.tree(&path)?;
+            let actual = match xpath.select(tree, Document::ROOT).as_slice() {
+                [] => return Err("the XPath did not match".into()),
+                &[element] => match kind {
+                    SnapshotKind::Element => tree.serialize(element),
+                    SnapshotKind::Text => tree.text(element),
+                },
+                elements => return Err(format!("expected 1 match but found {}", elements.len())),
+            };
+            let actual = actual.replace(&channel, "{{channel}}");
+            Ok(Verdict::Snapshot { name: name.to_owned(), actual, kind })
+        }
+        (directive, _) => Err(format!("invalid number of arguments for `{}`", name(directive))),
+    }
+}
+
+/// Whether a snapshot captures the serialized element or merely its text.
+#[derive(Clone, Copy)]
+pub(super) enum SnapshotKind {
+    Element,
+    Text,
+}
+
+/// A structural difference between two element snapshots.
+pub(super) struct Mismatch {
+    pub(super) reason: String,
+    /// The pretty-printed expected snapshot.
+    pub(super) expected: String,
+    /// The pretty-printed actual snapshot.
+    pub(super) actual: String,
+}
+
+/// Compare two element snapshots structurally like htmldocck does.
+///
+/// Whitespace around character data is insignificant and `*` matches any character data.
+pub(super) fn compare_snapshots(expected: &str, actual: &str) -> Option<Mismatch> {
+    // The snapshot may contain several top-level nodes since it includes the tail of the element.
+    let parse = |source: &str| Document::parse(&format!("<wrapper>{source}</wrapper>"));
+    let raw = |reason| Mismatch { reason, expected: expected.into(), actual: actual.into() };
+
+    let expected = match parse(expected) {
+        Ok(expected) => expected,
+        Err(error) => return Some(raw(format!("failed to parse the snapshot: {error}"))),
+    };
+    let actual = match parse(actual) {
+        Ok(actual) => actual,
+        Err(error) => return Some(raw(format!("failed to parse the actual HTML: {error}"))),
+    };
+
+    compare_elements((&expected, Document::ROOT), (&actual, Document::ROOT)).err().map(|reason| {
+        Mismatch {
+            reason,
+            expected: expected.pretty_print(Document::ROOT),
+            actual: actual.pretty_print(Document::ROOT),
+        }
+    })
+}
+
+fn compare_elements(
+    (expected, expected_id): (&Document, Id),
+    (actual, actual_id): (&Document, Id),
+) -> Result<(), String> {
+    let (expected_element, actual_element) = (&expected[expected_id], &actual[actual_id]);
+    let describe = |id: Id, tag: &str| {
+        if id == Document::ROOT { "the snapshot".to_owned() } else { format!("`<{tag}>`") }
+    };
+    let element = describe(actual_id, &actual_element.tag);
+
+    if expected_element.tag != actual_element.tag {
+        return Err(format!(
+            "expected {} but found {element}",
+            describe(expected_id, &expected_element.tag)
+        ));
+    }
+
+    for (name, value) in &expected_element.attributes {
+        match actual_element.attribute(name) {
+            Some(actual) if actual == value => {}
+            Some(actual) => {
+                return Err(format!(
+                    "expected attribute `{name}` of {element} to be `{value}` but found `{actual}`"
+                ));
+            }
+            None => return Err(format!("expected attribute `{name}` on {element}")),
+        }
+    }
+    if let Some((name, _)) = actual_element
+        .attributes
+        .iter()
+        .find(|(name, _)| expected_element.attribute(name).is_none())
+    {
+        return Err(format!("unexpected attribute `{name}` on {element}"));
+    }
+
+    if !data_matches(&expected_element.text, &actual_element.text) {
+        return Err(format!(
+            "expected text `{}` inside of {element} but found `{}`",
+            expected_element.text.trim(),
+            actual_element.text.trim()
+        ));
+    }
+
+    if expected_element.children.len() != actual_element.children.len() {
+        return Err(format!(
+            "expected {} children inside of {element} but found {}",
+            expected_element.children.len(),
+            actual_element.children.len()
+        ));
+    }
+    for (&expected_child, &actual_child) in
+        expected_element.children.iter().zip(&actual_element.children)
+    {
+        compare_elements((expected, expected_child), (actual, actual_child))?;
+        let (expected_tail, actual_tail) =
+            (&expected[expected_child].tail, &actual[actual_child].tail);
+        if !data_matches(expected_tail, actual_tail) {
+            return Err(format!(
+                "expected text `{}` after {} but found `{}`",
+                expected_tail.trim(),
+                describe(actual_child, &actual[actual_child].tag),
+                actual_tail.trim()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare character data like htmldocck does.
+fn data_matches(expected: &str, actual: &str) -> bool {
+    expected == "*" || expected.trim() == actual.trim()
+}
+
+/// Count the elements matching the `XPath` whose text or attribute matches the pattern.
+fn count_matches(
+    cache: &mut Cache,
+    path: &Path,
+    xpath: &str,
+    pattern: &str,
+    kind: Kind,
+    stop_at_first: bool,
+) -> Result<usize, String> {
+    let matcher = cache.matcher(pattern, kind)?;
+
+    if let Some((xpath, attribute)) = xpath.split_once("/@") {
+        let xpath = cache.xpath(xpath)?;
+        let tree = cache.tree(path)?;
+        // Like in htmldocck, this is at most one regardless of `stop_at_first`.
+        let found = xpath
+            .select(tree, Document::ROOT)
+            .into_iter()
+            .filter_map(|element| tree[element].attribute(attribute))
+            .any(|value| matcher.matches(value));
+        return Ok(found.into());
+    }
+
+    let xpath = cache.xpath(xpath.strip_suffix("/text()").unwrap_or(xpath))?;
+    let tree = cache.tree(path)?;
+    let elements = xpath.select(tree, Document::ROOT).into_iter();
+    let mut found = elements.filter(|&element| matcher.matches(&tree.text(element)));
+    Ok(if stop_at_first { found.next().into_iter().count() } else { found.count() })
+}
+
+/// Check that the folder contains exactly the given entries.
+fn check_files(cache: &Cache, folder: &str, entries: &str) -> Result<Verdict, String> {
+    let Some(entries) = entries.trim().strip_prefix('[').and_then(|it| it.strip_suffix(']')) else {
+        return Err("expected a list as the second argument (e.g., `[]`)".into());
+    };
+    let Some(entries) = shlex::split(&entries.replace(',', "")) else {
+        return Err("the list is not properly quoted".into());
+    };
+
+    let mut expected = BTreeSet::new();
+    for entry in entries {
+        if expected.contains(&entry) {
+            return Err(format!("duplicate entry `{entry}`"));
+        }
+        expected.insert(entry);
+    }
+
+    let path = cache.root.join(folder);
+    let actual: BTreeSet<_> = fs::read_dir(&path)
+        .map_err(|error| format!("failed to read directory `{folder}`: {error}"))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+
+    let list = |entries: Vec<&String>| {
+        entries.into_iter().map(|entry| format!("`{entry}`")).collect::<Vec<_>>().join(", ")
+    };
+    let missing: Vec<_> = expected.difference(&actual).collect();
+    let extra: Vec<_> = actual.difference(&expected).collect();
+
+    let mut reasons = Vec::new();
+    if !missing.is_empty() {
+        reasons.push(format!("entries not found in `{folder}`: {}", list(missing)));
+    }
+    if !extra.is_empty() {
+        reasons.push(format!("extra entries in `{folder}`: {}", list(extra)));
+    }
+    Ok(if reasons.is_empty() { Verdict::Pass } else { Verdict::Fail(reasons) })
+}
+
+fn parse_count(count: &str) -> Result<usize, String> {
+    count.parse().map_err(|_| format!("invalid count `{count}`"))
+}
+
+const fn name(directive: HtmlDocCkDirective) -> &'static str {
+    match directive {
+        HtmlDocCkDirective::Count => "count",
+        HtmlDocCkDirective::Files => "files",
+        HtmlDocCkDirective::Has => "has",
+        HtmlDocCkDirective::HasDir => "has-dir",
+        HtmlDocCkDirective::HasRaw => "hasraw",
+        HtmlDocCkDirective::Matches => "matches",
+        HtmlDocCkDirective::MatchesRaw => "matchesraw",
+        HtmlDocCkDirective::Snapshot => "snapshot",
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Substring,
+    Regex,
+}
+
+impl Kind {
+    const fn of(directive: HtmlDocCkDirective) -> Self {
+        match directive {
+            HtmlDocCkDirective::Matches | HtmlDocCkDirective::MatchesRaw => Self::Regex,
+            _ => Self::Substring,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Substring => "pattern",
+            Self::Regex => "regular expression",
+        }
+    }
+}
+
+enum Matcher {
+    /// Like in htmldocck, an empty pattern matches anything.
+    Any,
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Self::Any => true,
+            // Like in htmldocck, substring checks are insensitive to the amount of whitespace.
+            Self::Substring(pattern) => normalize_whitespace(text).contains(pattern.as_str()),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,425 @@
+//! A lenient HTML parser akin to the one htmldocck builds on top of Python's `HTMLParser`.
+//!
+//! Like `ElementTree`, it represents character data as the *text* of an element (preceding its
+//! first child) and as the *tail* of an element (following its end tag).
+
+use std::fmt::Write as _;
+
+/// Elements that never have an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "keygen", "link", "menuitem",
+    "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose content is raw text that doesn't get parsed or unescaped.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+pub(super) struct Document {
+    elements: Vec<Element>,
+}
+
+impl Document {
+    /// The root element.
+    pub(super) const ROOT: Id = Id(0);
+
+    pub(super) fn parse(source: &str) -> Result<Self, String> {
+        let mut builder = Builder::default();
+        let mut rest = source;
+
+        while !rest.is_empty() {
+            let Some(index) = rest.find('<') else {
+                builder.data(&unescape(rest));
+                break;
+            };
+            builder.data(&unescape(&rest[..index]));
+            rest = &rest[index..];
+
+            if let Some(after) = rest.strip_prefix("<!--") {
+                rest = after.find("-->").map_or("", |end| &after[end + "-->".len()..]);
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                // Declarations like the doctype and processing instructions.
+                rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            } else if let Some(after) = rest.strip_prefix("</") {
+                let Some(end) = after.find('>') else {
+                    builder.data(rest);
+                    break;
+                };
+                let tag = after[..end].trim_end().to_ascii_lowercase();
+                if tag.starts_with(|char: char| char.is_ascii_alphabetic()) {
+                    builder.end(&tag)?;
+                }
+                rest = &after[end + 1..];
+            } else if rest[1..].starts_with(|char: char| char.is_ascii_alphabetic()) {
+                let (tag, attributes, closed, after) = parse_start_tag(&rest[1..]);
+                rest = after;
+                builder.start(tag.clone(), attributes);
+
+                if closed || VOID_ELEMENTS.contains(&tag.as_str()) {
+                    builder.end(&tag)?;
+                } else if RAW_TEXT_ELEMENTS.contains(&tag.as_str()) {
+                    let end =
+                        rest.to_ascii_lowercase().find(&format!("</{tag}")).unwrap_or(rest.len());
+                    builder.data(&rest[..end]);
+                    rest = &rest[end..];
+                }
+            } else {
+                builder.data("<");
+                rest = &rest[1..];
+            }
+        }
+
+        builder.close()
+    }
+
+    /// The descendants of the given element in document order excluding the element itself.
+    pub(super) fn descendants(&self, id: Id) -> impl Iterator<Item = Id> + '_ {
+        let mut stack: Vec<_> = self[id].children.iter().rev().copied().collect();
+        std::iter::from_fn(move || {
+            let id = stack.pop()?;
+            stack.extend(self[id].children.iter().rev().copied());
+            Some(id)
+        })
+    }
+
+    /// The concatenated character data inside of the given element.
+    pub(super) fn text(&self, id: Id) -> String {
+        let mut text = String::new();
+        self.collect_text(id, &mut text);
+        text
+    }
+
+    fn collect_text(&self, id: Id, text: &mut String) {
+        let element = &self[id];
+        text.push_str(&element.text);
+        for &child in &element.children {
+            self.collect_text(child, text);
+            text.push_str(&self[child].tail);
+        }
+    }
+
+    /// Serialize the given element including its tail like `ElementTree` does.
+    pub(super) fn serialize(&self, id: Id) -> String {
+        let mut output = String::new();
+        self.serialize_into(id, &mut output);
+        output
+    }
+
+    fn serialize_into(&self, id: Id, output: &mut String) {
+        let element = &self[id];
+        output.push('<');
+        output.push_str(&element.tag);
+        for (name, value) in &element.attributes {
+            write!(output, " {name}=\"{}\"", escape(value, Context::Attribute)).unwrap();
+        }
+        if element.text.is_empty() && element.children.is_empty() {
+            output.push_str(" />");
+        } else {
+            output.push('>');
+            output.push_str(&escape(&element.text, Context::Text));
+            for &child in &element.children {
+                self.serialize_into(child, output);
+            }
+            write!(output, "</{}>", element.tag).unwrap();
+        }
+        output.push_str(&escape(&element.tail, Context::Text));
+    }
+
+    /// Pretty-print the contents of the given element with one node per line.
+    ///
+    /// Whitespace around character data is dropped, so the result is only fit for display.
+    pub(super) fn pretty_print(&self, id: Id) -> String {
+        let mut output = String::new();
+        self.pretty_print_contents(id, 0, &mut output);
+        output
+    }
+
+    fn pretty_print_contents(&self, id: Id, depth: usize, output: &mut String) {
+        let element = &self[id];
+        pretty_print_data(&element.text, depth, output);
+        for &child in &element.children {
+            self.pretty_print_into(child, depth, output);
+            pretty_print_data(&self[child].tail, depth, output);
+        }
+    }
+
+    fn pretty_print_into(&self, id: Id, depth: usize, output: &mut String) {
+        let element = &self[id];
+        write!(output, "{:indent$}<{}", "", element.tag, indent = depth * 4).unwrap();
+        for (name, value) in &element.attributes {
+            write!(output, " {name}=\"{}\"", escape(value, Context::Attribute)).unwrap();
+        }
+        let text = element.text.trim();
+        match (text.is_empty(), element.children.is_empty()) {
+            (true, true) => output.push_str(" />\n"),
+            (false, true) => {
+                writeln!(output, ">{}</{}>", escape(text, Context::Text), element.tag).unwrap();
+            }
+            (_, false) => {
+                output.push_str(">\n");
+                self.pretty_print_contents(id, depth + 1, output);
+                writeln!(output, "{:indent$}</{}>", "", element.tag, indent = depth * 4).unwrap();
+            }
+        }
+    }
+}
+
+fn pretty_print_data(data: &str, depth: usize, output: &mut String) {
+    let data = data.trim();
+    if !data.is_empty() {
+        writeln!(output, "{:indent$}{}", "", escape(data, Context::Text), indent = depth * 4)
+            .unwrap();
+    }
+}
+
+impl std::ops::Index<Id> for Document {
+    type Output = Element;
+
+    fn index(&self, Id(index): Id) -> &Self::Output {
+        &self.elements[index]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+pub(super) struct Id(usize);
+
+pub(super) struct Element {
+    pub(super) tag: String,
+    pub(super) attributes: Vec<(String, String)>,
+    pub(super) text: String,
+    pub(super) tail: String,
+    pub(super) children: Vec<Id>,
+    pub(super) parent: Option<Id>,
+}
+
+impl Element {
+    pub(super) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// A tree builder akin to `ElementTree`'s `TreeBuilder`.
+#[derive(Default)]
+struct Builder {
+    elements: Vec<Element>,
+    stack: Vec<Id>,
+    last: Option<Id>,
+    tail: bool,
+    data: String,
+}
+
+impl Builder {
+    fn start(&mut self, tag: String, attributes: Vec<(String, String)>) {
+        self.flush();
+        let id = Id(self.elements.len());
+        let parent = self.stack.last().copied();
+        if let Some(Id(parent)) = parent {
+            self.elements[parent].children.push(id);
+        }
+        // Like in ElementTree, top-level elements after the root element become detached.
+        self.elements.push(Element {
+            tag,
+            attributes,
+            text: String::new(),
+            tail: String::new(),
+            children: Vec::new(),
+            parent,
+        });
+        self.stack.push(id);
+        self.last = Some(id);
+        self.tail = false;
+    }
+
+    fn end(&mut self, tag: &str) -> Result<(), String> {
+        self.flush();
+        let Some(id) = self.stack.pop() else {
+            return Err(format!("unexpected end tag `</{tag}>`"));
+        };
+        let expected = &self.elements[id.0].tag;
+        if expected != tag {
+            return Err(format!("mismatched end tag: expected `</{expected}>` but got `</{tag}>`"));
+        }
+        self.last = Some(id);
+        self.tail = true;
+        Ok(())
+    }
+
+    fn data(&mut self, data: &str) {
+        self.data.push_str(data);
+    }
+
+    fn flush(&mut self) {
+        let data = std::mem::take(&mut self.data);
+        if !data.is_empty()
+            && let Some(Id(last)) = self.last
+        {
+            let last = &mut self.elements[last];
+            if self.tail {
+                last.tail = data;
+            } else {
+                last.text = data;
+            }
+        }
+    }
+
+    fn close(mut self) -> Result<Document, String> {
+        self.flush();
+        if let Some(&Id(id)) = self.stack.last() {
+            return Err(format!("missing end tag `</{}>`", self.elements[id].tag));
+        }
+        if self.elements.is_empty() {
+            return Err("no root element".into());
+        }
+        Ok(Document { elements: self.elements })
+    }
+}
+
+/// Parse the remainder of a start tag returning its name, its attributes, whether it's
+/// self-closing and the rest of the input.
+fn parse_start_tag(source: &str) -> (String, Vec<(String, String)>, bool, &str) {
+    let is_delimiter = |char: char| char.is_whitespace() || char == '/' || char == '>';
+    let end = source.find(is_delimiter).unwrap_or(source.len());
+    let tag = source[..end].to_ascii_lowercase();
+    let mut rest = &source[end..];
+    let mut attributes: Vec<(String, String)> = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            return (tag, attributes, true, after);
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return (tag, attributes, false, after);
+        }
+        if rest.is_empty() {
+            return (tag, attributes, false, rest);
+        }
+        if let Some(after) = rest.strip_prefix('/') {
+            rest = after;
+            continue;
+        }
+
+        let end = rest
+            .find(|char: char| char.is_whitespace() || char == '=' || char == '>' || char == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        let name = rest[..end].to_ascii_lowercase();
+        rest = &rest[end..];
+
+        let mut value = String::new();
+        if let Some(after) = rest.trim_start().strip_prefix('=') {
+            rest = after.trim_start();
+            let raw;
+            if let Some(quote) = rest.chars().next().filter(|&char| char == '"' || char == '\'') {
+                let after = &rest[1..];
+                let end = after.find(quote).unwrap_or(after.len());
+                raw = &after[..end];
+                rest = after.get(end + 1..).unwrap_or_default();
+            } else {
+                let end = rest
+                    .find(|char: char| char.is_whitespace() || char == '>')
+                    .unwrap_or(rest.len());
+                raw = &rest[..end];
+                rest = &rest[end..];
+            }
+            value = unescape(raw);
+        }
+
+        // Like in Python's dictionaries, later attributes overwrite earlier ones.
+        match attributes.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, previous)) => *previous = value,
+            None => attributes.push((name, value)),
+        }
+    }
+}
+
+/// Decode character references.
+///
+/// Unlike Python's `html.unescape`, we only know about a handful of named references.
+fn unescape(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(index) = rest.find('&') {
+        output.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        let decoded = rest.find(';').and_then(|end| Some((decode(&rest[1..end])?, end)));
+        if let Some((char, end)) = decoded {
+            output.push(char);
+            rest = &rest[end + 1..];
+        } else {
+            output.push('&');
+            rest = &rest[1..];
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn decode(reference: &str) -> Option<char> {
+    if let Some(number) = reference.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => number.parse(),
+        };
+        return char::from_u32(code.ok()?);
+    }
+
+    Some(match reference {
+        "amp" => '&',
+        "apos" => '\'',
+        "bull" => '•',
+        "copy" => '©',
+        "darr" => '↓',
+        "deg" => '°',
+        "gt" => '>',
+        "hellip" => '…',
+        "laquo" => '«',
+        "larr" => '←',
+        "ldquo" => '“',
+        "lsquo" => '‘',
+        "lt" => '<',
+        "mdash" => '—',
+        "middot" => '·',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "para" => '¶',
+        "quot" => '"',
+        "raquo" => '»',
+        "rarr" => '→',
+        "rdquo" => '”',
+        "reg" => '®',
+        "rsquo" => '’',
+        "sect" => '§',
+        "times" => '×',
+        "trade" => '™',
+        "uarr" => '↑',
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Context {
+    Text,
+    Attribute,
+}
+
+/// Escape character data like `ElementTree` does when serializing to XML.
+fn escape(source: &str, context: Context) -> String {
+    let mut output = String::with_capacity(source.len());
+    for char in source.chars() {
+        match (char, context) {
+            ('&', _) => output.push_str("&amp;"),
+            ('<', _) => output.push_str("&lt;"),
+            ('>', _) => output.push_str("&gt;"),
+            ('"', Context::Attribute) => output.push_str("&quot;"),
+            ('\n', Context::Attribute) => output.push_str("&#10;"),
+            ('\r', Context::Attribute) => output.push_str("&#13;"),
+            ('\t', Context::Attribute) => output.push_str("&#09;"),
+            (char, _) => output.push(char),
+        }
+    }
+    output
+}
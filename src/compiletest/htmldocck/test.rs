@@ -0,0 +1,102 @@
+use super::{html::Document, xpath};
+
+const SOURCE: &str = r#"<!DOCTYPE html>
+<html lang="en"><head><meta charset="utf-8"><title>krate - Rust</title>
+<script>if (a < b && c) {}</script></head>
+<body class="rustdoc mod"><!-- comment -->
+<h1>Crate <span class="name">krate</span>&nbsp;&#x1F980;</h1>
+<ul class="items"><li><a href="struct.S.html" title="struct krate::S">S</a></li><li><a href="fn.f.html">f</a></li><li><a href="fn.g.html">g</a><br/></li></ul>
+</body></html>
+"#;
+
+fn select(path: &str) -> (Document, Vec<String>) {
+    let document = Document::parse(SOURCE).unwrap();
+    let tags = xpath::Path::parse(path)
+        .unwrap()
+        .select(&document, Document::ROOT)
+        .into_iter()
+        .map(|element| document.serialize(element))
+        .collect();
+    (document, tags)
+}
+
+#[test]
+fn text() {
+    let document = Document::parse(SOURCE).unwrap();
+    let h1 = xpath::Path::parse(".//h1").unwrap().select(&document, Document::ROOT);
+    assert_eq!(document.text(h1[0]), "Crate krate\u{a0}🦀");
+}
+
+#[test]
+fn raw_text() {
+    let (_, scripts) = select(".//script");
+    assert_eq!(scripts, ["<script>if (a &lt; b &amp;&amp; c) {}</script>"]);
+}
+
+#[test]
+fn descendant_with_attribute() {
+    let (_, links) = select(".//ul[@class='items']//a[@href='fn.f.html']");
+    assert_eq!(links, [r#"<a href="fn.f.html">f</a>"#]);
+}
+
+#[test]
+fn attribute_inequality() {
+    let (_, links) = select(".//a[@title!='struct krate::S']");
+    assert_eq!(links, Vec::<String>::new());
+}
+
+#[test]
+fn positions() {
+    assert_eq!(
+        select(".//li[1]/a").1,
+        [r#"<a href="struct.S.html" title="struct krate::S">S</a>"#]
+    );
+    assert_eq!(select(".//li[last()]/a").1, [r#"<a href="fn.g.html">g</a>"#]);
+    assert_eq!(select(".//li[last()-1]/a").1, [r#"<a href="fn.f.html">f</a>"#]);
+}
+
+#[test]
+fn child_text_and_parent() {
+    let (_, items) = select(".//li[a='g']/..");
+    assert_eq!(items.len(), 1);
+    assert!(items[0].starts_with(r#"<ul class="items">"#));
+}
+
+#[test]
+fn void_and_self_closing_elements() {
+    let (_, elements) = select(".//li[br]/*");
+    assert_eq!(elements, [r#"<a href="fn.g.html">g</a>"#, "<br />"]);
+}
+
+#[test]
+fn unsupported_xpath() {
+    assert!(xpath::Path::parse(".//a/text()").is_err());
+    assert!(xpath::Path::parse(".//a[0]").is_err());
+}
+
+#[test]
+fn mismatched_end_tag() {
+    assert!(Document::parse("<div><span></div></span>").is_err());
+}
+
+#[test]
+fn structural_snapshot_comparison() {
+    let actual = r#"<div class="docblock"><p>Some <code>code</code>.</p></div>
+"#;
+    let expected = "<div class=\"docblock\">\n    <p>  Some <code>code</code>.  </p>\n</div>";
+    assert!(super::compare_snapshots(expected, actual).is_none());
+    let wildcards = r#"<div class="docblock"><p>*<code>*</code>*</p></div>"#;
+    assert!(super::compare_snapshots(wildcards, actual).is_none());
+
+    let mismatch =
+        super::compare_snapshots(r#"<div class="item"><p>*<code>*</code>*</p></div>"#, actual)
+            .unwrap();
+    assert_eq!(
+        mismatch.reason,
+        "expected attribute `class` of `<div>` to be `item` but found `docblock`"
+    );
+    assert_eq!(
+        mismatch.actual,
+        "<div class=\"docblock\">\n    <p>\n        Some\n        <code>code</code>\n        .\n    </p>\n</div>\n"
+    );
+}
@@ -0,0 +1,256 @@
+//! The subset of `XPath` supported by Python's `ElementTree` which htmldocck relies on.
+
+use super::html::{Document, Id};
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(super) struct Path {
+    steps: Vec<Step>,
+}
+
+impl Path {
+    pub(super) fn parse(source: &str) -> Result<Self, String> {
+        let unsupported = || format!("unsupported XPath `{source}`");
+
+        // Like in ElementTree, a trailing slash selects all children.
+        let starred;
+        let mut rest = if source.ends_with('/') {
+            starred = format!("{source}*");
+            starred.as_str()
+        } else {
+            source
+        };
+        let mut steps = Vec::new();
+        let mut descendant = false;
+
+        loop {
+            let end = rest.find(['/', '[']).unwrap_or(rest.len());
+            let step = match (&rest[..end], descendant) {
+                (".", false) => Step::Current,
+                ("..", false) => Step::Parent,
+                ("*", descendant) => Step::Element { test: Test::Any, descendant },
+                (name, descendant) if is_name(name) => {
+                    Step::Element { test: Test::Tag(name.to_owned()), descendant }
+                }
+                _ => return Err(unsupported()),
+            };
+            steps.push(step);
+            rest = &rest[end..];
+
+            while let Some(after) = rest.strip_prefix('[') {
+                let end = find_unquoted(after, ']').ok_or_else(unsupported)?;
+                steps.push(Step::Predicate(
+                    Predicate::parse(&after[..end]).ok_or_else(unsupported)?,
+                ));
+                rest = &after[end + 1..];
+            }
+
+            if rest.is_empty() {
+                break;
+            }
+            if let Some(after) = rest.strip_prefix("//") {
+                descendant = true;
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('/') {
+                descendant = false;
+                rest = after;
+            } else {
+                return Err(unsupported());
+            }
+        }
+
+        Ok(Self { steps })
+    }
+
+    /// Select the matching elements relative to the given element in document order.
+    ///
+    /// Like in `ElementTree`, the result may contain duplicates.
+    pub(super) fn select(&self, document: &Document, context: Id) -> Vec<Id> {
+        let mut result = vec![context];
+
+        for step in &self.steps {
+            result = match step {
+                Step::Current => result,
+                Step::Parent => {
+                    let mut parents = Vec::new();
+                    for parent in result.iter().filter_map(|&id| document[id].parent) {
+                        if !parents.contains(&parent) {
+                            parents.push(parent);
+                        }
+                    }
+                    parents
+                }
+                Step::Element { test, descendant: false } => result
+                    .iter()
+                    .flat_map(|&id| document[id].children.iter().copied())
+                    .filter(|&id| test.matches(document, id))
+                    .collect(),
+                Step::Element { test, descendant: true } => result
+                    .iter()
+                    .flat_map(|&id| document.descendants(id))
+                    .filter(|&id| test.matches(document, id))
+                    .collect(),
+                Step::Predicate(predicate) => {
+                    result.into_iter().filter(|&id| predicate.holds(document, id)).collect()
+                }
+            };
+        }
+
+        result
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+enum Step {
+    /// `.`
+    Current,
+    /// `..`
+    Parent,
+    /// `tag`, `*`, `//tag` or `//*`.
+    Element {
+        test: Test,
+        descendant: bool,
+    },
+    Predicate(Predicate),
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+enum Test {
+    Any,
+    Tag(String),
+}
+
+impl Test {
+    fn matches(&self, document: &Document, id: Id) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Tag(tag) => document[id].tag == *tag,
+        }
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+enum Predicate {
+    /// `[@name]`
+    HasAttribute(String),
+    /// `[@name='value']` or `[@name!='value']`.
+    Attribute { name: String, value: String, equal: bool },
+    /// `[tag]`
+    HasChild(String),
+    /// `[tag='text']` or `[tag!='text']`.
+    Child { tag: String, text: String, equal: bool },
+    /// `[.='text']` or `[.!='text']`.
+    Text { text: String, equal: bool },
+    /// `[N]`, `[last()]` or `[last()-N]`.
+    Position(Position),
+}
+
+impl Predicate {
+    fn parse(source: &str) -> Option<Self> {
+        if let Some(number) = source.strip_prefix("last()") {
+            let offset = match number.strip_prefix('-') {
+                Some(offset) => offset.parse().ok().filter(|&offset| offset > 0)?,
+                None if number.is_empty() => 0,
+                None => return None,
+            };
+            return Some(Self::Position(Position::FromEnd(offset)));
+        }
+        if source.starts_with(|char: char| char.is_ascii_digit()) {
+            let position: usize = source.parse().ok().filter(|&position| position > 0)?;
+            return Some(Self::Position(Position::FromStart(position - 1)));
+        }
+
+        let (lhs, comparison) = match find_unquoted(source, '=') {
+            Some(index) => {
+                let (lhs, equal) = match source[..index].strip_suffix('!') {
+                    Some(lhs) => (lhs, false),
+                    None => (&source[..index], true),
+                };
+                (lhs, Some((unquote(&source[index + 1..])?, equal)))
+            }
+            None => (source, None),
+        };
+
+        Some(match (lhs.strip_prefix('@'), comparison) {
+            (Some(name), None) if is_name(name) => Self::HasAttribute(name.to_owned()),
+            (Some(name), Some((value, equal))) if is_name(name) => {
+                Self::Attribute { name: name.to_owned(), value, equal }
+            }
+            (None, Some((text, equal))) if lhs == "." => Self::Text { text, equal },
+            (None, None) if is_name(lhs) => Self::HasChild(lhs.to_owned()),
+            (None, Some((text, equal))) if is_name(lhs) => {
+                Self::Child { tag: lhs.to_owned(), text, equal }
+            }
+            _ => return None,
+        })
+    }
+
+    fn holds(&self, document: &Document, id: Id) -> bool {
+        let element = &document[id];
+        match self {
+            Self::HasAttribute(name) => element.attribute(name).is_some(),
+            // Like in ElementTree, the element has to have the attribute even if negated.
+            Self::Attribute { name, value, equal } => {
+                element.attribute(name).is_some_and(|actual| (actual == value) == *equal)
+            }
+            Self::HasChild(tag) => {
+                element.children.iter().any(|&child| document[child].tag == *tag)
+            }
+            Self::Child { tag, text, equal } => element
+                .children
+                .iter()
+                .filter(|&&child| document[child].tag == *tag)
+                .any(|&child| (document.text(child) == *text) == *equal),
+            Self::Text { text, equal } => (document.text(id) == *text) == *equal,
+            Self::Position(position) => {
+                // Like in ElementTree, the position is relative to the siblings of the same tag.
+                let Some(parent) = element.parent else { return false };
+                let siblings: Vec<_> = document[parent]
+                    .children
+                    .iter()
+                    .copied()
+                    .filter(|&sibling| document[sibling].tag == element.tag)
+                    .collect();
+                let index = match *position {
+                    Position::FromStart(index) => Some(index),
+                    Position::FromEnd(offset) => siblings.len().checked_sub(offset + 1),
+                };
+                index.and_then(|index| siblings.get(index)) == Some(&id)
+            }
+        }
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+enum Position {
+    /// The zero-based index from the start.
+    FromStart(usize),
+    /// The zero-based offset from the end.
+    FromEnd(usize),
+}
+
+fn is_name(source: &str) -> bool {
+    !source.is_empty()
+        && source
+            .chars()
+            .all(|char| char.is_alphanumeric() || matches!(char, '-' | '_' | ':' | '.'))
+        && !source.starts_with('.')
+}
+
+/// Find the given character outside of single or double quotes.
+fn find_unquoted(source: &str, target: char) -> Option<usize> {
+    let mut quote = None;
+    for (index, char) in source.char_indices() {
+        match quote {
+            Some(delimiter) if char == delimiter => quote = None,
+            None if char == target => return Some(index),
+            None if char == '\'' || char == '"' => quote = Some(char),
+            Some(_) | None => {}
+        }
+    }
+    None
+}
+
+fn unquote(source: &str) -> Option<String> {
+    let quote = source.chars().next().filter(|&char| char == '\'' || char == '"')?;
+    source[1..].strip_suffix(quote).filter(|inner| !inner.contains(quote)).map(ToOwned::to_owned)
+}
@@ -0,0 +1,214 @@
+//! Processing the JSON output of rust{,do}c (`--error-format=json`).
+
+use super::Kind;
+use crate::{
+    source::LocalSpan,
+    utility::json::{self, Value},
+};
+use std::{ops::Range, path::Path};
+
+pub(super) struct Output {
+    /// The diagnostics as they would've been rendered by the human-readable emitter.
+    pub(super) rendered: String,
+    pub(super) diagnostics: Vec<Diagnostic>,
+    pub(super) suggestions: Vec<Suggestion>,
+}
+
+pub(super) struct Diagnostic {
+    pub(super) kind: Kind,
+    pub(super) message: String,
+    /// The line inside of the test file the diagnostic points to, if any.
+    pub(super) line: Option<usize>,
+    /// The primary span inside of the test file, if any.
+    pub(super) span: Option<LocalSpan>,
+}
+
+/// The suggestions of a top-level diagnostic.
+///
+/// Like in rustfix, each child diagnostic that suggests replacements constitutes a solution.
+pub(super) struct Suggestion {
+    pub(super) solutions: Vec<Vec<Replacement>>,
+}
+
+pub(super) struct Replacement {
+    /// The byte range inside of the test file.
+    pub(super) range: Range<usize>,
+    pub(super) text: String,
+    pub(super) machine_applicable: bool,
+}
+
+/// Extract the rendered output and the diagnostics pointing into the file at the given path.
+///
+/// Lines that aren't JSON (e.g., panic messages) are preserved as is.
+pub(super) fn process(stderr: &str, path: &Path) -> Output {
+    let mut rendered = String::new();
+    let mut diagnostics = Vec::new();
+    let mut suggestions = Vec::new();
+
+    for line in stderr.lines() {
+        let value = line.starts_with('{').then(|| json::parse(line).ok()).flatten();
+
+        let Some(value) = value else {
+            rendered += line;
+            rendered += "\n";
+            continue;
+        };
+
+        if value.get("$message_type").and_then(Value::as_str) == Some("future_incompat") {
+            render_future_incompat_report(&value, &mut rendered);
+            continue;
+        }
+
+        if let Some(string) = value.get("rendered").and_then(Value::as_str) {
+            rendered += string;
+        }
+
+        collect(&value, &[], path, &mut diagnostics);
+        suggestions.extend(suggestion(&value, path));
+    }
+
+    Output { rendered, diagnostics, suggestions }
+}
+
+/// Render a future incompatibility report exactly like compiletest does.
+fn render_future_incompat_report(value: &Value, rendered: &mut String) {
+    let Some(items) = value.get("future_incompat_report").and_then(Value::as_array) else {
+        return;
+    };
+    if items.is_empty() {
+        return;
+    }
+
+    *rendered += "Future incompatibility report: ";
+    for item in items {
+        *rendered += "Future breakage diagnostic:\n";
+        *rendered += item
+            .get("diagnostic")
+            .and_then(|diagnostic| diagnostic.get("rendered"))
+            .and_then(Value::as_str)
+            .unwrap_or("Not rendered");
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Location {
+    line: usize,
+    span: LocalSpan,
+}
+
+fn collect(value: &Value, parent: &[Location], path: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let (Some(message), Some(level)) =
+        (value.get("message").and_then(Value::as_str), value.get("level").and_then(Value::as_str))
+    else {
+        return;
+    };
+    let spans = value.get("spans").and_then(Value::as_array).unwrap_or_default();
+
+    // Children without any spans inherit the location of their parent.
+    let locations: Vec<_> = if spans.is_empty() {
+        parent.to_vec()
+    } else {
+        spans
+            .iter()
+            .filter(|span| matches!(span.get("is_primary"), Some(Value::Bool(true))))
+            .filter_map(|span| locate(span, path))
+            .collect()
+    };
+
+    let kind = match level {
+        "error" | "error: internal compiler error" => Some(Kind::Error),
+        "warning" => Some(Kind::Warning),
+        "note" => Some(Kind::Note),
+        "help" => Some(Kind::Help),
+        _ => None,
+    };
+
+    if let Some(kind) = kind
+        && !(spans.is_empty() && parent.is_empty() && is_summary(message))
+    {
+        let mut message = message.lines().next().unwrap_or_default().to_owned();
+        if let Some(code) =
+            value.get("code").and_then(|code| code.get("code")).and_then(Value::as_str)
+        {
+            message = format!("{message} [{code}]");
+        }
+
+        if locations.is_empty() {
+            diagnostics.push(Diagnostic { kind, message, line: None, span: None });
+        } else {
+            diagnostics.extend(locations.iter().map(|location| Diagnostic {
+                kind,
+                message: message.clone(),
+                line: Some(location.line),
+                span: Some(location.span),
+            }));
+        }
+    }
+
+    for span in spans {
+        if let Some(replacement) = span.get("suggested_replacement").and_then(Value::as_str)
+            && let Some(location) = locate(span, path)
+        {
+            diagnostics.push(Diagnostic {
+                kind: Kind::Suggestion,
+                message: replacement.to_owned(),
+                line: Some(location.line),
+                span: Some(location.span),
+            });
+        }
+    }
+
+    for child in value.get("children").and_then(Value::as_array).unwrap_or_default() {
+        collect(child, &locations, path, diagnostics);
+    }
+}
+
+/// Locate the span inside of the file at the given path, following the macro backtrace if necessary.
+fn locate(span: &Value, path: &Path) -> Option<Location> {
+    if span.get("file_name").and_then(Value::as_str).is_some_and(|name| Path::new(name) == path) {
+        let start = span.get("byte_start")?.as_usize()?;
+        let end = span.get("byte_end")?.as_usize()?;
+        return Some(Location {
+            line: span.get("line_start")?.as_usize()?,
+            span: LocalSpan::new(start.try_into().ok()?, end.try_into().ok()?),
+        });
+    }
+
+    locate(span.get("expansion")?.get("span")?, path)
+}
+
+fn suggestion(value: &Value, path: &Path) -> Option<Suggestion> {
+    let solutions: Vec<_> = value
+        .get("children")?
+        .as_array()?
+        .iter()
+        .map(|child| {
+            let spans = child.get("spans").and_then(Value::as_array).unwrap_or_default();
+            spans.iter().filter_map(|span| replacement(span, path)).collect::<Vec<_>>()
+        })
+        .filter(|solution| !solution.is_empty())
+        .collect();
+
+    (!solutions.is_empty()).then_some(Suggestion { solutions })
+}
+
+/// Unlike [`locate`], this doesn't follow the macro backtrace since rustfix doesn't either.
+fn replacement(span: &Value, path: &Path) -> Option<Replacement> {
+    if Path::new(span.get("file_name")?.as_str()?) != path {
+        return None;
+    }
+
+    Some(Replacement {
+        range: span.get("byte_start")?.as_usize()?..span.get("byte_end")?.as_usize()?,
+        text: span.get("suggested_replacement")?.as_str()?.to_owned(),
+        machine_applicable: span.get("suggestion_applicability").and_then(Value::as_str)
+            == Some("MachineApplicable"),
+    })
+}
+
+/// Whether the message is one of the final messages summarizing the compilation.
+fn is_summary(message: &str) -> bool {
+    message.starts_with("aborting due to")
+        || message.ends_with(" warning emitted")
+        || message.ends_with(" warnings emitted")
+}
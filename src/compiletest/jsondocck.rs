@@ -0,0 +1,167 @@
+//! An engine for jsondocck directives akin to `src/tools/jsondocck`.
+
+use crate::{
+    directive::{DocCheck, JsonDocCkDirective, Polarity},
+    utility::json::{self, Value},
+};
+use jsonpath::Query;
+use std::{collections::HashMap, fs, path::Path};
+
+mod jsonpath;
+#[cfg(test)]
+mod test;
+
+/// The JSON output of rustdoc together with the variables bound so far.
+pub(super) struct Cache {
+    value: Value,
+    variables: HashMap<String, Value>,
+}
+
+impl Cache {
+    pub(super) fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("failed to read `{}`: {error}", path.display()))?;
+        let value = json::parse(&contents)
+            .map_err(|error| format!("failed to parse `{}`: {error}", path.display()))?;
+        Ok(Self { value, variables: HashMap::new() })
+    }
+
+    /// Interpret the argument as a variable reference (`$name`) or as a JSON value.
+    fn value(&self, source: &str) -> Result<Value, String> {
+        if let Some(name) = source.strip_prefix('$') {
+            return self
+                .variables
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("variable `{name}` is not defined"));
+        }
+        json::parse(source).map_err(|error| format!("invalid JSON value `{source}`: {error}"))
+    }
+}
+
+/// Check the JSON output against the given check, returning the reason on failure.
+pub(super) fn check(
+    &DocCheck { directive, polarity, arguments, .. }: &DocCheck<'_, JsonDocCkDirective>,
+    cache: &mut Cache,
+) -> Result<(), String> {
+    let Some(arguments) = shlex::split(arguments) else {
+        return Err("the arguments are not properly quoted".into());
+    };
+    let arguments: Vec<_> = arguments.iter().map(String::as_str).collect();
+
+    if let (
+        JsonDocCkDirective::Count | JsonDocCkDirective::IsMany | JsonDocCkDirective::Set,
+        Polarity::Negative,
+    ) = (directive, polarity)
+    {
+        return Err(format!("`{}` cannot be negated", name(directive)));
+    }
+
+    let (path, arguments) = match (directive, arguments.as_slice()) {
+        (JsonDocCkDirective::Set, &[name, "=", path]) => {
+            let value = one(&Query::parse(path)?.select(&cache.value))?.clone();
+            if cache.variables.contains_key(name) {
+                return Err(format!("variable `{name}` is already defined"));
+            }
+            cache.variables.insert(name.to_owned(), value);
+            return Ok(());
+        }
+        (JsonDocCkDirective::Set, _) => {
+            return Err("expected arguments of the form `NAME = PATH`".into());
+        }
+        (_, [path, arguments @ ..]) => (path, arguments),
+        (_, []) => return Err("expected a JSONPath".into()),
+    };
+
+    let query = Query::parse(path)?;
+    let matches = query.select(&cache.value);
+
+    match (directive, polarity, arguments) {
+        (JsonDocCkDirective::Has, Polarity::Positive, []) => {
+            if matches.is_empty() {
+                return Err("matched no values".into());
+            }
+        }
+        (JsonDocCkDirective::Has, Polarity::Negative, []) => {
+            if !matches.is_empty() {
+                return Err(format!("matched {}", list(&matches)));
+            }
+        }
+        (JsonDocCkDirective::Has, Polarity::Positive, &[value]) => {
+            let value = cache.value(value)?;
+            if !matches.contains(&&value) {
+                return Err(format!("matched {}, which doesn't contain `{value}`", list(&matches)));
+            }
+        }
+        (JsonDocCkDirective::Has, Polarity::Negative, &[value]) => {
+            let value = cache.value(value)?;
+            if matches.is_empty() {
+                return Err(format!(
+                    "matched no values but expected some (not containing `{value}`)"
+                ));
+            }
+            if matches.contains(&&value) {
+                return Err(format!("matched {}, which contains `{value}`", list(&matches)));
+            }
+        }
+        (JsonDocCkDirective::Is, polarity, &[value]) => {
+            let value = cache.value(value)?;
+            let actual = one(&matches)?;
+            match polarity {
+                Polarity::Positive if *actual != value => {
+                    return Err(format!("matched `{actual}` but expected `{value}`"));
+                }
+                Polarity::Negative if *actual == value => {
+                    return Err(format!("matched `{actual}` but expected anything else"));
+                }
+                _ => {}
+            }
+        }
+        (JsonDocCkDirective::IsMany, _, values) if !values.is_empty() => {
+            let values =
+                values.iter().map(|value| cache.value(value)).collect::<Result<Vec<_>, _>>()?;
+            if values.len() != matches.len() {
+                return Err(format!(
+                    "expected {} values but matched {} ({})",
+                    values.len(),
+                    matches.len(),
+                    list(&matches)
+                ));
+            }
+            if let Some(actual) = matches.iter().find(|&&actual| !values.contains(actual)) {
+                return Err(format!("matched `{actual}`, which wasn't expected"));
+            }
+        }
+        (JsonDocCkDirective::Count, _, &[count]) => {
+            let expected: usize = count.parse().map_err(|_| format!("invalid count `{count}`"))?;
+            if matches.len() != expected {
+                return Err(format!("expected {expected} matches but found {}", matches.len()));
+            }
+        }
+        _ => return Err(format!("invalid number of arguments for `{}`", name(directive))),
+    }
+
+    Ok(())
+}
+
+fn one<'v>(matches: &[&'v Value]) -> Result<&'v Value, String> {
+    match *matches {
+        [] => Err("matched no values".into()),
+        [value] => Ok(value),
+        _ => Err(format!("matched multiple values ({}) but expected exactly one", list(matches))),
+    }
+}
+
+fn list(values: &[&Value]) -> String {
+    values.iter().map(|value| format!("`{value}`")).collect::<Vec<_>>().join(", ")
+}
+
+const fn name(directive: JsonDocCkDirective) -> &'static str {
+    match directive {
+        JsonDocCkDirective::Count => "count",
+        JsonDocCkDirective::Has => "has",
+        JsonDocCkDirective::Is => "is",
+        JsonDocCkDirective::IsMany => "ismany",
+        JsonDocCkDirective::Set => "set",
+    }
+}
@@ -0,0 +1,501 @@
+//! The subset of `JSONPath` (RFC 9535) jsondocck relies on.
+//!
+//! Function extensions like `length()` are not supported.
+
+use crate::utility::json::Value;
+use std::cmp::Ordering;
+
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub(super) struct Query {
+    root: Root,
+    segments: Vec<Segment>,
+}
+
+impl Query {
+    pub(super) fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser { source, index: 0 };
+        let query = parser.parse_query().and_then(|query| {
+            parser.parse_whitespace();
+            match parser.peek() {
+                Some(_) => Err(parser.unexpected()),
+                None => Ok(query),
+            }
+        });
+        query.map_err(|error| format!("invalid JSONPath `{source}`: {error}"))
+    }
+
+    /// Select the matching nodes in document order.
+    pub(super) fn select<'v>(&self, root: &'v Value) -> Vec<&'v Value> {
+        self.select_relative(root, root)
+    }
+
+    fn select_relative<'v>(&self, root: &'v Value, current: &'v Value) -> Vec<&'v Value> {
+        let mut nodes = vec![match self.root {
+            Root::Absolute => root,
+            Root::Current => current,
+        }];
+
+        for segment in &self.segments {
+            let mut selected = Vec::new();
+            for node in nodes {
+                if segment.descendant {
+                    let mut stack = vec![node];
+                    while let Some(node) = stack.pop() {
+                        segment.apply(node, root, &mut selected);
+                        stack.extend(children(node).rev());
+                    }
+                } else {
+                    segment.apply(node, root, &mut selected);
+                }
+            }
+            nodes = selected;
+        }
+
+        nodes
+    }
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+enum Root {
+    /// `$`
+    Absolute,
+    /// `@`
+    Current,
+}
+
+#[cfg_attr(test, derive(PartialEq, Debug))]
+struct Segment {
+    /// Whether the selectors apply to all descendants (`..`).
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+impl Segment {
+    fn apply<'v>(&self, node: &'v Value, root: &'v Value, selected: &mut Vec<&'v Value>) {
+        for selector in &self.selectors {
+            match selector {
+                Selector::Name(name) => selected.extend(node.get(name)),
+                Selector::Wildcard => selected.extend(children(node)),
+                &Selector::Index(index) => {
+                    let elements = node.as_array().unwrap_or_default();
+                    selected.extend(
+                        normalize(index, elements.len()).and_then(|index| elements.get(index)),
+                    );
+                }
+                &Selector::Slice { start, end, step } => {
+                    selected.extend(slice(node.as_array().unwrap_or_default(), start, end, step));
+                }
+                Selector::Filter(expression) => {
+                    selected.extend(children(node).filter(|&child| expression.holds(root, child)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq, Debug))]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice { start: Option<i64>, end: Option<i64>, step: Option<i64> },
+    Filter(Expression),
+}
+
+#[cfg_attr(test, derive(PartialEq, Debug))]
+enum Expression {
+    Or(Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Exists(Query),
+    Comparison(Comparable, Operator, Comparable),
+}
+
+impl Expression {
+    fn holds(&self, root: &Value, current: &Value) -> bool {
+        match self {
+            Self::Or(lhs, rhs) => lhs.holds(root, current) || rhs.holds(root, current),
+            Self::And(lhs, rhs) => lhs.holds(root, current) && rhs.holds(root, current),
+            Self::Not(expression) => !expression.holds(root, current),
+            Self::Exists(query) => !query.select_relative(root, current).is_empty(),
+            Self::Comparison(lhs, operator, rhs) => {
+                let lhs = lhs.evaluate(root, current);
+                let rhs = rhs.evaluate(root, current);
+                match operator {
+                    Operator::Equal => lhs == rhs,
+                    Operator::NotEqual => lhs != rhs,
+                    Operator::Less => less(lhs, rhs),
+                    Operator::LessEqual => less(lhs, rhs) || lhs == rhs,
+                    Operator::Greater => less(rhs, lhs),
+                    Operator::GreaterEqual => less(rhs, lhs) || lhs == rhs,
+                }
+            }
+        }
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq, Debug))]
+enum Comparable {
+    Literal(Value),
+    /// A query that's expected to select at most one node.
+    Query(Query),
+}
+
+impl Comparable {
+    /// The value if any (*nothing* otherwise).
+    fn evaluate<'v>(&'v self, root: &'v Value, current: &'v Value) -> Option<&'v Value> {
+        match self {
+            Self::Literal(value) => Some(value),
+            Self::Query(query) => match *query.select_relative(root, current) {
+                [value] => Some(value),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+fn less(lhs: Option<&Value>, rhs: Option<&Value>) -> bool {
+    match (lhs, rhs) {
+        (Some(Value::Number(lhs)), Some(Value::Number(rhs))) => lhs < rhs,
+        (Some(Value::String(lhs)), Some(Value::String(rhs))) => lhs < rhs,
+        _ => false,
+    }
+}
+
+fn children(node: &Value) -> Box<dyn DoubleEndedIterator<Item = &Value> + '_> {
+    match node {
+        Value::Array(elements) => Box::new(elements.iter()),
+        Value::Object(entries) => Box::new(entries.iter().map(|(_, value)| value)),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+/// Turn a possibly negative index into an index from the start.
+fn normalize(index: i64, length: usize) -> Option<usize> {
+    let length = i64::try_from(length).ok()?;
+    let index = if index < 0 { length + index } else { index };
+    usize::try_from(index).ok()
+}
+
+fn slice(
+    elements: &[Value],
+    start: Option<i64>,
+    end: Option<i64>,
+    step: Option<i64>,
+) -> Vec<&Value> {
+    let Ok(length) = i64::try_from(elements.len()) else { return Vec::new() };
+    let step = step.unwrap_or(1);
+    let bound = |index: i64| if index < 0 { length + index } else { index };
+    let element = |index: i64| &elements[usize::try_from(index).unwrap()];
+
+    match step.cmp(&0) {
+        Ordering::Greater => {
+            let start = bound(start.unwrap_or(0)).clamp(0, length);
+            let end = bound(end.unwrap_or(length)).clamp(0, length);
+            (start..end).step_by(step.unsigned_abs().try_into().unwrap()).map(element).collect()
+        }
+        Ordering::Less => {
+            let start = bound(start.unwrap_or(length - 1)).clamp(-1, length - 1);
+            let end = end.map_or(-1, |end| bound(end).clamp(-1, length - 1));
+            let mut selected = Vec::new();
+            let mut index = start;
+            while index > end {
+                selected.push(element(index));
+                index += step;
+            }
+            selected
+        }
+        Ordering::Equal => Vec::new(),
+    }
+}
+
+struct Parser<'src> {
+    source: &'src str,
+    index: usize,
+}
+
+impl<'src> Parser<'src> {
+    fn parse_query(&mut self) -> Result<Query, String> {
+        let root = match self.peek() {
+            Some('$') => Root::Absolute,
+            Some('@') => Root::Current,
+            _ => return Err(self.unexpected()),
+        };
+        self.advance();
+
+        let mut segments = Vec::new();
+        loop {
+            // Whitespace may only precede a segment.
+            let index = self.index;
+            self.parse_whitespace();
+
+            if self.consume("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.parse_bracketed()?,
+                    _ => vec![self.parse_shorthand()?],
+                };
+                segments.push(Segment { descendant: true, selectors });
+            } else if self.consume(".") {
+                segments
+                    .push(Segment { descendant: false, selectors: vec![self.parse_shorthand()?] });
+            } else if let Some('[') = self.peek() {
+                segments.push(Segment { descendant: false, selectors: self.parse_bracketed()? });
+            } else {
+                self.index = index;
+                break;
+            }
+        }
+
+        Ok(Query { root, segments })
+    }
+
+    fn parse_shorthand(&mut self) -> Result<Selector, String> {
+        if self.consume("*") {
+            return Ok(Selector::Wildcard);
+        }
+        let name = self.parse_name();
+        if name.is_empty() || name.starts_with(|char: char| char.is_ascii_digit()) {
+            return Err(self.unexpected());
+        }
+        Ok(Selector::Name(name.to_owned()))
+    }
+
+    fn parse_bracketed(&mut self) -> Result<Vec<Selector>, String> {
+        self.expect("[")?;
+        let mut selectors = Vec::new();
+        loop {
+            self.parse_whitespace();
+            selectors.push(self.parse_selector()?);
+            self.parse_whitespace();
+            if self.consume("]") {
+                return Ok(selectors);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, String> {
+        match self.peek() {
+            Some('\'' | '"') => self.parse_string().map(Selector::Name),
+            Some('*') => {
+                self.advance();
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.advance();
+                self.parse_whitespace();
+                self.parse_or().map(Selector::Filter)
+            }
+            _ => {
+                let start = self.parse_integer()?;
+                self.parse_whitespace();
+                if !self.consume(":") {
+                    return start.map(Selector::Index).ok_or_else(|| self.unexpected());
+                }
+                self.parse_whitespace();
+                let end = self.parse_integer()?;
+                self.parse_whitespace();
+                let step = if self.consume(":") {
+                    self.parse_whitespace();
+                    self.parse_integer()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_and()?;
+        while self.consume_padded("||") {
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_basic()?;
+        while self.consume_padded("&&") {
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_basic()?));
+        }
+        Ok(expression)
+    }
+
+    fn parse_basic(&mut self) -> Result<Expression, String> {
+        self.parse_whitespace();
+
+        if self.consume("!") {
+            self.parse_whitespace();
+            return self.parse_basic().map(|expression| Expression::Not(Box::new(expression)));
+        }
+        if self.consume("(") {
+            let expression = self.parse_or()?;
+            self.parse_whitespace();
+            self.expect(")")?;
+            return Ok(expression);
+        }
+
+        let lhs = self.parse_comparable()?;
+        let index = self.index;
+        self.parse_whitespace();
+
+        let operators = [
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<=", Operator::LessEqual),
+            (">=", Operator::GreaterEqual),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+        ];
+        if let Some(&(_, operator)) = operators.iter().find(|(token, _)| self.consume(token)) {
+            self.parse_whitespace();
+            let rhs = self.parse_comparable()?;
+            return Ok(Expression::Comparison(lhs, operator, rhs));
+        }
+
+        self.index = index;
+        match lhs {
+            Comparable::Query(query) => Ok(Expression::Exists(query)),
+            Comparable::Literal(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_comparable(&mut self) -> Result<Comparable, String> {
+        Ok(match self.peek() {
+            Some('$' | '@') => Comparable::Query(self.parse_query()?),
+            Some('\'' | '"') => Comparable::Literal(Value::String(self.parse_string()?)),
+            Some('-' | '0'..='9') => {
+                let start = self.index;
+                self.advance_while(|char| matches!(char, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'));
+                let number = self.source[start..self.index]
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}`", &self.source[start..self.index]))?;
+                Comparable::Literal(Value::Number(number))
+            }
+            _ => {
+                let start = self.index;
+                let literal = match self.parse_name() {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "null" => Value::Null,
+                    "" => return Err(self.unexpected()),
+                    name => {
+                        return Err(format!(
+                            "unsupported function or literal `{name}` at byte {start}"
+                        ));
+                    }
+                };
+                Comparable::Literal(literal)
+            }
+        })
+    }
+
+    fn parse_integer(&mut self) -> Result<Option<i64>, String> {
+        let start = self.index;
+        self.consume("-");
+        self.advance_while(|char| char.is_ascii_digit());
+        let integer = &self.source[start..self.index];
+        if integer.is_empty() {
+            return Ok(None);
+        }
+        integer.parse().map(Some).map_err(|_| format!("invalid integer `{integer}`"))
+    }
+
+    /// Parse a single- or double-quoted string.
+    fn parse_string(&mut self) -> Result<String, String> {
+        let Some(quote) = self.peek() else { return Err(self.unexpected()) };
+        self.advance();
+
+        let mut string = String::new();
+        loop {
+            let Some(char) = self.peek() else { return Err(self.unexpected()) };
+            self.advance();
+            match char {
+                char if char == quote => return Ok(string),
+                '\\' => {
+                    let Some(char) = self.peek() else { return Err(self.unexpected()) };
+                    self.advance();
+                    string.push(match char {
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        char => char,
+                    });
+                }
+                char => string.push(char),
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> &'src str {
+        let start = self.index;
+        self.advance_while(|char| char.is_alphanumeric() || char == '_' || !char.is_ascii());
+        &self.source[start..self.index]
+    }
+
+    fn parse_whitespace(&mut self) {
+        self.advance_while(char::is_whitespace);
+    }
+
+    fn consume_padded(&mut self, token: &str) -> bool {
+        let index = self.index;
+        self.parse_whitespace();
+        if self.consume(token) {
+            return true;
+        }
+        self.index = index;
+        false
+    }
+
+    fn consume(&mut self, token: &str) -> bool {
+        if self.source[self.index..].starts_with(token) {
+            self.index += token.len();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        if self.consume(token) { Ok(()) } else { Err(self.unexpected()) }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.index..].chars().next()
+    }
+
+    fn advance(&mut self) {
+        if let Some(char) = self.peek() {
+            self.index += char.len_utf8();
+        }
+    }
+
+    fn advance_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while let Some(char) = self.peek()
+            && predicate(char)
+        {
+            self.advance();
+        }
+    }
+
+    fn unexpected(&self) -> String {
+        match self.peek() {
+            Some(char) => format!("unexpected character `{char}` at byte {}", self.index),
+            None => "unexpected end of input".into(),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use super::*;
+use crate::source::Span;
+
+const SOURCE: &str = r#"{
+    "root": 0,
+    "index": {
+        "0": {"id": 0, "name": "krate", "inner": {"module": {"items": [1, 2]}}},
+        "1": {"id": 1, "name": "S", "visibility": "public", "inner": {"struct": {"impls": []}}},
+        "2": {"id": 2, "name": "f", "visibility": "public", "inner": {"function": {"sig": {"inputs": [["x", 1]]}}}}
+    }
+}"#;
+
+fn select(path: &str) -> Vec<String> {
+    let value = json::parse(SOURCE).unwrap();
+    Query::parse(path).unwrap().select(&value).into_iter().map(ToString::to_string).collect()
+}
+
+fn run(checks: &[(JsonDocCkDirective, Polarity, &str)]) -> Result<(), String> {
+    let mut cache = Cache { value: json::parse(SOURCE).unwrap(), variables: HashMap::new() };
+    checks.iter().try_for_each(|&(directive, polarity, arguments)| {
+        check(&DocCheck { directive, polarity, span: Span::SHAM, arguments }, &mut cache)
+    })
+}
+
+#[test]
+fn filter() {
+    assert_eq!(select("$.index[?(@.name=='S')].id"), ["1"]);
+    assert_eq!(select("$.index[?(@.inner.function)].name"), [r#""f""#]);
+    assert_eq!(select("$.index[?@.id > 0 && !(@.name == 'f')].name"), [r#""S""#]);
+}
+
+#[test]
+fn wildcard_and_indices() {
+    assert_eq!(select("$.index.*.id"), ["0", "1", "2"]);
+    assert_eq!(select("$.index['0'].inner.module.items[-1]"), ["2"]);
+    assert_eq!(select("$.index['0'].inner.module.items[::-1]"), ["2", "1"]);
+}
+
+#[test]
+fn descendants() {
+    assert_eq!(select("$..visibility"), [r#""public""#, r#""public""#]);
+}
+
+#[test]
+fn invalid_query() {
+    assert!(Query::parse("$.index[?length(@.name) == 1]").is_err());
+    assert!(Query::parse("index").is_err());
+}
+
+#[test]
+fn variables() {
+    assert_eq!(
+        run(&[
+            (JsonDocCkDirective::Set, Polarity::Positive, r#"s = "$.index[?(@.name=='S')].id""#),
+            (JsonDocCkDirective::Has, Polarity::Positive, "'$.index[*].inner.module.items[*]' $s"),
+            (
+                JsonDocCkDirective::Is,
+                Polarity::Positive,
+                r#""$.index[?(@.name=='f')].inner.function.sig.inputs[0][1]" $s"#
+            ),
+        ]),
+        Ok(())
+    );
+}
+
+#[test]
+fn failures() {
+    assert_eq!(
+        run(&[(JsonDocCkDirective::Is, Polarity::Positive, "$.index.*.name '\"S\"'")]),
+        Err(r#"matched multiple values (`"krate"`, `"S"`, `"f"`) but expected exactly one"#.into())
+    );
+    assert_eq!(
+        run(&[(JsonDocCkDirective::Count, Polarity::Positive, "$.index.*.visibility 3")]),
+        Err("expected 3 matches but found 2".into())
+    );
+    assert_eq!(
+        run(&[(JsonDocCkDirective::Has, Polarity::Negative, "$.index.*.name '\"f\"'")]),
+        Err(r#"matched `"krate"`, `"S"`, `"f"`, which contains `"f"`"#.into())
+    );
+}
@@ -0,0 +1,116 @@
+//! The normalization of output akin to compiletest.
+
+use super::{Origin, Stream};
+use crate::{
+    context::Context,
+    diagnostic::{error, fmt},
+    directive::Normalization,
+    error::Result,
+};
+use regex_lite::Regex;
+use std::{borrow::Cow, path::Path};
+
+#[cfg(test)]
+mod test;
+
+pub(super) struct Normalizer {
+    builtin: Vec<(Regex, String)>,
+    /// Rules that emulate the way `-Zui-testing` renders the gutter of diagnostics
+    /// for engines that don't accept it (i.e., stable and beta ones).
+    gutter: Vec<(Regex, String)>,
+    custom: Vec<(Stream, Regex, String)>,
+}
+
+impl Normalizer {
+    /// Create a normalizer for the test at the given absolute path whose artifacts are placed
+    /// in the given absolute build directory.
+    pub(super) fn new(
+        path: &Path,
+        build_dir: &Path,
+        normalizations: &[Normalization<'_>],
+        ui_testing: bool,
+        cx: Context<'_>,
+    ) -> Result<Self> {
+        let mut builtin = vec![(Regex::new("\r\n").unwrap(), "\n".into())];
+
+        // The order matters: The directory of the test might well be inside of the build directory.
+        if let Some(dir) = path.parent() {
+            builtin.push(literal(dir, "$DIR"));
+        }
+        builtin.extend([
+            // The remapped path prefix of the standard library in distributed toolchains.
+            (Regex::new("/rustc/[0-9a-f]+/library").unwrap(), "$$SRC_DIR".into()),
+            // The local path of the standard library if component `rust-src` is installed.
+            (Regex::new(r"[^\s'`]*/lib/rustlib/src/rust/library").unwrap(), "$$SRC_DIR".into()),
+            // The line numbers inside of the standard library are highly volatile.
+            (Regex::new(r"\$SRC_DIR(\S+?):\d+:\d+").unwrap(), "$$SRC_DIR${1}:LL:COL".into()),
+            // The ID of a panicking thread.
+            (
+                Regex::new(r"(?m)^thread '(.*?)' \(\d+\) panicked").unwrap(),
+                "thread '$1' ($$TID) panicked".into(),
+            ),
+        ]);
+        builtin.push(literal(build_dir, "$TEST_BUILD_DIR"));
+
+        // Under `-Zui-testing`, the engine renders the gutter like this by itself.
+        let gutter = if ui_testing {
+            Vec::new()
+        } else {
+            [
+                (r"(?m)^ *\d+ \|", "LL |"),
+                // Suggestions rendered as diffs.
+                (r"(?m)^ *\d+ ([-+~]) ", "LL $1 "),
+                (r"(?m)^ +\|", "   |"),
+                (r"(?m)^ +--> ", "  --> "),
+                (r"(?m)^ +::: ", "  ::: "),
+                (r"(?m)^ += ", "   = "),
+            ]
+            .into_iter()
+            .map(|(regex, replacement)| (Regex::new(regex).unwrap(), replacement.into()))
+            .collect()
+        };
+
+        let custom = normalizations
+            .iter()
+            .map(|&Normalization { stream, regex, replacement }| match Regex::new(regex.bare) {
+                Ok(compiled) => Ok((stream, compiled, replacement.replace("\\n", "\n"))),
+                Err(error) => Err(self::error(fmt!("invalid regular expression"))
+                    .highlight(regex.span, cx)
+                    .note(fmt!("{error}"))
+                    .done()
+                    .into()),
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { builtin, gutter, custom })
+    }
+
+    pub(super) fn normalize(&self, origin: Origin, stream: Stream, output: &str) -> String {
+        let mut output = output.to_owned();
+
+        let mut apply = |regex: &Regex, replacement: &str| {
+            if let Cow::Owned(result) = regex.replace_all(&output, replacement) {
+                output = result;
+            }
+        };
+
+        self.builtin.iter().for_each(|(regex, replacement)| apply(regex, replacement));
+
+        if let (Origin::Compiler, Stream::Stderr) = (origin, stream) {
+            self.gutter.iter().for_each(|(regex, replacement)| apply(regex, replacement));
+        }
+
+        // Like in compiletest, custom rules get applied last.
+        self.custom
+            .iter()
+            .filter(|&&(target, ..)| target == stream)
+            .for_each(|(_, regex, replacement)| apply(regex, replacement));
+
+        output
+    }
+}
+
+fn literal(path: &Path, replacement: &str) -> (Regex, String) {
+    let regex = Regex::new(&regex_lite::escape(&path.to_string_lossy())).unwrap();
+    (regex, replacement.replace('$', "$$"))
+}
@@ -0,0 +1,73 @@
+use super::Normalizer;
+use crate::{
+    build::DebugOptions,
+    compiletest::{Origin, Stream},
+    context,
+};
+use std::{num::NonZero, path::Path};
+
+const STDERR: &str = "\
+error[E0308]: mismatched types
+  --> /tests/ui/test.rs:12:5
+   |
+11 | fn f() -> u8 {
+   |           -- expected `u8` because of return type
+12 |     ()
+   |     ^^ expected `u8`, found `()`
+   |
+help: try this
+   |
+12 -     ()
+12 +     0
+   |
+   = note: some note
+";
+
+const NORMALIZED: &str = "\
+error[E0308]: mismatched types
+  --> $DIR/test.rs:12:5
+   |
+LL | fn f() -> u8 {
+   |           -- expected `u8` because of return type
+LL |     ()
+   |     ^^ expected `u8`, found `()`
+   |
+help: try this
+   |
+LL -     ()
+LL +     0
+   |
+   = note: some note
+";
+
+fn normalize(ui_testing: bool, origin: Origin, output: &str) -> String {
+    let cx = context::new!(context::Options {
+        toolchain: None,
+        dbg_opts: DebugOptions { verbose: false },
+        jobs: NonZero::<usize>::MIN,
+    });
+    let Ok(normalizer) =
+        Normalizer::new(Path::new("/tests/ui/test.rs"), Path::new("/build"), &[], ui_testing, cx)
+    else {
+        panic!()
+    };
+    normalizer.normalize(origin, Stream::Stderr, output)
+}
+
+#[test]
+fn gutter_gets_emulated_without_ui_testing() {
+    assert_eq!(normalize(false, Origin::Compiler, STDERR), NORMALIZED);
+}
+
+#[test]
+fn gutter_is_left_alone_under_ui_testing() {
+    // The engine already renders the gutter like this.
+    assert_eq!(normalize(true, Origin::Compiler, NORMALIZED), NORMALIZED);
+    // Any remaining line numbers aren't part of a gutter and must not be touched.
+    assert_eq!(normalize(true, Origin::Compiler, "12 | not a gutter\n"), "12 | not a gutter\n");
+}
+
+#[test]
+fn gutter_of_binary_output_is_left_alone() {
+    assert_eq!(normalize(false, Origin::Binary, "12 | output\n"), "12 | output\n");
+}
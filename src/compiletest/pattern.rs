@@ -0,0 +1,76 @@
+//! The checking of output against `error-pattern`, `regex-error-pattern` and `forbid-output`.
+
+use crate::{
+    context::Context,
+    diagnostic::{error, fmt},
+    directive::{Pattern, PatternKind, Polarity},
+    error::Result,
+    source::Span,
+};
+use regex_lite::Regex;
+
+#[cfg(test)]
+mod test;
+
+pub(super) struct Matcher {
+    patterns: Vec<(Span, Kind, Polarity)>,
+}
+
+enum Kind {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub(super) fn new(patterns: &[Pattern<'_>], cx: Context<'_>) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|&Pattern { source, kind, polarity }| {
+                let kind = match kind {
+                    PatternKind::Substring => Kind::Substring(source.bare.to_owned()),
+                    PatternKind::Regex => match Regex::new(source.bare) {
+                        Ok(regex) => Kind::Regex(regex),
+                        Err(error) => {
+                            return Err(self::error(fmt!("invalid regular expression"))
+                                .highlight(source.span, cx)
+                                .note(fmt!("{error}"))
+                                .done()
+                                .into());
+                        }
+                    },
+                };
+                Ok((source.span, kind, polarity))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// Whether there are any `error-pattern`s or `regex-error-pattern`s.
+    pub(super) fn expects_errors(&self) -> bool {
+        self.patterns.iter().any(|(_, _, polarity)| matches!(polarity, Polarity::Positive))
+    }
+
+    /// Check the given output against all patterns, returning the number of failures.
+    pub(super) fn check(&self, output: &str, subject: &str, cx: Context<'_>) -> usize {
+        let mut failures = 0;
+
+        for (span, kind, polarity) in &self.patterns {
+            let found = match kind {
+                Kind::Substring(substring) => output.contains(substring.as_str()),
+                Kind::Regex(regex) => regex.is_match(output),
+            };
+
+            let message = match (polarity, found) {
+                (Polarity::Positive, true) | (Polarity::Negative, false) => continue,
+                (Polarity::Positive, false) => "expected pattern not found",
+                (Polarity::Negative, true) => "forbidden pattern found",
+            };
+
+            failures += 1;
+            error(fmt!("{message} in the output of the {subject}")).highlight(*span, cx).done();
+        }
+
+        failures
+    }
+}
@@ -0,0 +1,103 @@
+use super::Matcher;
+use crate::{
+    build::DebugOptions,
+    context::{self, Context},
+    directive::{Pattern, PatternKind, Polarity},
+    source::Spanned,
+    utility::jobs,
+};
+use std::num::NonZero;
+
+const OUTPUT: &str = "\
+error[E0425]: cannot find value `x` in this scope
+warning: unused variable: `y`
+";
+
+fn pattern(source: &str, kind: PatternKind, polarity: Polarity) -> Pattern<'_> {
+    Pattern { source: Spanned::sham(source), kind, polarity }
+}
+
+/// Check the output against the patterns, returning the number of failures and the diagnostics.
+fn check(patterns: &[Pattern<'_>], output: &str, cx: Context<'_>) -> (usize, String) {
+    let Ok(matcher) = Matcher::new(patterns, cx) else { panic!() };
+    jobs::capture_stderr(|| matcher.check(output, "compiler", cx))
+}
+
+macro cx() {
+    context::new!(context::Options {
+        toolchain: None,
+        dbg_opts: DebugOptions { verbose: false },
+        jobs: NonZero::<usize>::MIN,
+    })
+}
+
+#[test]
+fn present_required_patterns() {
+    let cx = cx!();
+    let patterns = [
+        pattern("cannot find value `x`", PatternKind::Substring, Polarity::Positive),
+        pattern(r"E0\d{3}", PatternKind::Regex, Polarity::Positive),
+        pattern(r"(?m)^warning: unused", PatternKind::Regex, Polarity::Positive),
+    ];
+    assert_eq!(check(&patterns, OUTPUT, cx), (0, String::new()));
+}
+
+#[test]
+fn missing_required_patterns() {
+    let cx = cx!();
+    let patterns = [
+        pattern("cannot find value `z`", PatternKind::Substring, Polarity::Positive),
+        pattern(r"E1\d{3}", PatternKind::Regex, Polarity::Positive),
+        pattern("cannot find value", PatternKind::Substring, Polarity::Positive),
+    ];
+    let (failures, stderr) = check(&patterns, OUTPUT, cx);
+    assert_eq!(failures, 2);
+    assert_eq!(
+        stderr.matches("error: expected pattern not found in the output of the compiler").count(),
+        2
+    );
+}
+
+#[test]
+fn present_forbidden_patterns() {
+    let cx = cx!();
+    let patterns = [
+        pattern("unused variable", PatternKind::Substring, Polarity::Negative),
+        pattern("unused import", PatternKind::Substring, Polarity::Negative),
+    ];
+    let (failures, stderr) = check(&patterns, OUTPUT, cx);
+    assert_eq!(failures, 1);
+    assert!(stderr.starts_with("error: forbidden pattern found in the output of the compiler"));
+}
+
+#[test]
+fn regex_patterns_arent_substrings() {
+    let cx = cx!();
+    // The parentheses form a group instead of being matched literally.
+    let patterns = [pattern("value `(x)`", PatternKind::Substring, Polarity::Positive)];
+    assert_eq!(check(&patterns, OUTPUT, cx).0, 1);
+    let patterns = [pattern("value `(x)`", PatternKind::Regex, Polarity::Positive)];
+    assert_eq!(check(&patterns, OUTPUT, cx).0, 0);
+}
+
+#[test]
+fn invalid_regex() {
+    let cx = cx!();
+    let patterns = [pattern("(unclosed", PatternKind::Regex, Polarity::Positive)];
+    let (result, stderr) = jobs::capture_stderr(|| Matcher::new(&patterns, cx));
+    assert!(result.is_err());
+    assert!(stderr.starts_with("error: invalid regular expression"), "{stderr}");
+}
+
+#[test]
+fn errors_are_expected_only_by_required_patterns() {
+    let cx = cx!();
+    let expects_errors = |patterns: &[Pattern<'_>]| {
+        let Ok(matcher) = Matcher::new(patterns, cx) else { panic!() };
+        matcher.expects_errors()
+    };
+
+    assert!(!expects_errors(&[]));
+    assert!(!expects_errors(&[pattern("a", PatternKind::Substring, Polarity::Negative)]));
+    assert!(expects_errors(&[pattern("a", PatternKind::Regex, Polarity::Positive)]));
+}
@@ -0,0 +1,58 @@
+//! The application of suggestions akin to rustfix.
+
+use super::json::{Replacement, Suggestion};
+use crate::directive::Rustfix;
+use std::ops::Range;
+
+#[cfg(test)]
+mod test;
+
+/// Apply the given suggestions to the source code.
+///
+/// Like rustfix, this applies all solutions of a suggestion, not just the first one.
+/// Identical replacements are only applied once, overlapping ones result in an error.
+pub(super) fn apply(
+    source: &str,
+    suggestions: &[Suggestion],
+    rustfix: Rustfix,
+) -> Result<String, Conflict> {
+    let mut replacements: Vec<&Replacement> = suggestions
+        .iter()
+        .flat_map(|suggestion| &suggestion.solutions)
+        .flat_map(|solution| {
+            let replacements: Vec<_> = solution
+                .iter()
+                .filter(|replacement| match rustfix {
+                    Rustfix::All => true,
+                    Rustfix::MachineApplicable => replacement.machine_applicable,
+                })
+                .collect();
+            replacements
+        })
+        .collect();
+
+    replacements.sort_by_key(|replacement| (replacement.range.start, replacement.range.end));
+    replacements
+        .dedup_by(|next, previous| next.range == previous.range && next.text == previous.text);
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut cursor = 0;
+
+    for replacement in replacements {
+        let Range { start, end } = replacement.range;
+        if start < cursor || source.get(start..end).is_none() {
+            return Err(Conflict(replacement.range.clone()));
+        }
+        fixed += &source[cursor..start];
+        fixed += &replacement.text;
+        cursor = end;
+    }
+
+    fixed += &source[cursor..];
+
+    Ok(fixed)
+}
+
+/// A replacement that overlaps with a previous one or that's out of bounds.
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(super) struct Conflict(pub(super) Range<usize>);
@@ -0,0 +1,46 @@
+use super::*;
+
+fn replacement(range: Range<usize>, text: &str, machine_applicable: bool) -> Replacement {
+    Replacement { range, text: text.into(), machine_applicable }
+}
+
+#[test]
+fn apply_all() {
+    let suggestions = [
+        Suggestion { solutions: vec![vec![replacement(8..10, "u8", true)]] },
+        Suggestion { solutions: vec![vec![replacement(0..0, "pub ", false)]] },
+    ];
+    assert_eq!(
+        apply("fn f(_: i8) {}", &suggestions, Rustfix::All),
+        Ok("pub fn f(_: u8) {}".into())
+    );
+}
+
+#[test]
+fn apply_machine_applicable_only() {
+    let suggestions = [
+        Suggestion { solutions: vec![vec![replacement(8..10, "u8", true)]] },
+        Suggestion { solutions: vec![vec![replacement(0..0, "pub ", false)]] },
+    ];
+    assert_eq!(
+        apply("fn f(_: i8) {}", &suggestions, Rustfix::MachineApplicable),
+        Ok("fn f(_: u8) {}".into())
+    );
+}
+
+#[test]
+fn apply_identical() {
+    let suggestions = [
+        Suggestion { solutions: vec![vec![replacement(4..5, "_x", true)]] },
+        Suggestion { solutions: vec![vec![replacement(4..5, "_x", true)]] },
+    ];
+    assert_eq!(apply("let x;", &suggestions, Rustfix::All), Ok("let _x;".into()));
+}
+
+#[test]
+fn apply_overlapping() {
+    let suggestions = [Suggestion {
+        solutions: vec![vec![replacement(0..5, "a", true)], vec![replacement(3..6, "b", true)]],
+    }];
+    assert_eq!(apply("abcdefgh", &suggestions, Rustfix::All), Err(Conflict(3..6)));
+}
@@ -0,0 +1,190 @@
+use super::{Checker, Stream};
+use crate::{
+    build::DebugOptions,
+    context::{self, Context},
+    directive::{self, Flavor, Role, Scope},
+    operate::Bless,
+    source::{SourcePath, Spanned},
+    utility::{jobs, testing::TempDir},
+};
+use std::{
+    fs,
+    num::NonZero,
+    path::Path,
+    process::{ExitStatus, Output},
+};
+
+macro cx() {
+    context::new!(context::Options {
+        toolchain: None,
+        dbg_opts: DebugOptions { verbose: false },
+        jobs: NonZero::<usize>::MIN,
+    })
+}
+
+fn checker<'a>(path: &'a Path, bless: Bless, cx: Context<'_>) -> Checker<'a> {
+    let path = SourcePath::Regular(path);
+    let flavor = Flavor::Vanilla;
+    let Ok(directives) =
+        directive::gather(Spanned::sham(path), Scope::Base, Role::Principal, flavor, None, cx)
+    else {
+        panic!()
+    };
+    let Ok(checker) = Checker::new(path, None, bless, &directives, Scope::Base, None, false, cx)
+    else {
+        panic!()
+    };
+    checker
+}
+
+fn output(stdout: &str, stderr: &str) -> Output {
+    Output { status: ExitStatus::default(), stdout: stdout.into(), stderr: stderr.into() }
+}
+
+#[test]
+fn patterns_of_run_tests_apply_to_the_binary() {
+    let cx = cx!();
+    let dir = TempDir::new();
+    let path = dir.write("test.rs", "//@ run-pass\n//@ error-pattern: hello from the binary\n");
+    let mut checker = checker(&path, Bless::No, cx);
+
+    // The output of the compiler doesn't matter.
+    checker.check_output("", "", cx);
+    assert_eq!(checker.failures, 0);
+
+    assert!(checker.check_execution(&output("hello from the binary\n", ""), cx).is_ok());
+    assert_eq!(checker.failures, 0);
+    assert!(checker.check_execution(&output("", "hello from the binary\n"), cx).is_ok());
+    assert_eq!(checker.failures, 0);
+    let (result, stderr) = jobs::capture_stderr(|| {
+        checker.check_execution(&output("hello from the compiler\n", ""), cx)
+    });
+    assert!(result.is_ok());
+    assert_eq!(checker.failures, 1);
+    assert!(
+        stderr.starts_with("error: expected pattern not found in the output of the test binary")
+    );
+}
+
+#[test]
+fn patterns_of_other_tests_apply_to_the_compiler() {
+    let cx = cx!();
+    let dir = TempDir::new();
+    let path = dir.write("test.rs", "//@ check-pass\n//@ forbid-output: unused\n");
+    let mut checker = checker(&path, Bless::No, cx);
+
+    let ((), stderr) =
+        jobs::capture_stderr(|| checker.check_output("", "warning: unused variable\n", cx));
+    assert_eq!(checker.failures, 1);
+    assert!(stderr.starts_with("error: forbidden pattern found in the output of the compiler"));
+}
+
+#[test]
+fn blessing_creates_updates_and_removes_expectations() {
+    let cx = cx!();
+    let dir = TempDir::new();
+    let path = dir.write("test.rs", "");
+    let expectation = dir.path().join("test.stderr");
+    let mut checker = checker(&path, Bless::Yes, cx);
+    let mut compare = |actual| {
+        let (result, stderr) = jobs::capture_stderr(|| checker.compare(Stream::Stderr, actual));
+        assert!(result.is_ok());
+        stderr
+    };
+
+    // An empty output doesn't need an expectation file.
+    assert_eq!(compare(""), "");
+    assert!(!expectation.exists());
+
+    assert!(compare("error: first\n").starts_with("info: created"));
+    assert_eq!(fs::read_to_string(&expectation).unwrap(), "error: first\n");
+
+    assert_eq!(compare("error: first\n"), "");
+
+    assert!(compare("error: second\n").starts_with("info: updated"));
+    assert_eq!(fs::read_to_string(&expectation).unwrap(), "error: second\n");
+
+    assert!(compare("").starts_with("info: removed"));
+    assert!(!expectation.exists());
+
+    assert_eq!(checker.failures, 0);
+}
+
+#[test]
+fn mismatches_fail_unless_blessed() {
+    let cx = cx!();
+    let dir = TempDir::new();
+    let path = dir.write("test.rs", "");
+    let expectation = dir.write("test.stderr", "error: expected\n");
+    let mut checker = checker(&path, Bless::No, cx);
+
+    let (result, stderr) =
+        jobs::capture_stderr(|| checker.compare(Stream::Stderr, "error: actual\n"));
+    assert!(result.is_ok());
+    assert_eq!(checker.failures, 1);
+    assert!(stderr.contains("-error: expected\n+error: actual\n"), "{stderr}");
+    assert_eq!(fs::read_to_string(&expectation).unwrap(), "error: expected\n");
+
+    // A missing expectation file is equivalent to an empty one.
+    let (result, _) = jobs::capture_stderr(|| checker.compare(Stream::Stdout, "output\n"));
+    assert!(result.is_ok());
+    assert_eq!(checker.failures, 2);
+    assert!(!dir.path().join("test.stdout").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn run_fail_checks_the_failure_status() {
+    use std::os::unix::process::ExitStatusExt as _;
+
+    let cx = cx!();
+    let dir = TempDir::new();
+    let path = dir.write("test.rs", "//@ run-fail\n//@ failure-status: 3\n");
+    let mut checker = checker(&path, Bless::No, cx);
+    let exited = |code| Output { status: ExitStatus::from_raw(code << 8), ..output("", "") };
+
+    assert!(checker.check_execution(&exited(3), cx).is_ok());
+    assert_eq!(checker.failures, 0);
+
+    let (result, stderr) = jobs::capture_stderr(|| checker.check_execution(&exited(1), cx));
+    assert!(result.is_ok());
+    assert_eq!(checker.failures, 1);
+    assert!(stderr.starts_with("error: the test exited with an unexpected status"), "{stderr}");
+    assert!(stderr.contains("note: expected exit status: 3"), "{stderr}");
+    assert!(stderr.contains("note: exit status: 1"), "{stderr}");
+
+    let (result, stderr) = jobs::capture_stderr(|| checker.check_execution(&exited(0), cx));
+    assert!(result.is_ok());
+    assert_eq!(checker.failures, 2);
+    assert!(
+        stderr.starts_with("error: the test binary succeeded but it was expected to fail"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn check_run_results_compares_the_output_of_the_binary() {
+    let cx = cx!();
+    let dir = TempDir::new();
+    let path = dir.write("test.rs", "//@ run-pass\n//@ check-run-results\n");
+    dir.write("test.run.stdout", "hello\n");
+    let mut checker = checker(&path, Bless::No, cx);
+
+    assert!(checker.check_execution(&output("hello\n", ""), cx).is_ok());
+    assert_eq!(checker.failures, 0);
+
+    let (result, stderr) =
+        jobs::capture_stderr(|| checker.check_execution(&output("goodbye\n", "oops\n"), cx));
+    assert!(result.is_ok());
+    assert_eq!(checker.failures, 2);
+    let (stdout, stderr) = stderr.split_once("error: the stderr output").unwrap();
+    assert!(
+        stdout.starts_with("error: the stdout output of the test binary does not match"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("test.run.stdout"), "{stdout}");
+    assert!(stdout.contains("-hello\n+goodbye\n"), "{stdout}");
+    assert!(stderr.starts_with(" of the test binary does not match"), "{stderr}");
+    assert!(stderr.contains("test.run.stderr` does not exist"), "{stderr}");
+    assert!(stderr.contains("+oops\n"), "{stderr}");
+}
@@ -1,13 +1,17 @@
 use crate::{
-    build::{DebugOptions, Engine, QueryEnginePathError, QueryEngineVersionError},
+    build::{
+        DebugOptions, Engine, QueryEnginePathError, QueryEngineVersionError, QueryTargetInfoError,
+        QueryTargetLibdirError, TargetInfo,
+    },
     data::{PlusPrefixedToolchain, Version},
     source::SourceMap,
     utility::{
         default,
+        jobs::Jobs,
         small_fixed_map::{SmallFixedMap, SmallKey},
     },
 };
-use std::{cell::RefCell, path::PathBuf};
+use std::{num::NonZero, path::PathBuf, sync::Mutex};
 
 pub(crate) macro new($opts:expr) {{
     super let cx = ContextData::new($opts);
@@ -35,6 +39,10 @@ impl<'cx> Context<'cx> {
         &self.data.opts
     }
 
+    pub(crate) fn jobs(self) -> &'cx Jobs {
+        &self.data.jobs
+    }
+
     #[doc(hidden)] // used internally by macro `invoke`
     pub(crate) fn store(self) -> &'cx QueryStore {
         &self.data.store
@@ -45,13 +53,14 @@ impl<'cx> Context<'cx> {
 pub(crate) struct ContextData {
     map: SourceMap,
     opts: Options,
+    jobs: Jobs,
     store: QueryStore,
 }
 
 impl ContextData {
     #[doc(hidden)] // used internally by macro `new`
     pub(crate) fn new(opts: Options) -> Self {
-        Self { map: default(), opts, store: default() }
+        Self { map: default(), jobs: Jobs::new(opts.jobs), opts, store: default() }
     }
 }
 
@@ -60,6 +69,8 @@ impl ContextData {
 pub(crate) struct Options {
     pub(crate) toolchain: Option<PlusPrefixedToolchain>,
     pub(crate) dbg_opts: DebugOptions,
+    /// The maximum number of jobs to run concurrently.
+    pub(crate) jobs: NonZero<usize>,
 }
 
 macro_rules! store {
@@ -81,6 +92,8 @@ store! {
     query_engine_path(engine: Engine) -> Result<PathBuf, QueryEnginePathError>;
     // FIXME: Smh. return `&'cx Version<String>` or better yet `Version<&'cx str>` instead of `Version<String>`.
     query_engine_version(engine: Engine) -> Result<Version<String>, QueryEngineVersionError>;
+    query_target_libdir(unit: ()) -> Result<PathBuf, QueryTargetLibdirError>;
+    query_target_info(unit: ()) -> Result<TargetInfo, QueryTargetInfoError>;
 }
 
 pub(crate) macro invoke($cx:ident.$query:ident($input:expr)) {
@@ -94,16 +107,19 @@ pub(crate) fn invoke<I: SmallKey, O: Clone>(
     input: I,
     cx: Context<'_>,
 ) -> O {
-    if let Some(result) = query.cache.borrow().get(input) {
+    if let Some(result) = query.cache.lock().unwrap().get(input) {
         return result.clone();
     }
 
-    query.cache.borrow_mut().get_or_insert(input, compute(input, cx)).clone()
+    // We don't hold the lock during the computation since it may invoke other queries.
+    // If multiple jobs compute the same query simultaneously, the first result wins.
+    let result = compute(input, cx);
+    query.cache.lock().unwrap().get_or_insert(input, result).clone()
 }
 
 #[doc(hidden)] // used internally by macro `invoke`
 pub(crate) struct Query<I: SmallKey, O> {
-    cache: RefCell<SmallFixedMap<I, O>>,
+    cache: Mutex<SmallFixedMap<I, O>>,
 }
 
 impl<I: SmallKey, O> Default for Query<I, O> {
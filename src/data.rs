@@ -13,6 +13,7 @@ use std::{
     fmt,
     io::{self, Write as _},
     num::NonZero,
+    path::PathBuf,
 };
 
 #[cfg(test)]
@@ -133,9 +134,37 @@ pub(crate) struct CrateType(pub &'static str);
 impl CrateType {
     pub(crate) const LIB: Self = Self("lib");
     pub(crate) const BIN: Self = Self("bin");
+    pub(crate) const DYLIB: Self = Self("dylib");
     pub(crate) const PROC_MACRO: Self = Self("proc-macro");
 }
 
+/// A crate passed to the engine via `--extern`.
+#[derive(Clone)]
+pub(crate) struct ExternCrate {
+    pub(crate) name: String,
+    /// The path to the artifact.
+    ///
+    /// If absent, the engine looks for the crate in the library search paths.
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) modifiers: ExternModifiers,
+}
+
+impl ExternCrate {
+    pub(crate) fn new(name: String) -> Self {
+        Self { name, path: None, modifiers: ExternModifiers::default() }
+    }
+}
+
+/// The options of an extern crate (`--extern priv,noprelude:name`).
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) struct ExternModifiers {
+    /// Whether the crate is a private dependency (`priv`).
+    pub(crate) private: bool,
+    /// Whether the crate doesn't get added to the extern prelude (`noprelude`).
+    pub(crate) no_prelude: bool,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(test, derive(Debug))]
 pub(crate) struct CrateName<S: AsRef<str>>(S);
@@ -178,7 +207,7 @@ impl<S: AsRef<str>> CrateName<S> {
 }
 
 impl CrateName<String> {
-    const FALLBACK: &str = "rust_out";
+    pub(crate) const FALLBACK: &str = "rust_out";
 
     pub(crate) fn parse_relaxed(source: &str) -> Result<Self, ()> {
         // NB: See the comment over in `CrateName::parse` for why this makes sense.
@@ -198,6 +227,7 @@ impl CrateName<String> {
     pub(crate) fn prepare_source_path(path: SourcePath<'_>) -> Result<&str, ()> {
         match path {
             SourcePath::Regular(path) => path.file_stem().ok_or(())?.to_str().ok_or(()),
+            SourcePath::Virtual { name, .. } => Ok(name),
             SourcePath::Stdin => Ok(Self::FALLBACK),
         }
     }
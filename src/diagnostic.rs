@@ -1,17 +1,25 @@
 use crate::{
     context::Context,
     source::{LocalSpan, SourcePath, Span},
-    utility,
+    utility::{
+        self,
+        jobs::{self, Stream},
+    },
 };
 use anstyle::{AnsiColor, Effects};
 use std::io::{self, Write as _};
 use unicode_segmentation::UnicodeSegmentation as _;
 use unicode_width::UnicodeWidthStr as _;
 
-pub(crate) type Painter = utility::paint::Painter<io::BufWriter<io::StderrLock<'static>>>;
+pub(crate) type Painter = utility::paint::Painter<jobs::Writer>;
 
 pub(crate) trait Paint = FnOnce(&mut Painter) -> io::Result<()>;
 
+/// A painter for the standard error stream that respects the output buffering of jobs.
+pub(crate) fn painter() -> Painter {
+    Painter::new(io::stderr(), |_| jobs::Writer::new(Stream::Stderr))
+}
+
 pub(crate) fn bug(message: impl Paint) -> Diagnostic {
     Diagnostic::new(Severity::Bug, message)
 }
@@ -24,6 +32,10 @@ pub(crate) fn warn(message: impl Paint) -> Diagnostic {
     Diagnostic::new(Severity::Warning, message)
 }
 
+pub(crate) fn info(message: impl Paint) -> Diagnostic {
+    Diagnostic::new(Severity::Info, message)
+}
+
 pub(crate) fn debug(message: impl Paint) -> Diagnostic {
     Diagnostic::new(Severity::Debug, message)
 }
@@ -47,7 +59,7 @@ impl Diagnostic {
     //  NOTE: if we do that change, don't keep the lock the entire time!
     //        we want rustc to print to stderr too!
     fn new(severity: Severity, message: impl Paint) -> Self {
-        let mut p = Painter::new(io::stderr().lock(), io::BufWriter::new);
+        let mut p = painter();
 
         (|| {
             p.set(Effects::BOLD)?;
@@ -87,12 +99,17 @@ impl Diagnostic {
 
         let p = &mut self.p;
         (|| {
+            // Subsequent highlights start on a new line.
+            if self.aux_offset.is_some() {
+                writeln!(p)?;
+            }
             write!(p, "   ")?;
             p.with(Effects::ITALIC, |p| {
                 match file.path {
                     // FIXME: Custom style for Stdin
                     SourcePath::Stdin => write!(p, "⟨stdin⟩"),
                     SourcePath::Regular(path) => write!(p, "{}", path.display()),
+                    SourcePath::Virtual { path, name } => write!(p, "{}⟨{name}⟩", path.display()),
                 }?;
                 write!(p, ":{line_number}:{column_number}",)
             })?;
@@ -103,6 +120,7 @@ impl Diagnostic {
             p.with(self.severity.color().on_default().bold(), fmt!("{}", underline.string))?;
 
             self.aux_offset = Some(underline.offset + underline.width);
+            self.aux_seen = false;
 
             io::Result::Ok(())
         })()
@@ -142,6 +160,7 @@ impl Diagnostic {
     }
 }
 
+#[derive(Clone, Copy)]
 pub(crate) struct EmittedError(());
 
 #[derive(Clone, Copy)]
@@ -149,6 +168,7 @@ pub(crate) enum Severity {
     Bug,
     Error,
     Warning,
+    Info,
     Debug,
 }
 
@@ -158,6 +178,7 @@ impl Severity {
             Self::Bug => "rruxwry-internal error",
             Self::Error => "error",
             Self::Warning => "warning",
+            Self::Info => "info",
             Self::Debug => "debug",
         }
     }
@@ -166,6 +187,7 @@ impl Severity {
         match self {
             Self::Bug | Self::Error => AnsiColor::Red,
             Self::Warning => AnsiColor::Yellow,
+            Self::Info => AnsiColor::Green,
             Self::Debug => AnsiColor::Magenta,
         }
     }
@@ -15,8 +15,9 @@
 
 use crate::{
     build::VerbatimOptions,
+    compiletest::Stream,
     context::Context,
-    data::{CrateName, CrateType},
+    data::{CrateName, CrateType, ExternModifiers},
     diagnostic::{EmittedError, error, fmt, warn},
     source::{LocalSpan, SourceFile, SourcePath, Span, Spanned},
     utility::{Conjunction, ListingExt, default},
@@ -48,6 +49,19 @@ pub(crate) fn gather<'cx>(
     directives.instantiate(revision).map_err(|error| error.emit().into())
 }
 
+/// The revisions declared by the principal crate.
+pub(crate) fn revisions<'cx>(
+    path: Spanned<SourcePath<'_>>,
+    scope: Scope,
+    flavor: Flavor,
+    cx: Context<'cx>,
+) -> crate::error::Result<BTreeSet<&'cx str>> {
+    // Any errors get reported later when the directives of the individual revisions get gathered.
+    let directives =
+        parse(cx.map().read(path, cx)?, scope, Role::Principal, flavor, &mut Errors::default());
+    Ok(directives.revisions)
+}
+
 fn parse<'cx>(
     file: SourceFile<'cx>,
     scope: Scope,
@@ -58,8 +72,12 @@ fn parse<'cx>(
     let mut directives = Directives::new(role);
 
     let mut index = 0u32;
+    // The name of the currently open inline crate, whether it's raw and the start of its body.
+    let mut inline_crate: Option<(Spanned<&'cx str>, bool, u32)> = None;
     // `\r` gets strpped as whitespace later on.
     for line in file.contents.split('\n') {
+        let next_index = index + u32::try_from(line.len()).unwrap() + 1;
+
         if let Some(directive) = line.trim_start().strip_prefix("//@") {
             // FIXME: This is super awkward! Replace this!
             let offset = line.substr_range(directive).unwrap().start;
@@ -69,13 +87,44 @@ fn parse<'cx>(
             //        For example, DuplicateRevisions should lead to a hard error (unless `--force`d).
             //        Also, under Flavor::Rruxwry a lot of the warnings should become hard errors, too.
             match Parser::new(directive, scope, role, flavor, offset).parse_directive() {
+                Ok(Directive { predicate, bare: SimpleDirective::InlineCrate(delimiter) }) => {
+                    if let Some(predicate) = predicate {
+                        errors.insert(Error::ConditionalInlineCrate(predicate.span));
+                    }
+                    match (delimiter, inline_crate) {
+                        (Delimiter::Begin { name, .. }, Some(_)) => {
+                            errors.insert(Error::NestedInlineCrate(name.span));
+                        }
+                        (Delimiter::Begin { name, raw }, None) => {
+                            inline_crate = Some((name, raw, next_index));
+                        }
+                        (Delimiter::End(span), None) => {
+                            errors.insert(Error::UnexpectedInlineCrateEnd(span));
+                        }
+                        (Delimiter::End(_), Some((name, raw, start))) => {
+                            let body = LocalSpan::new(start, index).global(file);
+                            directives.instantiated.inline_crates.push(InlineCrate {
+                                name,
+                                raw,
+                                body,
+                            });
+                            inline_crate = None;
+                        }
+                    }
+                }
+                // The directives of inline crates get gathered separately.
+                Ok(_) | Err(_) if inline_crate.is_some() => {}
                 Ok(directive) => directives.add(directive),
                 Err(error) => errors.insert(error),
             }
         }
 
         // FIXME: Is this really correct (empty lines, trailing line breaks, …)?
-        index += u32::try_from(line.len()).unwrap() + 1;
+        index = next_index;
+    }
+
+    if let Some((name, ..)) = inline_crate {
+        errors.insert(Error::UnterminatedInlineCrate(name));
     }
 
     // FIXME: Move this into `gather` (tests need to be updated to use a custom `gather` over `parse`).
@@ -85,11 +134,15 @@ fn parse<'cx>(
 }
 
 fn validate<'cx>(directives: &Directives<'cx>, errors: &mut Errors<'cx>) {
-    directives
-        .uninstantiated
-        .iter()
-        .filter(|&(revision, _)| !directives.revisions.contains(revision.bare))
-        .map(|&(revision, _)| Error::UndeclaredRevision {
+    let mut revisions = Vec::new();
+    for (predicate, _) in &directives.uninstantiated {
+        predicate.revisions(&mut revisions);
+    }
+
+    revisions
+        .into_iter()
+        .filter(|revision| !directives.revisions.contains(revision.bare))
+        .map(|revision| Error::UndeclaredRevision {
             revision,
             available: directives.revisions.clone(),
         })
@@ -142,8 +195,8 @@ impl<'src> Directives<'src> {
             // FIXME: Emit a warning for this:
             // We ignore revision predicates on revisions since that's what `compiletest` does, too.
             self.revisions.extend(revisions);
-        } else if let Some(revision) = directive.revision {
-            self.uninstantiated.push((revision, directive.bare));
+        } else if let Some(predicate) = directive.predicate {
+            self.uninstantiated.push((predicate, directive.bare));
         } else {
             // We immediately adjoin unconditional directives to prevent needlessly
             // instantiating them over and over later in `Self::instantiate`.
@@ -168,16 +221,17 @@ impl<'src> Directives<'src> {
                     available: revisions,
                 });
             }
-
-            for (revision, directive) in directives {
-                if revision.bare == active_revision {
-                    self.instantiated.adjoin(directive);
-                }
-            }
         } else if !revisions.is_empty() {
             return Err(InstantiationError::MissingActiveRevision { available: revisions });
         }
 
+        for (predicate, directive) in directives {
+            if predicate.bare.holds(active_revision.map(|Revision(revision)| revision)) {
+                self.instantiated.adjoin(directive);
+            }
+        }
+
+        self.instantiated.revisions = revisions;
         Ok(self.instantiated)
     }
 }
@@ -223,6 +277,8 @@ impl InstantiationError<'_, '_> {
 #[derive(Default, Clone)]
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 pub(crate) struct InstantiatedDirectives<'src> {
+    /// The revisions declared by the crate.
+    pub(crate) revisions: BTreeSet<&'src str>,
     pub(crate) build_aux_docs: bool,
     pub(crate) auxes: Vec<Auxiliary<'src>>,
     pub(crate) edition: Option<Spanned<&'src str>>,
@@ -230,6 +286,19 @@ pub(crate) struct InstantiatedDirectives<'src> {
     pub(crate) v_d_opts: VerbatimOptions<'src, ()>,
     pub(crate) run_v_opts: VerbatimOptions<'src>,
     pub(crate) prefer_dylib: PreferDylib,
+    /// Whether to suppress the automatic `--check-cfg` for the revisions (`no-auto-check-cfg`).
+    pub(crate) no_auto_check_cfg: bool,
+    pub(crate) normalizations: Vec<Normalization<'src>>,
+    pub(crate) mode: Option<Spanned<Mode>>,
+    pub(crate) check_run_results: bool,
+    pub(crate) failure_status: Option<Spanned<i32>>,
+    pub(crate) should_ice: Option<Span>,
+    pub(crate) patterns: Vec<Pattern<'src>>,
+    pub(crate) rustfix: Option<Rustfix>,
+    pub(crate) html_checks: Vec<DocCheck<'src, HtmlDocCkDirective>>,
+    pub(crate) json_checks: Vec<DocCheck<'src, JsonDocCkDirective>>,
+    pub(crate) inline_crates: Vec<InlineCrate<'src>>,
+    pub(crate) conditions: Vec<Spanned<Condition<'src>>>,
 }
 
 impl<'src> InstantiatedDirectives<'src> {
@@ -239,27 +308,32 @@ impl<'src> InstantiatedDirectives<'src> {
             SimpleDirective::Aux(directive) => self.auxes.push(match directive {
                 // FIXME: Audit this!
                 AuxiliaryDirective::Bin { path } => {
-                    Auxiliary { prefix: None, path, typ: Some(CrateType::BIN) }
+                    Auxiliary { name: None, modifiers: default(), path, typ: Some(CrateType::BIN) }
+                }
+                AuxiliaryDirective::Build { path } => {
+                    Auxiliary { name: None, modifiers: default(), path, typ: None }
                 }
-                AuxiliaryDirective::Build { path } => Auxiliary { prefix: None, path, typ: None },
-                AuxiliaryDirective::Crate { prefix, path } => {
-                    Auxiliary { prefix: Some(prefix.into()), path, typ: None }
+                AuxiliaryDirective::Crate { name, modifiers, path } => {
+                    Auxiliary { name: Some(name.into()), modifiers, path, typ: None }
                 }
                 AuxiliaryDirective::ProcMacro { path } => {
                     // FIXME: unwrap
                     // FIXME: is this what compiletest does, too?
-                    let prefix = CrateName::parse_source_file_relaxed(SourcePath::Regular(
+                    let name = CrateName::parse_source_file_relaxed(SourcePath::Regular(
                         Path::new(path.bare),
                     ))
                     .unwrap();
                     Auxiliary {
-                        prefix: Some(prefix.into_inner().into()),
+                        name: Some(name.into_inner().into()),
+                        modifiers: default(),
                         path,
                         typ: Some(CrateType::PROC_MACRO),
                     }
                 }
             }),
             SimpleDirective::BuildAuxDocs => self.build_aux_docs = true,
+            SimpleDirective::CheckRunResults => self.check_run_results = true,
+            SimpleDirective::Condition(condition) => self.conditions.push(condition),
             // FIXME: Emit an error if multiple `edition` directives were specified just like `compiletest` does.
             // FIXME: When encountering unconditional+conditional, emit a warning.
             SimpleDirective::Edition(edition) => self.edition = Some(edition),
@@ -282,13 +356,28 @@ impl<'src> InstantiatedDirectives<'src> {
                 //        Use crate `shlex` for this. What does compiletest do btw?
                 stage.extend(flags.split_whitespace());
             }
+            SimpleDirective::FailureStatus(status) => self.failure_status = Some(status),
+            SimpleDirective::HtmlDocCk(check) => self.html_checks.push(check),
+            SimpleDirective::JsonDocCk(check) => self.json_checks.push(check),
+            SimpleDirective::Normalize(normalization) => self.normalizations.push(normalization),
+            SimpleDirective::Pattern(pattern) => self.patterns.push(pattern),
+            // FIXME: compiletest rejects multiple pass/fail modes, we should, too.
+            SimpleDirective::Mode(mode) => self.mode = Some(mode),
             // FIXME: What does compiletest do on duplicates? We should at least warn.
             SimpleDirective::NoPreferDynamic => self.prefer_dylib = PreferDylib::No,
-            SimpleDirective::Revisions(_) => unreachable!(), // Already dealt with in `Directives::add`.
-            // FIXME: Actually implement these directives.
-            | SimpleDirective::HtmlDocCk(..)
-            | SimpleDirective::JsonDocCk(..)
-            | SimpleDirective::Rruxwry(..) => {}
+            SimpleDirective::NoAutoCheckCfg => self.no_auto_check_cfg = true,
+            // Already dealt with in `Directives::add` and in `parse`, respectively.
+            SimpleDirective::Revisions(_) | SimpleDirective::InlineCrate(_) => unreachable!(),
+            SimpleDirective::Rustfix(rustfix) => {
+                // The more restrictive directive wins.
+                self.rustfix = match (self.rustfix, rustfix) {
+                    (Some(Rustfix::MachineApplicable), _) | (_, Rustfix::MachineApplicable) => {
+                        Some(Rustfix::MachineApplicable)
+                    }
+                    (_, Rustfix::All) => Some(Rustfix::All),
+                };
+            }
+            SimpleDirective::ShouldIce(span) => self.should_ice = Some(span),
         }
     }
 }
@@ -304,39 +393,215 @@ pub(crate) enum PreferDylib {
 #[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 pub(crate) struct Auxiliary<'src> {
-    // FIXME: bad name
-    pub(crate) prefix: Option<Cow<'src, str>>,
+    /// The name under which the auxiliary gets passed to its parent, if at all.
+    pub(crate) name: Option<Cow<'src, str>>,
+    pub(crate) modifiers: ExternModifiers,
     pub(crate) path: Spanned<&'src str>,
     pub(crate) typ: Option<CrateType>,
 }
 
-type UninstantiatedDirectives<'src> = Vec<(Spanned<&'src str>, SimpleDirective<'src>)>;
+/// A pass/fail mode, i.e., the phase up to which the test gets built and its expected outcome.
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) struct Mode {
+    pub(crate) phase: Phase,
+    pub(crate) outcome: Outcome,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+pub(crate) enum Phase {
+    Check,
+    Build,
+    Run,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+pub(crate) enum Outcome {
+    Pass,
+    Fail,
+}
+
+/// A custom normalization rule for the output of the compiler or the executable.
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) struct Normalization<'src> {
+    pub(crate) stream: Stream,
+    pub(crate) regex: Spanned<&'src str>,
+    /// The replacement where `$name` refers to a capture group and where `\n` denotes a line break.
+    pub(crate) replacement: &'src str,
+}
+
+/// A pattern the output of the compiler or the executable has to contain or must not contain.
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) struct Pattern<'src> {
+    pub(crate) source: Spanned<&'src str>,
+    pub(crate) kind: PatternKind,
+    pub(crate) polarity: Polarity,
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) enum PatternKind {
+    Substring,
+    Regex,
+}
+
+/// A check of the documentation generated by rustdoc (htmldocck, jsondocck).
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) struct DocCheck<'src, D> {
+    pub(crate) directive: D,
+    pub(crate) polarity: Polarity,
+    /// The span of the entire directive including its arguments.
+    pub(crate) span: Span,
+    /// The unsplit arguments.
+    pub(crate) arguments: &'src str,
+}
+
+/// A crate defined inline between `//@ crate NAME {` or `//@ raw-crate NAME {` and `//@ }`.
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) struct InlineCrate<'src> {
+    pub(crate) name: Spanned<&'src str>,
+    /// Whether the crate doesn't get added to the extern prelude (`raw-crate`).
+    pub(crate) raw: bool,
+    /// The span of the lines between the delimiters.
+    pub(crate) body: Span,
+}
+
+/// A condition under which the test gets ignored (`ignore-*`, `only-*`, `needs-*`).
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) enum Condition<'src> {
+    /// Ignore the test if the target matches.
+    Ignore(Target<'src>),
+    /// Ignore the test unless the target matches.
+    Only(Target<'src>),
+    /// Ignore the test unless the target supports the given capability.
+    Needs(Capability),
+}
+
+/// A property of the target or of the toolchain.
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) enum Target<'src> {
+    /// Any target whatsoever (`ignore-test`).
+    Any,
+    Os(&'src str),
+    Family(&'src str),
+    Arch(&'src str),
+    Env(&'src str),
+    Vendor(&'src str),
+    /// The pointer width in bits.
+    PointerWidth(&'src str),
+    Endian(&'src str),
+    Channel(&'src str),
+    Triple(&'src str),
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) enum Capability {
+    Unwind,
+    DynamicLinking,
+    AsmSupport,
+}
+
+/// The suggestions to apply (`run-rustfix`, `rustfix-only-machine-applicable`).
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(crate) enum Rustfix {
+    All,
+    MachineApplicable,
+}
+
+type UninstantiatedDirectives<'src> = Vec<(Spanned<Predicate<'src>>, SimpleDirective<'src>)>;
 
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 struct Directive<'src> {
-    revision: Option<Spanned<&'src str>>,
+    predicate: Option<Spanned<Predicate<'src>>>,
     bare: SimpleDirective<'src>,
 }
 
+/// A condition on the active revision (`//@[PREDICATE]`).
+///
+/// Under flavor vanilla, a predicate is always a single revision. Under flavor rruxwry,
+/// predicates may be combined cfg-style, e.g., `//@[any(next, not(old))]`.
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+enum Predicate<'src> {
+    Revision(&'src str),
+    Not(Box<Spanned<Self>>),
+    Any(Vec<Spanned<Self>>),
+    All(Vec<Spanned<Self>>),
+    Bool(bool),
+}
+
+impl<'src> Spanned<Predicate<'src>> {
+    /// Collect the revisions referenced by the predicate.
+    fn revisions(&self, revisions: &mut Vec<Spanned<&'src str>>) {
+        match &self.bare {
+            &Predicate::Revision(revision) => revisions.push(Spanned::new(self.span, revision)),
+            Predicate::Not(predicate) => predicate.revisions(revisions),
+            Predicate::Any(predicates) | Predicate::All(predicates) => {
+                for predicate in predicates {
+                    predicate.revisions(revisions);
+                }
+            }
+            Predicate::Bool(_) => {}
+        }
+    }
+}
+
+impl Predicate<'_> {
+    fn holds(&self, active_revision: Option<&str>) -> bool {
+        let holds = |predicate: &Spanned<Self>| predicate.bare.holds(active_revision);
+        match self {
+            &Self::Revision(revision) => active_revision == Some(revision),
+            Self::Not(predicate) => !holds(predicate),
+            Self::Any(predicates) => predicates.iter().any(holds),
+            Self::All(predicates) => predicates.iter().all(holds),
+            &Self::Bool(value) => value,
+        }
+    }
+}
+
 // FIXME: Can somehow get rid of this? By merging a few steps. This isn't super scalable rn.
 #[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 enum SimpleDirective<'src> {
     Aux(AuxiliaryDirective<'src>),
     BuildAuxDocs,
+    CheckRunResults,
+    Condition(Spanned<Condition<'src>>),
     Edition(Spanned<&'src str>),
     EnvVar(&'src str, Option<&'src str>, Stage),
+    FailureStatus(Spanned<i32>),
     // FIXME: Badly modeled: Stage::Runtime is incompatible with Receiver::Rustdoc.
     //        Make this state unrepresentable!
     Flags(&'src str, Stage, FlagScope),
     Revisions(Vec<&'src str>),
+    Mode(Spanned<Mode>),
+    Normalize(Normalization<'src>),
+    NoAutoCheckCfg,
     NoPreferDynamic,
-    #[allow(dead_code)]
-    HtmlDocCk(HtmlDocCkDirective, Polarity),
-    #[allow(dead_code)]
-    JsonDocCk(JsonDocCkDirective, Polarity),
-    #[allow(dead_code)]
-    Rruxwry(RruxwryDirective),
+    Pattern(Pattern<'src>),
+    Rustfix(Rustfix),
+    ShouldIce(Span),
+    HtmlDocCk(DocCheck<'src, HtmlDocCkDirective>),
+    JsonDocCk(DocCheck<'src, JsonDocCkDirective>),
+    InlineCrate(Delimiter<'src>),
+}
+
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+enum Delimiter<'src> {
+    /// `crate NAME {` or `raw-crate NAME {`.
+    Begin { name: Spanned<&'src str>, raw: bool },
+    /// `}`
+    End(Span),
 }
 
 #[derive(Clone)]
@@ -344,7 +609,7 @@ enum SimpleDirective<'src> {
 enum AuxiliaryDirective<'src> {
     Bin { path: Spanned<&'src str> },
     Build { path: Spanned<&'src str> },
-    Crate { prefix: &'src str, path: Spanned<&'src str> },
+    Crate { name: &'src str, modifiers: ExternModifiers, path: Spanned<&'src str> },
     ProcMacro { path: Spanned<&'src str> },
 }
 
@@ -404,19 +669,25 @@ impl<'src> Parser<'src> {
     fn parse_directive(mut self) -> Result<Directive<'src>, Error<'src>> {
         self.parse_whitespace();
 
-        let revision = if self.consume(|char| char == '[') {
-            // FIXME: (1) Warn on empty/blank revision ("literally treated as a revision")
-            //        (2) Warn on padded revision ("treated literally (not trimmed)")
-            //        (3) Warn on quoted revision and commas inside the revision
-            //        NOTE: In cases (1)(2) we already warn that they're undefined
-            //              (it's impossible for a user to declare such revisions).
-            //              However, emitting a more precise diagnostic feels nicer.
-            // FIXME: Do we want to trim on Flavor::Rruxwry?
-            // FIXME: Under Flavor::Rruxwry support cfg-like logic predicates here (n-ary `not`, `any`, `all`; `false`, `true`).
-            let revision = self.parse_while(|char| char != ']');
+        let predicate = if self.consume(|char| char == '[') {
+            let predicate = match self.flavor {
+                // FIXME: (1) Warn on empty/blank revision ("literally treated as a revision")
+                //        (2) Warn on padded revision ("treated literally (not trimmed)")
+                //        (3) Warn on quoted revision and commas inside the revision
+                //        NOTE: In cases (1)(2) we already warn that they're undefined
+                //              (it's impossible for a user to declare such revisions).
+                //              However, emitting a more precise diagnostic feels nicer.
+                Flavor::Vanilla => self.parse_while(|char| char != ']').map(Predicate::Revision),
+                Flavor::Rruxwry => {
+                    self.parse_whitespace();
+                    let predicate = self.parse_predicate()?;
+                    self.parse_whitespace();
+                    predicate
+                }
+            };
             self.expect(']')?;
 
-            Some(revision)
+            Some(predicate)
         } else {
             None
         };
@@ -424,11 +695,56 @@ impl<'src> Parser<'src> {
         self.parse_whitespace();
 
         // FIXME: This is slightly hacky / "leaky".
-        let directive =
-            self.expect_many(|char| matches!(char, '-' | '!' | '}') || char.is_alphabetic())?;
+        let directive = self
+            .expect_many(|char| matches!(char, '-' | '_' | '!' | '}') || char.is_alphanumeric())?;
 
         self.parse_simple_directive(directive)
-            .map(|directive| Directive { revision, bare: directive })
+            .map(|directive| Directive { predicate, bare: directive })
+    }
+
+    /// Parse a cfg-like predicate: A revision, `true`, `false`, `not(…)`, `any(…)` or `all(…)`.
+    fn parse_predicate(&mut self) -> Result<Spanned<Predicate<'src>>, Error<'src>> {
+        let name = self.parse_while(|char| {
+            !(char.is_whitespace() || matches!(char, '[' | ']' | '(' | ')' | ','))
+        });
+        if name.span.is_empty() {
+            return Err(Error::ExpectedPredicate(name.span));
+        }
+
+        self.parse_whitespace();
+        if !self.consume(|char| char == '(') {
+            return Ok(name.map(|name| match name {
+                "true" => Predicate::Bool(true),
+                "false" => Predicate::Bool(false),
+                name => Predicate::Revision(name),
+            }));
+        }
+
+        let mut predicates = Vec::new();
+        loop {
+            self.parse_whitespace();
+            if self.consume(|char| char == ')') {
+                break;
+            }
+            predicates.push(self.parse_predicate()?);
+            self.parse_whitespace();
+            if !self.consume(|char| char == ',') {
+                self.expect(')')?;
+                break;
+            }
+        }
+        let span = Span::new(name.span.start, self.span(LocalSpan::empty(self.index())).end);
+
+        let predicate = match name.bare {
+            "not" => match <[_; 1]>::try_from(predicates) {
+                Ok([predicate]) => Predicate::Not(Box::new(predicate)),
+                Err(_) => return Err(Error::NotArity(Spanned::new(span, name.bare))),
+            },
+            "any" => Predicate::Any(predicates),
+            "all" => Predicate::All(predicates),
+            _ => return Err(Error::UnknownPredicate(name)),
+        };
+        Ok(Spanned::new(span, predicate))
     }
 
     fn parse_simple_directive(
@@ -444,8 +760,11 @@ impl<'src> Parser<'src> {
         let jsondocck = Self::parse_jsondocck_directive(source.bare);
         let rruxwry = Self::parse_rruxwry_directive(source.bare);
         match (self.scope, htmldocck, jsondocck) {
-            (Scope::HtmlDocCk, Some(directive), _) | (Scope::JsonDocCk, _, Some(directive)) => {
-                return Ok(directive);
+            (Scope::HtmlDocCk, Some(directive), _) => {
+                return Ok(SimpleDirective::HtmlDocCk(self.parse_doc_check(source, directive)));
+            }
+            (Scope::JsonDocCk, _, Some(directive)) => {
+                return Ok(SimpleDirective::JsonDocCk(self.parse_doc_check(source, directive)));
             }
             | (Scope::HtmlDocCk | Scope::Base, None, Some(_))
             | (Scope::JsonDocCk | Scope::Base, Some(_), None)
@@ -468,7 +787,7 @@ impl<'src> Parser<'src> {
                     actual: self.flavor.into(),
                     expected: Flavor::Rruxwry.into(),
                 }),
-                Flavor::Rruxwry => Ok(directive),
+                Flavor::Rruxwry => self.parse_delimiter(source, directive),
             };
         }
         Err(Error::UnknownDirective(source))
@@ -494,15 +813,19 @@ impl<'src> Parser<'src> {
             "aux-crate" => {
                 self.parse_separator(Padding::Yes)?;
 
-                // The prefix contains the crate name as well as extern options.
-                let prefix = self.expect_many(|char| char != '=')?.bare;
+                // The prefix contains the crate name as well as extern modifiers.
+                let prefix = self.expect_many(|char| char != '=')?;
+                let (modifiers, name) = match prefix.bare.split_once(':') {
+                    Some((modifiers, name)) => (parse_extern_modifiers(prefix, modifiers)?, name),
+                    None => (default(), prefix.bare),
+                };
 
                 // FIXME: If Flavor::Rruxwry, support optional paths where the path is
                 //        deduced from the prefix (e.g., `priv:name` → path is `name.rs`).
                 self.expect('=')?;
 
                 let path = self.parse_until_line_break();
-                SimpleDirective::Aux(AuxiliaryDirective::Crate { prefix, path })
+                SimpleDirective::Aux(AuxiliaryDirective::Crate { name, modifiers, path })
             }
             "build-aux-docs" => {
                 match self.scope {
@@ -521,6 +844,20 @@ impl<'src> Parser<'src> {
 
                 SimpleDirective::BuildAuxDocs
             }
+            "build-fail" | "build-pass" | "check-fail" | "check-pass" | "run-fail" | "run-pass" => {
+                let (phase, outcome) = source.bare.split_once('-').unwrap();
+                let phase = match phase {
+                    "check" => Phase::Check,
+                    "build" => Phase::Build,
+                    _ => Phase::Run,
+                };
+                let outcome = if outcome == "pass" { Outcome::Pass } else { Outcome::Fail };
+                SimpleDirective::Mode(source.map(|_| Mode { phase, outcome }))
+            }
+            "check-run-results" => {
+                self.limit(source, Scope::Base)?;
+                SimpleDirective::CheckRunResults
+            }
             "compile-flags" => {
                 return self.parse_flags(Stage::CompileTime, FlagScope::Base).map(Some);
             }
@@ -549,11 +886,29 @@ impl<'src> Parser<'src> {
                 // Indeed, we parse until a line break and include things like whitespace!
                 SimpleDirective::Edition(self.parse_until_line_break())
             }
+            "error-pattern" => {
+                return self.parse_pattern(PatternKind::Substring, Polarity::Positive).map(Some);
+            }
             "exec-env" => {
                 self.limit(source, Scope::Base)?;
                 return self.parse_set_env_var(Stage::RunTime).map(Some);
             }
+            "failure-status" => {
+                self.limit(source, Scope::Base)?;
+                self.parse_separator(Padding::Yes)?;
+                let status = self.parse_until_line_break();
+                let Ok(code) = status.bare.trim_end().parse() else {
+                    return Err(Error::InvalidValue(status.bare));
+                };
+                SimpleDirective::FailureStatus(status.map(|_| code))
+            }
+            "forbid-output" => {
+                return self.parse_pattern(PatternKind::Substring, Polarity::Negative).map(Some);
+            }
+            "no-auto-check-cfg" => SimpleDirective::NoAutoCheckCfg,
             "no-prefer-dynamic" => SimpleDirective::NoPreferDynamic,
+            "normalize-stderr" => return self.parse_normalization(Stream::Stderr).map(Some),
+            "normalize-stdout" => return self.parse_normalization(Stream::Stdout).map(Some),
             "proc-macro" => {
                 self.parse_separator(Padding::Yes)?; // FIXME: Audit Padding::Yes
 
@@ -561,6 +916,9 @@ impl<'src> Parser<'src> {
                     path: self.parse_until_line_break(),
                 })
             }
+            "regex-error-pattern" => {
+                return self.parse_pattern(PatternKind::Regex, Polarity::Positive).map(Some);
+            }
             // FIXME: Warn/error if we're inside of an auxiliary file.
             //        ->Warn: "directive gets ignored // revisions are inherited in aux"
             //        ->Error: "directive not permitted // revisions are inherited in aux"
@@ -587,7 +945,12 @@ impl<'src> Parser<'src> {
                 self.limit(source, Scope::Base)?;
                 return self.parse_flags(Stage::RunTime, FlagScope::Base).map(Some);
             }
+            "run-rustfix" => SimpleDirective::Rustfix(Rustfix::All),
             "rustc-env" => return self.parse_set_env_var(Stage::CompileTime).map(Some),
+            "rustfix-only-machine-applicable" => {
+                SimpleDirective::Rustfix(Rustfix::MachineApplicable)
+            }
+            "should-ice" => SimpleDirective::ShouldIce(source.span),
             "unset-rustc-env" => return self.parse_unset_env_var(Stage::CompileTime).map(Some),
             "unset-exec-env" => {
                 self.limit(source, Scope::Base)?;
@@ -595,26 +958,17 @@ impl<'src> Parser<'src> {
             }
             // FIXME: Actually support some of these flags. In order of importance:
             //        `unique-doc-out-dir` (I think),
-            //        `incremental`,
-            //        `no-auto-check-cfg` (once we actually automatically check-cfg)
+            //        `incremental`.
             | "add-core-stubs"
             | "assembly-output"
             | "aux-codegen-backend"
-            | "build-fail"
-            | "build-pass"
-            | "check-fail"
-            | "check-pass"
-            | "check-run-results"
             | "check-stdout"
             | "check-test-line-numbers-match"
             | "dont-check-compiler-stderr"
             | "dont-check-compiler-stdout"
             | "dont-check-failure-status"
-            | "error-pattern"
             | "exact-llvm-major-version"
-            | "failure-status"
             | "filecheck-flags"
-            | "forbid-output"
             | "force-host"
             | "incremental"
             | "known-bug"
@@ -625,29 +979,25 @@ impl<'src> Parser<'src> {
             | "min-lldb-version"
             | "min-llvm-version"
             | "min-system-llvm-version"
-            | "no-auto-check-cfg"
             | "normalize-stderr-32bit"
             | "normalize-stderr-64bit"
-            | "normalize-stderr"
-            | "normalize-stdout"
             | "pp-exact"
             | "pretty-compare-only"
             | "pretty-mode"
             | "reference"
-            | "regex-error-pattern"
             | "remap-src-base"
-            | "run-fail"
-            | "run-pass"
-            | "run-rustfix"
-            | "rustfix-only-machine-applicable"
             | "should-fail"
-            | "should-ice"
             | "stderr-per-bitwidth"
             | "test-mir-pass"
             | "unique-doc-out-dir"
             | "unused-revision-names" => {
                 return Err(Error::UnsupportedDirective(source));
             }
+            _ if let Some(condition) = Self::parse_condition(source.bare) => {
+                // Like in compiletest, anything after the name is a comment (the reason).
+                self.parse_until_line_break();
+                SimpleDirective::Condition(Spanned::new(source.span, condition))
+            }
             _ if source.bare.starts_with("ignore-")
                 || source.bare.starts_with("needs-")
                 || source.bare.starts_with("only-") =>
@@ -696,8 +1046,31 @@ impl<'src> Parser<'src> {
         Ok(SimpleDirective::EnvVar(self.parse_until_line_break().bare, None, stage))
     }
 
-    // FIXME: Actually parse them fully and do sth. with them, otherwise turn this into a array lookup.
-    fn parse_htmldocck_directive(source: &'src str) -> Option<SimpleDirective<'src>> {
+    fn parse_normalization(
+        &mut self,
+        stream: Stream,
+    ) -> Result<SimpleDirective<'src>, Error<'src>> {
+        self.parse_separator(Padding::Yes)?;
+        let regex = self.parse_quoted()?;
+        self.parse_whitespace();
+        self.expect('-')?;
+        self.expect('>')?;
+        self.parse_whitespace();
+        let replacement = self.parse_quoted()?.bare;
+        Ok(SimpleDirective::Normalize(Normalization { stream, regex, replacement }))
+    }
+
+    fn parse_pattern(
+        &mut self,
+        kind: PatternKind,
+        polarity: Polarity,
+    ) -> Result<SimpleDirective<'src>, Error<'src>> {
+        self.parse_separator(Padding::Yes)?;
+        let source = self.parse_until_line_break();
+        Ok(SimpleDirective::Pattern(Pattern { source, kind, polarity }))
+    }
+
+    fn parse_htmldocck_directive(source: &'src str) -> Option<(HtmlDocCkDirective, Polarity)> {
         let (source, polarity) = Self::parse_polarity(source);
         let directive = match source {
             "count" => HtmlDocCkDirective::Count,
@@ -710,11 +1083,10 @@ impl<'src> Parser<'src> {
             "snapshot" => HtmlDocCkDirective::Snapshot,
             _ => return None,
         };
-        Some(SimpleDirective::HtmlDocCk(directive, polarity))
+        Some((directive, polarity))
     }
 
-    // FIXME: Actually parse them fully and do sth. with them, otherwise turn this into a array lookup.
-    fn parse_jsondocck_directive(source: &'src str) -> Option<SimpleDirective<'src>> {
+    fn parse_jsondocck_directive(source: &'src str) -> Option<(JsonDocCkDirective, Polarity)> {
         let (source, polarity) = Self::parse_polarity(source);
         let directive = match source {
             "count" => JsonDocCkDirective::Count,
@@ -724,18 +1096,88 @@ impl<'src> Parser<'src> {
             "set" => JsonDocCkDirective::Set,
             _ => return None,
         };
-        Some(SimpleDirective::JsonDocCk(directive, polarity))
+        Some((directive, polarity))
     }
 
-    // FIXME: Actually parse them fully.
-    fn parse_rruxwry_directive(source: &'src str) -> Option<SimpleDirective<'src>> {
-        let directive = match source {
+    /// Parse the arguments of an htmldocck or jsondocck directive.
+    ///
+    /// They get split later by the respective engine since their syntax differs.
+    fn parse_doc_check<D>(
+        &mut self,
+        name: Spanned<&'src str>,
+        (directive, polarity): (D, Polarity),
+    ) -> DocCheck<'src, D> {
+        self.parse_whitespace();
+        let arguments = self.parse_until_line_break();
+        let trimmed = arguments.bare.trim_end();
+        #[allow(clippy::cast_possible_truncation)] // The line is shorter than the file.
+        let end = arguments.span.start + trimmed.len() as u32;
+        DocCheck { directive, polarity, span: Span::new(name.span.start, end), arguments: trimmed }
+    }
+
+    fn parse_condition(source: &'src str) -> Option<Condition<'src>> {
+        if let Some(capability) = source.strip_prefix("needs-") {
+            return Some(Condition::Needs(match capability {
+                "unwind" => Capability::Unwind,
+                "dynamic-linking" => Capability::DynamicLinking,
+                "asm-support" => Capability::AsmSupport,
+                _ => return None,
+            }));
+        }
+
+        let (target, only) = match source.strip_prefix("ignore-") {
+            Some(target) => (target, false),
+            None => (source.strip_prefix("only-")?, true),
+        };
+        let target = match target {
+            "test" if !only => Target::Any,
+            _ if OSES.contains(&target) => Target::Os(target),
+            "unix" | "windows" | "wasm" => Target::Family(target),
+            _ if ARCHES.contains(&target) => Target::Arch(target),
+            _ if ENVS.contains(&target) => Target::Env(target),
+            "apple" => Target::Vendor(target),
+            "16bit" | "32bit" | "64bit" => {
+                Target::PointerWidth(target.strip_suffix("bit").unwrap())
+            }
+            "endian-big" | "endian-little" => {
+                Target::Endian(target.strip_prefix("endian-").unwrap())
+            }
+            "stable" | "beta" | "nightly" | "dev" => Target::Channel(target),
+            _ if is_target_triple(target) => Target::Triple(target),
+            _ => return None,
+        };
+
+        Some(if only { Condition::Only(target) } else { Condition::Ignore(target) })
+    }
+
+    fn parse_rruxwry_directive(source: &'src str) -> Option<RruxwryDirective> {
+        Some(match source {
             "crate" => RruxwryDirective::AuxCrateBegin,
             "raw-crate" => RruxwryDirective::RawCrateBegin,
             "}" => RruxwryDirective::CrateEnd,
             _ => return None,
+        })
+    }
+
+    fn parse_delimiter(
+        &mut self,
+        source: Spanned<&'src str>,
+        directive: RruxwryDirective,
+    ) -> Result<SimpleDirective<'src>, Error<'src>> {
+        let raw = match directive {
+            RruxwryDirective::AuxCrateBegin => false,
+            RruxwryDirective::RawCrateBegin => true,
+            RruxwryDirective::CrateEnd => {
+                return Ok(SimpleDirective::InlineCrate(Delimiter::End(source.span)));
+            }
         };
-        Some(SimpleDirective::Rruxwry(directive))
+
+        self.parse_whitespace();
+        let name = self.expect_many(|char| char.is_alphanumeric() || char == '_')?;
+        self.parse_whitespace();
+        self.expect('{')?;
+
+        Ok(SimpleDirective::InlineCrate(Delimiter::Begin { name, raw }))
     }
 
     fn parse_polarity(source: &'src str) -> (&'src str, Polarity) {
@@ -838,6 +1280,16 @@ impl<'src> Parser<'src> {
         self.spanned(span)
     }
 
+    /// Parse a double-quoted string.
+    ///
+    /// Like in compiletest, there are no escape sequences.
+    fn parse_quoted(&mut self) -> Result<Spanned<&'src str>, Error<'src>> {
+        self.expect('"')?;
+        let result = self.parse_while(|char| char != '"');
+        self.expect('"')?;
+        Ok(result)
+    }
+
     // FIXME: Should we bail on empty lines?
     fn parse_until_line_break(&mut self) -> Spanned<&'src str> {
         self.parse_while(|char| char != '\n')
@@ -858,11 +1310,128 @@ impl<'src> Parser<'src> {
     }
 }
 
+/// The operating systems tests can be conditional on (`target_os`).
+const OSES: &[&str] = &[
+    "aix",
+    "android",
+    "cuda",
+    "dragonfly",
+    "emscripten",
+    "espidf",
+    "freebsd",
+    "fuchsia",
+    "haiku",
+    "hermit",
+    "horizon",
+    "hurd",
+    "illumos",
+    "ios",
+    "l4re",
+    "linux",
+    "macos",
+    "netbsd",
+    "none",
+    "nto",
+    "nuttx",
+    "openbsd",
+    "psp",
+    "redox",
+    "rtems",
+    "solaris",
+    "teeos",
+    "trusty",
+    "tvos",
+    "uefi",
+    "visionos",
+    "vita",
+    "vxworks",
+    "wasi",
+    "watchos",
+    "xous",
+    "zkvm",
+];
+
+/// The architectures tests can be conditional on (`target_arch`).
+const ARCHES: &[&str] = &[
+    "aarch64",
+    "amdgpu",
+    "arm",
+    "arm64ec",
+    "avr",
+    "bpf",
+    "csky",
+    "hexagon",
+    "loongarch32",
+    "loongarch64",
+    "m68k",
+    "mips",
+    "mips32r6",
+    "mips64",
+    "mips64r6",
+    "msp430",
+    "nvptx64",
+    "powerpc",
+    "powerpc64",
+    "riscv32",
+    "riscv64",
+    "s390x",
+    "sparc",
+    "sparc64",
+    "wasm32",
+    "wasm64",
+    "x86",
+    "x86_64",
+    "xtensa",
+];
+
+/// The environments tests can be conditional on (`target_env`).
+const ENVS: &[&str] = &["gnu", "msvc", "musl", "newlib", "ohos", "sgx", "uclibc"];
+
+/// The architecture components of target triples that don't start with the `target_arch`.
+const TRIPLE_ARCHES: &[&str] = &["amdgcn", "i386", "i586", "i686", "thumb"];
+
+/// Whether the given name looks like a target triple.
+///
+/// Target triples consist of at least three components, the first of which is the architecture
+/// (possibly refined, e.g., `armv7` or `riscv64gc`). This way, we don't mistake directives
+/// like `ignore-compare-mode-next-solver` for ones referring to a target.
+fn is_target_triple(name: &str) -> bool {
+    let mut components = name.split('-');
+    // unwrap: `split` always yields at least one item.
+    let arch = components.next().unwrap();
+    components.count() >= 2
+        && ARCHES.iter().chain(TRIPLE_ARCHES).any(|prefix| arch.starts_with(prefix))
+}
+
 #[allow(clippy::cast_possible_truncation)] // False positive, `len_utf8`'s result is always in 0..=4.
 fn len_utf8(char: char) -> u32 {
     char.len_utf8() as _
 }
 
+/// Parse the comma-separated extern modifiers (e.g., `priv,noprelude`) at the start of the prefix.
+fn parse_extern_modifiers<'src>(
+    prefix: Spanned<&'src str>,
+    source: &'src str,
+) -> Result<ExternModifiers, Error<'src>> {
+    let mut modifiers = ExternModifiers::default();
+    let mut start = prefix.span.start;
+
+    for modifier in source.split(',') {
+        let length = u32::try_from(modifier.len()).unwrap();
+        match modifier {
+            "priv" => modifiers.private = true,
+            "noprelude" => modifiers.no_prelude = true,
+            _ => {
+                let span = Span::with_len(start, length);
+                return Err(Error::UnknownExternModifier(Spanned::new(span, modifier)));
+            }
+        }
+        start += length + 1;
+    }
+
+    Ok(modifiers)
+}
+
 #[derive(Default)]
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 struct Errors<'src>(Vec<Error<'src>>);
@@ -908,6 +1477,11 @@ impl Error<'_> {
             Self::UnexpectedEndOfInput(span) => {
                 error(fmt!("unexpected end of input")).highlight(span, cx)
             }
+            Self::UnknownExternModifier(modifier) => {
+                error(fmt!("unknown extern modifier `{modifier}`"))
+                    .highlight(modifier.span, cx)
+                    .note(fmt!("available modifiers are `priv` and `noprelude`"))
+            }
             // FIXME: Source span!
             Self::InvalidValue(value) => error(fmt!("invalid value `{value}`")),
             Self::DuplicateRevisions(span) => {
@@ -933,16 +1507,41 @@ impl Error<'_> {
                     it.note(fmt!("available revisions are: {}", list(available)))
                 }
             }
+            Self::ExpectedPredicate(span) => error(fmt!("expected a revision or a predicate"))
+                .highlight(span, cx)
+                .note(fmt!("{PREDICATES}")),
+            Self::UnknownPredicate(name) => error(fmt!("unknown predicate `{name}`"))
+                .highlight(name.span, cx)
+                .note(fmt!("{PREDICATES}")),
+            Self::NotArity(predicate) => error(fmt!("predicate `not` takes exactly one argument"))
+                .highlight(predicate.span, cx),
             Self::AuxiliaryRevisionDeclaration(span) => {
                 warn(fmt!("revision declaration in auxiliary file"))
                     .highlight(span, cx)
                     .note(fmt!("declared revisions are inherited from the principal file"))
             }
+            Self::ConditionalInlineCrate(span) => {
+                error(fmt!("inline crates cannot be conditional")).highlight(span, cx)
+            }
+            Self::NestedInlineCrate(span) => error(fmt!("inline crates cannot be nested"))
+                .highlight(span, cx)
+                .help(fmt!("close the enclosing inline crate with `//@ }}` first")),
+            Self::UnexpectedInlineCrateEnd(span) => {
+                error(fmt!("unexpected end of inline crate")).highlight(span, cx)
+            }
+            Self::UnterminatedInlineCrate(name) => {
+                error(fmt!("unterminated inline crate `{name}`"))
+                    .highlight(name.span, cx)
+                    .help(fmt!("close it with `//@ }}`"))
+            }
         }
         .done();
     }
 }
 
+const PREDICATES: &str =
+    "predicates are revisions, `true`, `false`, `not(…)`, `any(…)` and `all(…)`";
+
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 enum Error<'src> {
     UnavailableDirective {
@@ -958,12 +1557,20 @@ enum Error<'src> {
     },
     UnexpectedEndOfInput(Span),
     InvalidValue(&'src str),
+    UnknownExternModifier(Spanned<&'src str>),
     DuplicateRevisions(Span),
     UndeclaredRevision {
         revision: Spanned<&'src str>,
         available: BTreeSet<&'src str>,
     },
+    ExpectedPredicate(Span),
+    UnknownPredicate(Spanned<&'src str>),
+    NotArity(Spanned<&'src str>),
     AuxiliaryRevisionDeclaration(Span),
+    ConditionalInlineCrate(Span),
+    NestedInlineCrate(Span),
+    UnexpectedInlineCrateEnd(Span),
+    UnterminatedInlineCrate(Spanned<&'src str>),
 }
 
 // FIXME: Overly general name for this.
@@ -90,8 +90,192 @@ fn unavailable_jsondocck_directive_htmldocck() {
 #[test]
 fn unsupported_directive() {
     assert_eq!(
-        parse_directive("check-pass", Scope::Base),
-        Err(Error::UnsupportedDirective(spanned(0, 10, "check-pass")))
+        parse_directive("known-bug: #0", Scope::Base),
+        Err(Error::UnsupportedDirective(spanned(0, 9, "known-bug")))
+    );
+}
+
+#[test]
+fn condition_directives() {
+    let condition = |source, start, end, condition| {
+        assert_eq!(
+            parse_directive(source, Scope::Base),
+            Ok(Directive {
+                predicate: None,
+                bare: SimpleDirective::Condition(spanned(start, end, condition)),
+            })
+        );
+    };
+    condition("only-x86_64", 0, 11, Condition::Only(Target::Arch("x86_64")));
+    condition("ignore-64bit", 0, 12, Condition::Ignore(Target::PointerWidth("64")));
+    condition("ignore-windows FIXME: flaky", 0, 14, Condition::Ignore(Target::Family("windows")));
+    condition(
+        "only-x86_64-unknown-linux-gnu",
+        0,
+        29,
+        Condition::Only(Target::Triple("x86_64-unknown-linux-gnu")),
+    );
+    condition("needs-unwind", 0, 12, Condition::Needs(Capability::Unwind));
+}
+
+#[test]
+fn target_triple_condition_directives() {
+    for triple in [
+        "x86_64-pc-windows-msvc",
+        "i686-unknown-linux-gnu",
+        "armv7-unknown-linux-gnueabihf",
+        "thumbv7em-none-eabihf",
+        "riscv64gc-unknown-linux-gnu",
+        "wasm32-unknown-unknown",
+        "amdgcn-amd-amdhsa",
+    ] {
+        let source = format!("ignore-{triple}");
+        assert_eq!(
+            parse_directive(&source, Scope::Base),
+            Ok(Directive {
+                predicate: None,
+                bare: SimpleDirective::Condition(spanned(
+                    0,
+                    u32::try_from(source.len()).unwrap(),
+                    Condition::Ignore(Target::Triple(triple))
+                )),
+            })
+        );
+    }
+}
+
+#[test]
+fn unsupported_condition_directive() {
+    assert_eq!(
+        parse_directive("needs-sanitizer-address", Scope::Base),
+        Err(Error::UnsupportedDirective(spanned(0, 23, "needs-sanitizer-address")))
+    );
+    assert_eq!(
+        parse_directive("only-test", Scope::Base),
+        Err(Error::UnsupportedDirective(spanned(0, 9, "only-test")))
+    );
+    // Hyphenated names don't refer to target triples unless they start with an architecture.
+    assert_eq!(
+        parse_directive("ignore-compare-mode-next-solver", Scope::Base),
+        Err(Error::UnsupportedDirective(spanned(0, 31, "ignore-compare-mode-next-solver")))
+    );
+    assert_eq!(
+        parse_directive("only-x86_64-unknown", Scope::Base),
+        Err(Error::UnsupportedDirective(spanned(0, 19, "only-x86_64-unknown")))
+    );
+}
+
+#[test]
+fn mode_directive() {
+    assert_eq!(
+        parse_directive("run-fail", Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::Mode(spanned(
+                0,
+                8,
+                Mode { phase: Phase::Run, outcome: Outcome::Fail }
+            )),
+        })
+    );
+}
+
+#[test]
+fn failure_status_directive() {
+    assert_eq!(
+        parse_directive("failure-status: 101", Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::FailureStatus(spanned(16, 19, 101))
+        })
+    );
+}
+
+#[test]
+fn failure_status_directive_invalid_value() {
+    assert_eq!(
+        parse_directive("failure-status: panic", Scope::Base),
+        Err(Error::InvalidValue("panic"))
+    );
+}
+
+#[test]
+fn aux_crate_directive_modifiers() {
+    assert_eq!(
+        parse_directive("aux-crate: priv,noprelude:dep=file.rs", Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::Aux(AuxiliaryDirective::Crate {
+                name: "dep",
+                modifiers: ExternModifiers { private: true, no_prelude: true },
+                path: spanned(30, 37, "file.rs")
+            }),
+        })
+    );
+}
+
+#[test]
+fn aux_crate_directive_unknown_modifier() {
+    assert_eq!(
+        parse_directive("aux-crate: priv,pub:dep=file.rs", Scope::Base),
+        Err(Error::UnknownExternModifier(spanned(16, 19, "pub")))
+    );
+}
+
+#[test]
+fn exec_env_directive() {
+    assert_eq!(
+        parse_directive("exec-env:KEY=value", Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::EnvVar("KEY", Some("value"), Stage::RunTime)
+        })
+    );
+}
+
+#[test]
+fn forbid_output_directive() {
+    assert_eq!(
+        parse_directive("forbid-output: unused variable", Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::Pattern(Pattern {
+                source: spanned(15, 30, "unused variable"),
+                kind: PatternKind::Substring,
+                polarity: Polarity::Negative,
+            }),
+        })
+    );
+}
+
+#[test]
+fn regex_error_pattern_directive() {
+    assert_eq!(
+        parse_directive("regex-error-pattern: E0\\d{3}", Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::Pattern(Pattern {
+                source: spanned(21, 28, r"E0\d{3}"),
+                kind: PatternKind::Regex,
+                polarity: Polarity::Positive,
+            }),
+        })
+    );
+}
+
+#[test]
+fn regex_error_pattern_directive_invalid_regex() {
+    // The regex only gets validated once it's compiled by the checker.
+    assert_eq!(
+        parse_directive("regex-error-pattern: (unclosed", Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::Pattern(Pattern {
+                source: spanned(21, 30, "(unclosed"),
+                kind: PatternKind::Regex,
+                polarity: Polarity::Positive,
+            }),
+        })
     );
 }
 
@@ -107,21 +291,74 @@ fn unknown_directive() {
 fn build_aux_docs_directive() {
     assert_eq!(
         parse_directive("build-aux-docs", Scope::HtmlDocCk),
-        Ok(Directive { revision: None, bare: SimpleDirective::BuildAuxDocs })
+        Ok(Directive { predicate: None, bare: SimpleDirective::BuildAuxDocs })
     );
 }
 
+#[test]
+fn no_auto_check_cfg_directive() {
+    let instantiate = |revision| {
+        let mut errors = Errors::default();
+        let directives = parse_directives(
+            "//@ revisions: one two\n//@[two] no-auto-check-cfg",
+            Scope::Base,
+            Flavor::Vanilla,
+            &mut errors,
+        );
+        assert_eq!(errors, default());
+        directives.instantiate(Some(Revision(revision))).unwrap()
+    };
+    assert!(!instantiate("one").no_auto_check_cfg);
+    assert!(instantiate("two").no_auto_check_cfg);
+}
+
 #[test]
 fn htmldocck_directive() {
     assert_eq!(
         parse_directive("has 'krate/constant.K.html'", Scope::HtmlDocCk),
         Ok(Directive {
-            revision: None,
-            bare: SimpleDirective::HtmlDocCk(HtmlDocCkDirective::Has, Polarity::Positive),
+            predicate: None,
+            bare: SimpleDirective::HtmlDocCk(DocCheck {
+                directive: HtmlDocCkDirective::Has,
+                polarity: Polarity::Positive,
+                span: span(0, 27),
+                arguments: "'krate/constant.K.html'",
+            }),
+        })
+    );
+}
+
+#[test]
+fn normalize_stderr_directive() {
+    assert_eq!(
+        parse_directive(r#"normalize-stderr: "\d+ bytes" -> "$$N bytes""#, Scope::Base),
+        Ok(Directive {
+            predicate: None,
+            bare: SimpleDirective::Normalize(Normalization {
+                stream: Stream::Stderr,
+                regex: spanned(19, 28, r"\d+ bytes"),
+                replacement: "$$N bytes",
+            }),
         })
     );
 }
 
+#[test]
+fn normalize_stdout_directive_unquoted_replacement() {
+    assert_eq!(
+        parse_directive(r#"normalize-stdout: "a" -> b"#, Scope::Base),
+        Err(Error::UnexpectedToken { actual: spanned(25, 26, 'b'), expected: '"' })
+    );
+}
+
+#[test]
+fn normalize_stderr_directive_missing_arrow() {
+    assert_eq!(
+        parse_directive(r#"normalize-stderr: "a" "b""#, Scope::Base),
+        Err(Error::UnexpectedToken { actual: spanned(22, 23, '"'), expected: '-' })
+    );
+}
+
 #[test]
 fn edition_directive_no_colon() {
     // FIXME: This should only warn (under -@) and discard the whole directive
@@ -136,7 +373,7 @@ fn revisions_directive() {
     assert_eq!(
         parse_directive("revisions: one \ttwo  three", Scope::Base),
         Ok(Directive {
-            revision: None,
+            predicate: None,
             bare: SimpleDirective::Revisions(vec!["one", "three", "two"])
         })
     );
@@ -155,7 +392,7 @@ fn conditional_directive() {
     assert_eq!(
         parse_directive("[rev] aux-build: file.rs", Scope::Base),
         Ok(Directive {
-            revision: Some(spanned(1, 4, "rev")),
+            predicate: Some(spanned(1, 4, Predicate::Revision("rev"))),
             bare: SimpleDirective::Aux(AuxiliaryDirective::Build {
                 path: spanned(17, 24, "file.rs")
             }),
@@ -185,7 +422,7 @@ fn empty_revision() {
     assert_eq!(
         parse_directive("[] edition: 2021", Scope::Base),
         Ok(Directive {
-            revision: Some(spanned(1, 1, "")),
+            predicate: Some(spanned(1, 1, Predicate::Revision(""))),
             bare: SimpleDirective::Edition(spanned(12, 16, "2021"))
         })
     );
@@ -197,7 +434,7 @@ fn padded_revision_not_trimmed() {
     assert_eq!(
         parse_directive(" [  padded \t] edition: 2015", Scope::Base),
         Ok(Directive {
-            revision: Some(spanned(2, 12, "  padded \t")),
+            predicate: Some(spanned(2, 12, Predicate::Revision("  padded \t"))),
             bare: SimpleDirective::Edition(spanned(23, 27, "2015"))
         })
     );
@@ -209,7 +446,7 @@ fn quoted_revision_not_unquoted() {
     assert_eq!(
         parse_directive("[\"literally\"] compile-flags:", Scope::Base),
         Ok(Directive {
-            revision: Some(spanned(1, 12, "\"literally\"")),
+            predicate: Some(spanned(1, 12, Predicate::Revision("\"literally\""))),
             bare: SimpleDirective::Flags("", Stage::CompileTime, FlagScope::Base)
         })
     );
@@ -221,7 +458,7 @@ fn commas_inside_revision() {
     assert_eq!(
         parse_directive("[one,two] compile-flags:", Scope::Base),
         Ok(Directive {
-            revision: Some(spanned(1, 8, "one,two")),
+            predicate: Some(spanned(1, 8, Predicate::Revision("one,two"))),
             bare: SimpleDirective::Flags("", Stage::CompileTime, FlagScope::Base)
         })
     );
@@ -234,7 +471,7 @@ fn conditional_directives_directive() {
     assert_eq!(
         parse_directive("[recur] revisions: recur", Scope::Base),
         Ok(Directive {
-            revision: Some(spanned(1, 6, "recur")),
+            predicate: Some(spanned(1, 6, Predicate::Revision("recur"))),
             bare: SimpleDirective::Revisions(vec!["recur"])
         })
     );
@@ -309,9 +546,12 @@ fn conditional_directives() {
                 ..default()
             },
             uninstantiated: vec![
-                (spanned(27, 30, "one"), SimpleDirective::Edition(spanned(41, 45, "2018"))),
                 (
-                    spanned(86, 89, "two"),
+                    spanned(27, 30, Predicate::Revision("one")),
+                    SimpleDirective::Edition(spanned(41, 45, "2018"))
+                ),
+                (
+                    spanned(86, 89, Predicate::Revision("two")),
                     SimpleDirective::Flags(
                         "-Zparse-crate-root-only",
                         Stage::CompileTime,
@@ -342,6 +582,7 @@ fn instantiate_conditional_directives() {
     assert_eq!(
         directives,
         Ok(InstantiatedDirectives {
+            revisions: ["one", "two"].into(),
             v_opts: VerbatimOptions {
                 arguments: vec!["--crate-type=lib", "-Zparse-crate-root-only"],
                 ..default()
@@ -367,7 +608,7 @@ fn conditional_directives_revision_declared_after_use() {
             revisions: ["classic", "next"].into(),
             instantiated: default(),
             uninstantiated: vec![(
-                spanned(4, 8, "next"),
+                spanned(4, 8, Predicate::Revision("next")),
                 SimpleDirective::Flags("-Znext-solver", Stage::CompileTime, FlagScope::Base)
             )],
             role: Role::Principal
@@ -393,10 +634,13 @@ fn conditional_directives_undeclared_revisions() {
             instantiated: default(),
             uninstantiated: vec![
                 (
-                    spanned(4, 9, "block"),
+                    spanned(4, 9, Predicate::Revision("block")),
                     SimpleDirective::Flags("--crate-type lib", Stage::CompileTime, FlagScope::Base)
                 ),
-                (spanned(47, 51, "wall"), SimpleDirective::Edition(spanned(62, 66, "2021"))),
+                (
+                    spanned(47, 51, Predicate::Revision("wall")),
+                    SimpleDirective::Edition(spanned(62, 66, "2021"))
+                ),
             ],
             role: Role::Principal
         }
@@ -432,3 +676,112 @@ fn missing_active_revision() {
         Err(InstantiationError::MissingActiveRevision { available: ["first", "second"].into() })
     );
 }
+
+#[test]
+fn inline_crates() {
+    let mut errors = Errors::default();
+    let directives = parse_directives(
+        "//@ edition: 2021\n//@ crate dep {\n//@ edition: 2015\npub fn f() {}\n//@ }\n//@ raw-crate other {\n//@ }\n",
+        Scope::Base,
+        Flavor::Rruxwry,
+        &mut errors,
+    );
+
+    assert_eq!(errors, Errors::default());
+    assert_eq!(directives.instantiated.edition, Some(spanned(13, 17, "2021")));
+    assert_eq!(
+        directives.instantiated.inline_crates,
+        [
+            InlineCrate { name: spanned(28, 31, "dep"), raw: false, body: span(34, 66) },
+            InlineCrate { name: spanned(86, 91, "other"), raw: true, body: span(94, 94) },
+        ]
+    );
+}
+
+#[test]
+fn unterminated_nested_inline_crates() {
+    let mut errors = Errors::default();
+    parse_directives("//@ crate a {\n//@ crate b {\n", Scope::Base, Flavor::Rruxwry, &mut errors);
+
+    assert_eq!(
+        errors,
+        Errors(vec![
+            Error::NestedInlineCrate(span(24, 25)),
+            Error::UnterminatedInlineCrate(spanned(10, 11, "a")),
+        ])
+    );
+}
+
+#[test]
+fn instantiate_predicates() {
+    let instantiate = |revision| {
+        let mut errors = Errors::default();
+        let directives = parse_directives(
+            "//@ revisions: old next e2015\n\
+             //@[any(next, old)] compile-flags: -Zfoo\n\
+             //@[ not( e2015 ) ] edition: 2021\n\
+             //@[all(true, not(any()), next)] compile-flags: -Zbar\n\
+             //@[false] compile-flags: -Zbaz",
+            Scope::Base,
+            Flavor::Rruxwry,
+            &mut errors,
+        );
+        assert_eq!(errors, default());
+        let directives = directives.instantiate(Some(Revision(revision))).unwrap();
+        (directives.v_opts.arguments, directives.edition.map(|edition| edition.bare))
+    };
+
+    assert_eq!(instantiate("old"), (vec!["-Zfoo"], Some("2021")));
+    assert_eq!(instantiate("next"), (vec!["-Zfoo", "-Zbar"], Some("2021")));
+    assert_eq!(instantiate("e2015"), (vec![], None));
+}
+
+#[test]
+fn predicates_undeclared_revisions() {
+    let mut errors = Errors::default();
+    parse_directives(
+        "//@ revisions: one\n//@[any(one, not(two))] edition: 2021",
+        Scope::Base,
+        Flavor::Rruxwry,
+        &mut errors,
+    );
+    assert_eq!(
+        errors,
+        Errors(vec![Error::UndeclaredRevision {
+            revision: spanned(36, 39, "two"),
+            available: ["one"].into()
+        }])
+    );
+}
+
+#[test]
+fn predicates_vanilla() {
+    assert_eq!(
+        parse_directive("[not(one)] edition: 2021", Scope::Base),
+        Ok(Directive {
+            predicate: Some(spanned(1, 9, Predicate::Revision("not(one)"))),
+            bare: SimpleDirective::Edition(spanned(20, 24, "2021"))
+        })
+    );
+}
+
+#[test]
+fn invalid_predicates() {
+    let parse = |source| {
+        Parser::new(source, Scope::Base, Role::Principal, Flavor::Rruxwry, 0)
+            .parse_directive()
+            .map(|_| ())
+    };
+
+    assert_eq!(parse("[] edition: 2021"), Err(Error::ExpectedPredicate(span(1, 1))));
+    assert_eq!(parse("[any(one,,two)] edition: 2021"), Err(Error::ExpectedPredicate(span(9, 9))));
+    assert_eq!(
+        parse("[none(one)] edition: 2021"),
+        Err(Error::UnknownPredicate(spanned(1, 5, "none")))
+    );
+    assert_eq!(parse("[not(one, two)] edition: 2021"), Err(Error::NotArity(spanned(1, 14, "not"))));
+    assert_eq!(
+        parse("[all(one two)] edition: 2021"),
+        Err(Error::UnexpectedToken { actual: spanned(9, 10, 't'), expected: ')' })
+    );
+}
@@ -6,6 +6,10 @@ pub(crate) type Result<T = (), E = Error> = std::result::Result<T, E>;
 pub(crate) enum Error {
     Io(io::Error),
     Emitted(EmittedError),
+    /// The test got ignored due to an `ignore-*`, `only-*` or `needs-*` directive.
+    ///
+    /// The reason has already been reported.
+    Ignored,
 }
 
 impl Error {
@@ -13,6 +17,7 @@ impl Error {
         match self {
             Self::Io(error) => self::error(fmt!("{error}")).done(),
             Self::Emitted(error) => error,
+            Self::Ignored => self::error(fmt!("the test was ignored")).done(),
         }
     }
 }
@@ -8,11 +8,17 @@ use crate::{
         CrateName, CrateType, DocBackend, Edition, ExtEdition, Identity, PlusPrefixedToolchain,
     },
     directive::{Flavor, Revision},
-    operate::{Bless, CompileMode, DirectiveOptions, DocMode, Open, Operation, Run, Test},
+    operate::{
+        Bless, CompileMode, Dependency, DirectiveOptions, DocMode, Open, Operation, Run, Test,
+    },
     source::SourcePathBuf,
     utility::{Conjunction, ListingExt as _, default, parse},
 };
-use std::ffi::OsString;
+use std::{ffi::OsString, num::NonZero};
+
+mod graph;
+#[cfg(test)]
+mod test;
 
 // Similar to `-h`, `-V` is compatible with all other flags and renders required arguments optional.
 // While there could be a world where `-V` is incompatible with flags like `-r` (run) or `-o` (open)
@@ -67,10 +73,11 @@ pub(crate) fn arguments() -> Arguments {
     Arguments {
         toolchain,
         source,
-        dependencies: matches
-            .remove_many(id::extern_)
-            .map(|paths| paths.into_iter().map(SourcePathBuf::new).collect())
-            .unwrap_or_default(),
+        dependencies: {
+            let mut dependencies: Vec<_> = matches.remove_one(id::build_graph).unwrap_or_default();
+            dependencies.extend(matches.remove_many(id::extern_).into_iter().flatten());
+            dependencies
+        },
         verbatim: matches.remove_many(id::verbatim).map(Iterator::collect).unwrap_or_default(),
         operation,
         crate_name: matches.remove_one(id::crate_name),
@@ -80,6 +87,7 @@ pub(crate) fn arguments() -> Arguments {
         edition: matches.remove_one(id::EDITION),
         b_opts: BuildOptions {
             cfgs: matches.remove_many(id::cfgs).map(Iterator::collect).unwrap_or_default(),
+            check_cfgs: default(),
             unstable_features: matches
                 .remove_many(id::unstable_features)
                 .map(Iterator::collect)
@@ -92,8 +100,15 @@ pub(crate) fn arguments() -> Arguments {
             no_dedupe: matches.remove_one(id::no_dedupe).unwrap_or_default(),
             log: matches.remove_one(id::log),
             no_backtrace: matches.remove_one(id::no_backtrace).unwrap_or_default(),
+            json_diagnostics: false,
+            ui_testing: false,
+            no_ice_reports: false,
+            prefer_dynamic: false,
+            out_dir: None,
+            dependency_search_paths: Vec::new(),
         },
         dbg_opts: DebugOptions { verbose: matches.remove_one(id::verbose).unwrap() },
+        jobs: matches.remove_one(id::jobs).unwrap_or(NonZero::<usize>::MIN),
         color: matches.remove_one(id::color).unwrap(),
     }
 }
@@ -255,12 +270,16 @@ fn source_arg() -> impl IntoIterator<Item = clap::Arg> {
         clap::Arg::new(id::extern_)
             .short('x')
             .long("extern")
-            .value_name("PATH")
-            .value_parser(clap::builder::ValueParser::path_buf())
+            .value_name("PATH[FLAGS]")
+            .value_parser(Dependency::parse_cli_style)
             .action(clap::ArgAction::Append)
-            // FIXME: Temporary limitation of the operation module.
-            .conflicts_with(id::directives)
             .help("Add the source file path to an extern crate"),
+        clap::Arg::new(id::build_graph)
+            .short('G')
+            .long("build-graph")
+            .value_name("GRAPH")
+            .value_parser(Dependency::parse_graph_cli_style)
+            .help("Describe a build graph of extern crates"),
     ]
 }
 
@@ -292,6 +311,13 @@ fn compiletest_args() -> impl IntoIterator<Item = clap::Arg> {
             .requires(id::compiletest)
             .action(clap::ArgAction::SetTrue)
             .help("Update the test expectations"),
+        clap::Arg::new(id::jobs)
+            .short('J')
+            .long("jobs")
+            .value_name("N")
+            .value_parser(clap::value_parser!(NonZero<usize>))
+            .requires(id::directives)
+            .help("Compile auxiliaries and revisions in up to N jobs at once"),
     ]
 }
 
@@ -321,29 +347,43 @@ fn edition_arg() -> clap::Arg {
 
 fn cfg_args() -> impl IntoIterator<Item = clap::Arg> {
     [
-        clap::Arg::new(id::cfgs)
-            .long("cfg")
-            // FIXME: This gets rendered as `<NAME[="VALUE"]>` by clap but ideally we'd print `<NAME>[="<VALUE>"]`.
-            .value_name(r#"NAME[="VALUE"]"#)
-            .action(clap::ArgAction::Append)
-            .help("Enable a configuration"),
+        cfg_arg(),
         clap::Arg::new(id::revision)
             .short('R')
             .long("revision")
             .value_name("NAME")
             .requires(id::directives)
-            .help("Enable a compiletest revision"),
+            .help("Enable a compiletest revision (`*` for each one in turn)"),
+        clap::Arg::new(id::all_revisions)
+            .long("all-revisions")
+            .action(clap::ArgAction::SetTrue)
+            .requires(id::directives)
+            .conflicts_with(id::revision)
+            .help("Enable each compiletest revision in turn"),
         // FIXME: This doesn't really belong in this "group" (`cfgs`)
-        clap::Arg::new(id::unstable_features)
-            .short('F')
-            .long("feature")
-            .value_name("NAME")
-            .value_parser(parse_unstable_feature_cli_style)
-            .action(clap::ArgAction::Append)
-            .help("Enable an experimental library or language feature"),
+        unstable_feature_arg(),
     ]
 }
 
+fn cfg_arg() -> clap::Arg {
+    clap::Arg::new(id::cfgs)
+        .long("cfg")
+        // FIXME: This gets rendered as `<NAME[="VALUE"]>` by clap but ideally we'd print `<NAME>[="<VALUE>"]`.
+        .value_name(r#"NAME[="VALUE"]"#)
+        .action(clap::ArgAction::Append)
+        .help("Enable a configuration")
+}
+
+fn unstable_feature_arg() -> clap::Arg {
+    clap::Arg::new(id::unstable_features)
+        .short('F')
+        .long("feature")
+        .value_name("NAME")
+        .value_parser(parse_unstable_feature_cli_style)
+        .action(clap::ArgAction::Append)
+        .help("Enable an experimental library or language feature")
+}
+
 fn extra_args() -> impl IntoIterator<Item = clap::Arg> {
     [
         clap::Arg::new(id::suppress_lints)
@@ -461,9 +501,13 @@ fn extract_normal_operation(operation: &str, matches: &mut clap::ArgMatches) ->
 
 fn extract_dir_opts(matches: &mut clap::ArgMatches) -> Option<DirectiveOptions> {
     let flavor = matches.remove_one::<Flavor>(id::directives)?;
+    let revision: Option<String> = matches.remove_one(id::revision);
+    let all_revisions = revision.as_deref() == Some("*")
+        || matches.remove_one(id::all_revisions).unwrap_or_default();
     Some(DirectiveOptions {
         flavor,
-        revision: matches.remove_one(id::revision).map(Revision),
+        revision: revision.filter(|revision| revision != "*").map(Revision),
+        all_revisions,
         test: match matches.remove_one(id::compiletest).unwrap_or_default() {
             false => Test::No,
             true => Test::Yes(match matches.remove_one(id::bless).unwrap_or_default() {
@@ -477,7 +521,7 @@ fn extract_dir_opts(matches: &mut clap::ArgMatches) -> Option<DirectiveOptions>
 pub(crate) struct Arguments {
     pub(crate) toolchain: Option<PlusPrefixedToolchain>,
     pub(crate) source: Option<Source>,
-    pub(crate) dependencies: Vec<SourcePathBuf>,
+    pub(crate) dependencies: Vec<Dependency>,
     pub(crate) verbatim: Vec<String>,
     pub(crate) operation: Operation,
     pub(crate) crate_name: Option<CrateName<String>>,
@@ -485,6 +529,7 @@ pub(crate) struct Arguments {
     pub(crate) edition: Option<ExtEdition<'static>>,
     pub(crate) b_opts: BuildOptions,
     pub(crate) dbg_opts: DebugOptions,
+    pub(crate) jobs: NonZero<usize>,
     pub(crate) color: clap::ColorChoice,
 }
 
@@ -493,6 +538,81 @@ pub(crate) enum Source {
     String(String),
 }
 
+impl Dependency {
+    /// Parse the path to an extern crate optionally followed by flags in square brackets.
+    ///
+    /// E.g., `dep.rs[-e2015 -F gce --cfg special -- -Zverbose-internals]`. Otherwise, the path
+    /// is taken verbatim. Paths that themselves end in a bracket can be followed by `[]`.
+    fn parse_cli_style(source: &str) -> Result<Self, String> {
+        let (path, arguments) =
+            match source.strip_suffix(']').and_then(|source| source.rsplit_once('[')) {
+                Some((path, flags)) => (
+                    path.trim_end(),
+                    shlex::split(flags).ok_or("the flags are not properly quoted")?,
+                ),
+                None => (source, Vec::new()),
+            };
+        if path.is_empty() {
+            return Err("expected a path".into());
+        }
+        Self::parse_flags(path, &arguments)
+    }
+
+    /// Parse a build graph whose nodes may be followed by flags specific to them.
+    ///
+    /// E.g., `a.rs <- b.rs[-e2015], c.rs; b.rs <- c.rs[-F gce]`.
+    fn parse_graph_cli_style(source: &str) -> Result<Vec<Self>, String> {
+        graph::parse(source)?
+            .into_iter()
+            .map(|node| {
+                let arguments = match node.flags {
+                    Some(flags) => shlex::split(flags).ok_or_else(|| {
+                        format!("the flags of `{}` are not properly quoted", node.path)
+                    })?,
+                    None => Vec::new(),
+                };
+                let dependency = Self::parse_flags(node.path, &arguments)
+                    .map_err(|error| format!("invalid flags for `{}`: {error}", node.path))?;
+                Ok(Self { dependencies: node.dependencies, ..dependency })
+            })
+            .collect()
+    }
+
+    fn parse_flags(path: &str, arguments: &[String]) -> Result<Self, String> {
+        let mut matches = clap::Command::new(id::extern_)
+            .no_binary_name(true)
+            .disable_help_flag(true)
+            .args(crate_name_and_type_args())
+            .arg(edition_arg())
+            .arg(cfg_arg())
+            .arg(unstable_feature_arg())
+            .arg(verbatim_arg())
+            .try_get_matches_from(arguments)
+            .map_err(|error| {
+                // Only keep the gist, the usage message would refer to the wrong command.
+                let error = error.render().to_string();
+                let error = error.lines().next().unwrap_or_default();
+                error.strip_prefix("error: ").unwrap_or(error).to_owned()
+            })?;
+
+        Ok(Self {
+            path: SourcePathBuf::new(path.into()),
+            name: matches.remove_one(id::crate_name),
+            typ: matches
+                .remove_one(id::crate_type)
+                .map(|typ: String| CrateType::parse_cli_style(typ.leak())),
+            edition: matches.remove_one(id::EDITION),
+            cfgs: matches.remove_many(id::cfgs).map(Iterator::collect).unwrap_or_default(),
+            unstable_features: matches
+                .remove_many(id::unstable_features)
+                .map(Iterator::collect)
+                .unwrap_or_default(),
+            verbatim: matches.remove_many(id::verbatim).map(Iterator::collect).unwrap_or_default(),
+            dependencies: Vec::new(),
+        })
+    }
+}
+
 impl ExtEdition<'static> {
     fn parse_cli_style(source: &str) -> Result<Self, String> {
         parse!(
@@ -664,9 +784,9 @@ macro_rules! ids {
 
 #[rustfmt::skip]
 ids! {
-    bless, build, cfgs, check_only, color, compiletest, crate_name, crate_type, crate_version,
-    cross_crate, directives, doc, dump, EDITION, extern_, hidden, identity, internals,
-    json, layout, link_to_def, log, next_solver, normalize, no_backtrace, no_dedupe, open,
-    PATH, query_engine_version, private, revision, run, shallow, SOURCE, suppress_lints, THEME,
-    unstable_features, verbatim, verbose,
+    all_revisions, bless, build, build_graph, cfgs, check_only, color, compiletest, crate_name,
+    crate_type, crate_version, cross_crate, directives, doc, dump, EDITION, extern_, hidden,
+    identity, internals, jobs, json, layout, link_to_def, log, next_solver, normalize,
+    no_backtrace, no_dedupe, open, PATH, query_engine_version, private, revision, run, shallow,
+    SOURCE, suppress_lints, THEME, unstable_features, verbatim, verbose,
 }
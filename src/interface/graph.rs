@@ -0,0 +1,155 @@
+//! A concise DSL for describing build graphs (`-G`, `--build-graph`).
+//!
+//! A graph consists of statements separated by `;`, each of the form `NODE <- NODE, …` meaning
+//! that the crate on the left depends on the crates on the right. The list of dependencies may
+//! be omitted. A node is the path to a source file optionally followed by flags in square
+//! brackets, e.g., `a.rs[-e2015 --cfg special] <- b.rs, c.rs; b.rs <- c.rs`.
+
+#[cfg(test)]
+mod test;
+
+#[cfg_attr(test, derive(PartialEq, Eq, Debug))]
+pub(super) struct Node<'src> {
+    pub(super) path: &'src str,
+    pub(super) flags: Option<&'src str>,
+    /// The indices of the direct dependencies, all of which precede the node.
+    pub(super) dependencies: Vec<usize>,
+}
+
+/// Parse the given build graph and order its nodes topologically, dependencies first.
+pub(super) fn parse(source: &str) -> Result<Vec<Node<'_>>, String> {
+    let mut nodes = Vec::new();
+
+    for statement in split(source, ";")? {
+        let statement = statement.trim();
+        // We permit empty statements to allow for trailing semicolons.
+        if statement.is_empty() {
+            continue;
+        }
+
+        let (dependent, dependencies) = match *split(statement, "<-")? {
+            [dependent] => (dependent, None),
+            [dependent, dependencies] => (dependent, Some(dependencies)),
+            _ => return Err(format!("the statement `{statement}` contains more than one `<-`")),
+        };
+        let dependent = intern(&mut nodes, dependent)?;
+
+        for dependency in dependencies.map(|deps| split(deps, ",")).transpose()?.unwrap_or_default()
+        {
+            let dependency = intern(&mut nodes, dependency)?;
+            if !nodes[dependent].dependencies.contains(&dependency) {
+                nodes[dependent].dependencies.push(dependency);
+            }
+        }
+    }
+
+    let mut states = vec![State::Unvisited; nodes.len()];
+    let mut order = Vec::with_capacity(nodes.len());
+    for index in 0..nodes.len() {
+        visit(index, &nodes, &mut states, &mut order)?;
+    }
+
+    let mut ranks = vec![0; nodes.len()];
+    for (rank, &index) in order.iter().enumerate() {
+        ranks[index] = rank;
+    }
+
+    let mut nodes: Vec<_> = nodes
+        .into_iter()
+        .map(|node| Node {
+            dependencies: node.dependencies.iter().map(|&index| ranks[index]).collect(),
+            ..node
+        })
+        .enumerate()
+        .collect();
+    nodes.sort_by_key(|&(index, _)| ranks[index]);
+
+    Ok(nodes.into_iter().map(|(_, node)| node).collect())
+}
+
+/// Look up the node or add it if it doesn't exist yet.
+fn intern<'src>(nodes: &mut Vec<Node<'src>>, source: &'src str) -> Result<usize, String> {
+    let source = source.trim();
+    let (path, flags) = match source.strip_suffix(']').and_then(|source| source.split_once('[')) {
+        Some((path, flags)) => (path.trim_end(), Some(flags)),
+        None => (source, None),
+    };
+
+    if path.is_empty() {
+        return Err(format!("expected a path but found `{source}`"));
+    }
+    if path.contains(['[', ']']) {
+        return Err(format!("unexpected bracket in `{source}`"));
+    }
+
+    if let Some(index) = nodes.iter().position(|node| node.path == path) {
+        if let Some(flags) = flags
+            && nodes[index].flags.replace(flags).is_some()
+        {
+            return Err(format!("the flags of `{path}` are specified more than once"));
+        }
+        return Ok(index);
+    }
+
+    nodes.push(Node { path, flags, dependencies: Vec::new() });
+    Ok(nodes.len() - 1)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Unvisited,
+    Visiting,
+    Visited,
+}
+
+fn visit(
+    index: usize,
+    nodes: &[Node<'_>],
+    states: &mut [State],
+    order: &mut Vec<usize>,
+) -> Result<(), String> {
+    match states[index] {
+        State::Visited => return Ok(()),
+        State::Visiting => {
+            return Err(format!("the crate `{}` is part of a dependency cycle", nodes[index].path));
+        }
+        State::Unvisited => {}
+    }
+
+    states[index] = State::Visiting;
+    for &dependency in &nodes[index].dependencies {
+        visit(dependency, nodes, states, order)?;
+    }
+    states[index] = State::Visited;
+    order.push(index);
+
+    Ok(())
+}
+
+/// Split the source at the given separator outside of square brackets.
+fn split<'src>(source: &'src str, separator: &str) -> Result<Vec<&'src str>, String> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut bracketed = false;
+
+    for (index, char) in source.char_indices() {
+        match (char, bracketed) {
+            ('[', false) => bracketed = true,
+            (']', true) => bracketed = false,
+            ('[', true) => return Err("unexpected nested `[`".into()),
+            (']', false) => return Err("unexpected unmatched `]`".into()),
+            (_, false) if index >= start && source[index..].starts_with(separator) => {
+                pieces.push(&source[start..index]);
+                start = index + separator.len();
+            }
+            _ => {}
+        }
+    }
+
+    if bracketed {
+        return Err("expected a closing `]`".into());
+    }
+    pieces.push(&source[start..]);
+
+    Ok(pieces)
+}
@@ -0,0 +1,85 @@
+use super::{Node, parse};
+
+fn node<'src>(path: &'src str, flags: Option<&'src str>, dependencies: &[usize]) -> Node<'src> {
+    Node { path, flags, dependencies: dependencies.to_vec() }
+}
+
+#[test]
+fn single_node() {
+    assert_eq!(parse("a.rs"), Ok(vec![node("a.rs", None, &[])]));
+}
+
+#[test]
+fn dependencies_precede_dependents() {
+    assert_eq!(
+        parse("a.rs <- b.rs, c.rs; b.rs <- c.rs"),
+        Ok(vec![node("c.rs", None, &[]), node("b.rs", None, &[0]), node("a.rs", None, &[1, 0])])
+    );
+}
+
+#[test]
+fn flags() {
+    assert_eq!(
+        parse("a.rs[-e2015 --cfg special] <- b.rs [-n other]; b.rs"),
+        Ok(vec![
+            node("b.rs", Some("-n other"), &[]),
+            node("a.rs", Some("-e2015 --cfg special"), &[0])
+        ])
+    );
+}
+
+#[test]
+fn separators_within_flags() {
+    assert_eq!(
+        parse("a.rs[-- -Cllvm-args=a,b;c<-d]"),
+        Ok(vec![node("a.rs", Some("-- -Cllvm-args=a,b;c<-d"), &[])])
+    );
+}
+
+#[test]
+fn trailing_semicolon_and_duplicate_dependency() {
+    assert_eq!(
+        parse(" a.rs <- b.rs, b.rs ; "),
+        Ok(vec![node("b.rs", None, &[]), node("a.rs", None, &[0])])
+    );
+}
+
+#[test]
+fn flags_specified_twice() {
+    assert_eq!(
+        parse("a.rs[-e2015] <- b.rs; a.rs[-e2018]"),
+        Err("the flags of `a.rs` are specified more than once".into())
+    );
+}
+
+#[test]
+fn cycle() {
+    assert_eq!(
+        parse("a.rs <- b.rs; b.rs <- c.rs; c.rs <- a.rs"),
+        Err("the crate `a.rs` is part of a dependency cycle".into())
+    );
+    assert_eq!(parse("a.rs <- a.rs"), Err("the crate `a.rs` is part of a dependency cycle".into()));
+}
+
+#[test]
+fn missing_path() {
+    assert_eq!(parse("a.rs <-"), Err("expected a path but found ``".into()));
+    assert_eq!(parse("a.rs <- b.rs,"), Err("expected a path but found ``".into()));
+    assert_eq!(parse("[-e2015]"), Err("expected a path but found `[-e2015]`".into()));
+}
+
+#[test]
+fn multiple_arrows() {
+    assert_eq!(
+        parse("a.rs <- b.rs <- c.rs"),
+        Err("the statement `a.rs <- b.rs <- c.rs` contains more than one `<-`".into())
+    );
+}
+
+#[test]
+fn unbalanced_brackets() {
+    assert_eq!(parse("a.rs[-e2015"), Err("expected a closing `]`".into()));
+    assert_eq!(parse("a.rs]"), Err("unexpected unmatched `]`".into()));
+    assert_eq!(parse("a.rs[[]]"), Err("unexpected nested `[`".into()));
+    assert_eq!(parse("a[x].rs"), Err("unexpected bracket in `a[x].rs`".into()));
+}
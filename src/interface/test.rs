@@ -0,0 +1,59 @@
+use crate::{
+    data::{Edition, ExtEdition},
+    operate::Dependency,
+    source::SourcePath,
+};
+use std::path::Path;
+
+fn path(dependency: &Dependency) -> SourcePath<'_> {
+    dependency.path.as_ref()
+}
+
+#[test]
+fn extern_path_without_flags() {
+    let dependency = Dependency::parse_cli_style("dep.rs").unwrap();
+    assert!(path(&dependency) == SourcePath::Regular(Path::new("dep.rs")));
+    assert!(dependency.edition.is_none() && dependency.cfgs.is_empty());
+}
+
+#[test]
+fn extern_path_containing_spaces_and_backslashes() {
+    let dependency = Dependency::parse_cli_style("my dir/dep.rs").unwrap();
+    assert!(path(&dependency) == SourcePath::Regular(Path::new("my dir/dep.rs")));
+
+    let dependency = Dependency::parse_cli_style(r"C:\deps\dep.rs").unwrap();
+    assert!(path(&dependency) == SourcePath::Regular(Path::new(r"C:\deps\dep.rs")));
+
+    let dependency = Dependency::parse_cli_style("dep[1].rs[]").unwrap();
+    assert!(path(&dependency) == SourcePath::Regular(Path::new("dep[1].rs")));
+}
+
+#[test]
+fn extern_path_with_flags() {
+    let dependency =
+        Dependency::parse_cli_style("my dir/dep.rs[-e2015 --cfg 'a b' -- -Zverbose-internals]")
+            .unwrap();
+    assert!(path(&dependency) == SourcePath::Regular(Path::new("my dir/dep.rs")));
+    assert!(matches!(dependency.edition, Some(ExtEdition::Fixed(Edition::Rust2015))));
+    assert_eq!(dependency.cfgs, ["a b"]);
+    assert_eq!(dependency.verbatim, ["-Zverbose-internals"]);
+}
+
+#[test]
+fn extern_path_with_bad_flag() {
+    let error = Dependency::parse_cli_style("dep.rs[--undefined]").err().unwrap();
+    assert!(error.contains("--undefined"), "{error}");
+}
+
+#[test]
+fn extern_path_with_unbalanced_quote() {
+    assert_eq!(
+        Dependency::parse_cli_style("dep.rs[--cfg 'a]").err(),
+        Some("the flags are not properly quoted".into())
+    );
+}
+
+#[test]
+fn extern_path_missing() {
+    assert_eq!(Dependency::parse_cli_style("[-e2015]").err(), Some("expected a path".into()));
+}
@@ -13,6 +13,7 @@
 #![feature(iter_collect_into)]
 #![feature(macro_derive)]
 #![feature(macro_metavar_expr)]
+#![feature(mapped_lock_guards)]
 #![feature(min_generic_const_args)]
 #![feature(slice_split_once)]
 #![feature(str_split_remainder)]
@@ -34,6 +35,7 @@ use diagnostic::{bug, fmt};
 use std::process::ExitCode;
 
 mod build;
+mod compiletest;
 mod context;
 mod data;
 mod diagnostic;
@@ -49,6 +51,9 @@ fn main() -> ExitCode {
 
     match result {
         Ok(()) => ExitCode::SUCCESS,
+        // Like `git bisect run`, we use the exit status 125 to signify that the test was skipped.
+        // This way, it's distinct from failures (1) and from invalid invocations (2).
+        Err(error::Error::Ignored) => ExitCode::from(125),
         Err(error) => {
             error.emit();
             ExitCode::FAILURE
@@ -67,7 +72,11 @@ fn try_main() -> error::Result {
         clap::ColorChoice::Auto => {}
     }
 
-    let cx = context::new!(context::Options { toolchain: args.toolchain, dbg_opts: args.dbg_opts });
+    let cx = context::new!(context::Options {
+        toolchain: args.toolchain,
+        dbg_opts: args.dbg_opts,
+        jobs: args.jobs,
+    });
 
     let path = match args.source {
         Some(Source::String(contents)) => {
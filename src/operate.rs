@@ -4,31 +4,40 @@
 
 use crate::{
     build::{
-        self, CompileOptions, DocOptions, Engine, EngineOptions, ImplyUnstableOptions, Options,
-        VerbatimOptions,
+        self, BuildOptions, CompileOptions, DocOptions, Engine, EngineOptions,
+        ImplyUnstableOptions, Options, VerbatimOptions,
     },
+    compiletest::{self, Stream},
     context::Context,
-    data::{Crate, CrateName, CrateType, DocBackend, Edition, ExtEdition},
-    diagnostic::{error, fmt, warn},
-    directive::{self, Revision},
-    error::Result,
-    source::{SourcePath, SourcePathBuf, Spanned},
-    utility::{OsStrExt as _, default, paint::Painter},
+    data::{Crate, CrateName, CrateType, DocBackend, Edition, ExtEdition, ExternCrate},
+    diagnostic::{Diagnostic, EmittedError, error, fmt, info, warn},
+    directive::{self, Phase, Revision},
+    error::{Error, Result},
+    source::{SourcePath, SourcePathBuf, Span, Spanned, mask},
+    utility::{Conjunction, ListingExt as _, OsStrExt as _, default, paint::Painter},
 };
 use anstyle::AnsiColor;
 use std::{
     ascii::Char,
-    cell::LazyCell,
+    collections::{BTreeSet, HashMap},
+    env::consts::{DLL_PREFIX, DLL_SUFFIX},
+    fs,
     io::{self, Write as _},
     mem,
     path::{Path, PathBuf},
+    process::ExitStatus,
+    sync::{Condvar, Mutex},
+    thread::{self, ThreadId},
 };
 
+#[cfg(test)]
+mod test;
+
 pub(crate) fn perform(
     op: Operation,
     krate: Crate<'_, ExtEdition<'_>>,
-    mut opts: Options<'_>,
-    deps: Vec<SourcePathBuf>,
+    opts: Options<'_>,
+    deps: Vec<Dependency>,
     cx: Context<'_>,
 ) -> Result<()> {
     let mut reads_from_stdin = false;
@@ -36,7 +45,7 @@ pub(crate) fn perform(
     // FIXME: We currently also reject `printf '…' | rrc -: '…' -x-` since we 'desugar'
     //        `-:` to SourcePath::Stdin despite being totally reasonable: We clearly
     //        have to distinct sources, STDIN and the one provided inline.
-    for path in deps.iter().map(SourcePathBuf::as_ref).chain(krate.path) {
+    for path in deps.iter().map(|dep| dep.path.as_ref()).chain(krate.path) {
         if let SourcePath::Stdin = path
             && mem::replace(&mut reads_from_stdin, true)
         {
@@ -50,42 +59,126 @@ pub(crate) fn perform(
         return render_engine_version(engine, &opts, cx);
     }
 
-    compile_deps(deps, &mut opts.b_opts.extern_crates, cx)?;
-
     match op {
         Operation::Compile { mode, run, options: c_opts } => {
-            compile(mode, run, krate, opts, c_opts, cx)
+            compile(mode, run, krate, opts, c_opts, deps, cx)
         }
         Operation::Document { mode, open, options: d_opts } => {
-            document(mode, open, krate, opts, d_opts, cx)
+            document(mode, open, krate, opts, d_opts, deps, cx)
         }
         Operation::QueryEngineVersion(_) => Ok(()),
     }
 }
 
-fn compile_deps(deps: Vec<SourcePathBuf>, registry: &mut Vec<String>, cx: Context<'_>) -> Result {
-    // This is really primitive. In the future we could introduce `-G, --build-graph <GRAPH>`
-    // where the build graph is described via a super concise DSL as originally described in
-    // <https://github.com/fmease/rruxwry/issues/3#issuecomment-2619062193>.
-    //
-    // FIXME: Extend the CLI to support passing arbitrary rrx arguments for individual extern crates.
+/// Compile the given dependencies in order, each of which has to succeed its own dependencies.
+///
+/// If the principal crate is part of the build graph, it only depends on the crates it's declared
+/// to depend on. Otherwise, it depends on all crates no other crate depends on.
+fn compile_deps(
+    deps: Vec<Dependency>,
+    principal: Option<SourcePath<'_>>,
+    dir_opts: Option<&DirectiveOptions>,
+    b_opts: &mut BuildOptions,
+    cx: Context<'_>,
+) -> Result {
+    let principal = principal.and_then(|path| {
+        let path = identify(path)?;
+        deps.iter().position(|dep| identify(dep.path.as_ref()).as_ref() == Some(&path))
+    });
 
-    registry.reserve(deps.len());
-    for path in deps {
-        let krate = Crate {
-            path: Some(path.as_ref()),
-            name: None,
-            typ: Some(CrateType::LIB),
-            edition: None,
+    let direct: Vec<bool> = if let Some(principal) = principal {
+        if deps.iter().any(|dep| dep.dependencies.contains(&principal)) {
+            let error =
+                error(fmt!("the principal crate cannot be a dependency of other crates")).done();
+            return Err(error.into());
+        }
+        if deps[principal].has_flags() {
+            let error = error(fmt!("the principal crate cannot be given flags in the build graph"))
+                .help(fmt!("pass them to the principal crate directly instead"))
+                .done();
+            return Err(error.into());
+        }
+        (0..deps.len()).map(|index| deps[principal].dependencies.contains(&index)).collect()
+    } else {
+        let mut direct = vec![true; deps.len()];
+        for &index in deps.iter().flat_map(|dep| &dep.dependencies) {
+            direct[index] = false;
+        }
+        direct
+    };
+
+    let mut crates: Vec<Option<ExternCrate>> = Vec::with_capacity(deps.len());
+    for (index, dep) in deps.into_iter().enumerate() {
+        if principal == Some(index) {
+            crates.push(None);
+            continue;
+        }
+
+        let path = dep.path.as_ref();
+        let e_opts = EngineOptions::Rustc(default());
+        let name = dep.name.as_ref().map(CrateName::as_ref);
+        let typ = Some(dep.typ.unwrap_or(CrateType::LIB));
+        let opts = Options {
+            b_opts: BuildOptions {
+                cfgs: dep.cfgs,
+                unstable_features: dep.unstable_features,
+                extern_crates: dep
+                    .dependencies
+                    .iter()
+                    .filter_map(|&index| crates[index].clone())
+                    .collect(),
+                out_dir: b_opts.out_dir.clone(),
+                dependency_search_paths: vec![b_opts.search_path().to_owned()],
+                ..default()
+            },
+            v_opts: VerbatimOptions {
+                arguments: dep.verbatim.iter().map(String::as_str).collect(),
+                variables: Vec::new(),
+            },
         };
-        // FIXME: `build_directive_driven` under `-@`! Don't forget to set Role to Auxiliary!
-        //        I guess we want to fwd the active revision (that's what we do for normal
-        //        auxiliaries IIRC)?
-        build_default(&EngineOptions::Rustc(default()), krate, default(), cx)?;
-        // FIXME: unwrap
-        let name = CrateName::parse_source_file_relaxed(path.as_ref()).unwrap();
-        registry.push(name.into_inner());
+
+        // Under `-@`, dependencies are treated just like auxiliaries.
+        let library = if let Some(dir_opts) = dir_opts {
+            let base_path = aux_base_path(path);
+            let mut auxiliaries = Auxiliaries {
+                base_path: &base_path,
+                e_opts: &e_opts,
+                dir_opts,
+                cx,
+                check_only: false,
+                compiled: &default(),
+                stack: Vec::new(),
+            };
+            auxiliaries.compile_dependency(
+                Spanned::sham(path),
+                name,
+                typ,
+                dep.edition,
+                opts,
+                false,
+            )?
+        } else {
+            let krate = Crate { path: Some(path), name, typ, edition: dep.edition };
+            build_default(&e_opts, krate, opts, cx)?;
+            None
+        };
+
+        let krate = library.unwrap_or_else(|| {
+            ExternCrate::new(match name {
+                Some(name) => name.as_str().to_owned(),
+                // FIXME: unwrap
+                None => CrateName::parse_source_file_relaxed(path).unwrap().into_inner(),
+            })
+        });
+        crates.push(Some(krate));
+    }
+
+    if crates.iter().any(Option::is_some) {
+        b_opts.dependency_search_paths.push(b_opts.search_path().to_owned());
     }
+    b_opts.extern_crates.extend(
+        crates.into_iter().zip(direct).filter_map(|(krate, direct)| krate.filter(|_| direct)),
+    );
 
     Ok(())
 }
@@ -94,24 +187,27 @@ fn compile<'a>(
     mode: CompileMode,
     run: Run,
     krate: Crate<'a, ExtEdition<'a>>,
-    opts: Options<'a>,
+    mut opts: Options<'a>,
     c_opts: CompileOptions,
+    deps: Vec<Dependency>,
     cx: Context<'a>,
 ) -> Result {
     let mut e_opts = EngineOptions::Rustc(c_opts);
-    let (krate, opts, run_v_opts) = match mode {
+    let (krate, opts) = match mode {
         CompileMode::Default => {
+            compile_deps(deps, krate.path, None, &mut opts.b_opts, cx)?;
             let typ = krate.typ.or_else(|| matches!(run, Run::No).then_some(CrateType::LIB));
             let krate = Crate { typ, ..krate };
-            let (krate, opts) = build_default(&e_opts, krate, opts, cx)?;
-            (krate, opts, default())
+            build_default(&e_opts, krate, opts, cx)?
         }
+        // Whether to run the binary may depend on the directives, so we leave it to the callee.
         CompileMode::DirectiveDriven(dir_opts) => {
-            build_directive_driven(&mut e_opts, krate, dir_opts, opts, cx)?
+            build_directive_driven(&mut e_opts, krate, dir_opts, run, opts, deps, cx)?;
+            return Ok(());
         }
     };
     match run {
-        Run::Yes => self::run(krate, &opts, &run_v_opts, cx),
+        Run::Yes => self::run(krate, &opts, &default(), cx),
         Run::No => Ok(()),
     }
 }
@@ -122,6 +218,34 @@ fn run(
     run_v_opts: &VerbatimOptions<'_>,
     cx: Context<'_>,
 ) -> Result {
+    let path = executable_path(krate, opts, cx)?;
+
+    execute(&path, run_v_opts, opts, cx)?.exit_ok().map_err(|error| {
+        self::error(fmt!("process for `{}` exited unsuccessfully", path.display()))
+            .note(fmt!("{}", error.into_status()))
+            .done()
+    })?;
+    Ok(())
+}
+
+fn execute(
+    path: &Path,
+    run_v_opts: &VerbatimOptions<'_>,
+    opts: &Options<'_>,
+    cx: Context<'_>,
+) -> Result<ExitStatus> {
+    let library_paths = build::library_paths(opts, cx)?;
+    build::run(path, run_v_opts, &library_paths, cx).map_err(|error| run_error(path, &error))
+}
+
+fn run_error(path: &Path, error: &io::Error) -> Error {
+    self::error(fmt!("failed to run the built binary `{}`", path.display()))
+        .note(fmt!("{error}"))
+        .done()
+        .into()
+}
+
+fn executable_path(krate: Crate<'_>, opts: &Options<'_>, cx: Context<'_>) -> Result<PathBuf> {
     let crate_name = build::query_crate_name(krate, opts, cx).map_err(|error| {
         // FIXME: Actually create a 'parent' error diagnostic with a message akin to
         //        "failed to run the built binary (requested …)" and smh.
@@ -138,44 +262,38 @@ fn run(
         crate_name.as_str()
     };
 
-    let mut path = PathBuf::from_iter([".", path]);
+    let mut path = Path::new(".").join(opts.b_opts.artifact_path(path));
     path.set_extension(std::env::consts::EXE_EXTENSION);
-
-    build::run(&path, run_v_opts, cx)
-        .map_err(|error| {
-            self::error(fmt!("failed to run the built binary `{}`", path.display()))
-                .note(fmt!("{error}"))
-                .done()
-        })?
-        .map_err(|error| {
-            self::error(fmt!("process for `{}` exited unsuccessfully", path.display()))
-                .note(fmt!("{}", error.into_status()))
-                .done()
-        })?;
-    Ok(())
+    Ok(path)
 }
 
 fn document<'a>(
     mode: DocMode,
     open: Open,
     krate: Crate<'a, ExtEdition<'a>>,
-    opts: Options<'a>,
+    mut opts: Options<'a>,
     d_opts: DocOptions<'a>,
+    deps: Vec<Dependency>,
     cx: Context<'a>,
 ) -> Result<()> {
     let (krate, opts) = match mode {
-        DocMode::Default => build_default(&EngineOptions::Rustdoc(d_opts), krate, opts, cx)?,
-        DocMode::CrossCrate => return document_cross_crate(krate, opts, d_opts, open, cx),
-        DocMode::DirectiveDriven(dir_opts) => {
-            let (krate, opts, _) = build_directive_driven(
-                &mut EngineOptions::Rustdoc(d_opts),
-                krate,
-                dir_opts,
-                opts,
-                cx,
-            )?;
-            (krate, opts)
+        DocMode::Default => {
+            compile_deps(deps, krate.path, None, &mut opts.b_opts, cx)?;
+            build_default(&EngineOptions::Rustdoc(d_opts), krate, opts, cx)?
+        }
+        DocMode::CrossCrate => {
+            compile_deps(deps, krate.path, None, &mut opts.b_opts, cx)?;
+            return document_cross_crate(krate, opts, d_opts, open, cx);
         }
+        DocMode::DirectiveDriven(dir_opts) => build_directive_driven(
+            &mut EngineOptions::Rustdoc(d_opts),
+            krate,
+            dir_opts,
+            Run::No,
+            opts,
+            deps,
+            cx,
+        )?,
     };
     match open {
         Open::Yes => self::open(krate, &opts, cx),
@@ -186,7 +304,7 @@ fn document<'a>(
 fn open(krate: Crate<'_>, opts: &Options<'_>, cx: Context<'_>) -> Result<()> {
     let Some(path) = krate.path else { return Ok(()) };
 
-    let mut artifact_path = PathBuf::from("./doc");
+    let mut artifact_path = Path::new(".").join(opts.b_opts.artifact_path("doc"));
 
     if let SourcePath::Regular(path) = path
         && let Some(ext) = path.extension()
@@ -240,7 +358,7 @@ fn document_cross_crate(
 
     let root_crate_name = CrateName::new_unchecked(format!("u_{crate_name}"));
     let root_crate_path = match path {
-        SourcePath::Regular(path) => path,
+        SourcePath::Regular(path) | SourcePath::Virtual { path, .. } => path,
         SourcePath::Stdin => Path::new(""),
     }
     .with_file_name(root_crate_name.as_str())
@@ -261,7 +379,7 @@ fn document_cross_crate(
     }
 
     // FIXME: Don't to_owned, extern_crates should be a Cow
-    opts.b_opts.extern_crates.push(crate_name.as_str().to_owned());
+    opts.b_opts.extern_crates.push(ExternCrate::new(crate_name.as_str().to_owned()));
 
     let krate = Crate {
         path: Some(SourcePath::Regular(&root_crate_path)),
@@ -302,9 +420,11 @@ fn build_directive_driven<'a>(
     e_opts: &mut EngineOptions<'a>,
     krate: Crate<'a, ExtEdition<'a>>,
     mut dir_opts: DirectiveOptions,
-    mut opts: Options<'a>,
+    run: Run,
+    opts: Options<'a>,
+    deps: Vec<Dependency>,
     cx: Context<'a>,
-) -> Result<(Crate<'a>, Options<'a>, VerbatimOptions<'a>)> {
+) -> Result<(Crate<'a>, Options<'a>)> {
     let path = krate.path.ok_or_else(|| {
         error(fmt!(
             "the `PATH` argument was not provided but it's required under `-@`, `--directives`"
@@ -340,10 +460,145 @@ fn build_directive_driven<'a>(
                 .done()
                 .into());
         }
+        (Some(Revision(rev)), None) if dir_opts.all_revisions => {
+            return Err(error(fmt!("two conflicting active revisions were passed"))
+                .note(fmt!("path suffix `{rev}` conflicts with requesting all revisions"))
+                .done()
+                .into());
+        }
         (Some(Revision(rev)), None) => dir_opts.revision = Some(Revision(rev.to_owned())),
         (None, _) => {}
     }
 
+    let krate = Crate { path: Some(path), ..krate };
+    if dir_opts.all_revisions {
+        return build_all_revisions(e_opts, krate, &dir_opts, run, opts, &deps, cx);
+    }
+
+    build_revision(e_opts, krate, &dir_opts, run, opts, deps, cx)
+}
+
+/// Enable each revision declared by the principal crate in turn and summarize the outcomes.
+///
+/// Returns the crate and options of the last revision.
+fn build_all_revisions<'a>(
+    e_opts: &EngineOptions<'a>,
+    krate: Crate<'a, ExtEdition<'a>>,
+    dir_opts: &DirectiveOptions,
+    run: Run,
+    opts: Options<'a>,
+    deps: &[Dependency],
+    cx: Context<'a>,
+) -> Result<(Crate<'a>, Options<'a>)> {
+    // unwrap: set by the caller.
+    let path = krate.path.unwrap();
+    let revisions = directive::revisions(Spanned::sham(path), scope(e_opts), dir_opts.flavor, cx)?;
+    let dir_opts = DirectiveOptions { all_revisions: false, ..dir_opts.clone() };
+
+    if revisions.is_empty() {
+        warn(fmt!("the crate does not declare any revisions")).done();
+        return build_revision(&mut e_opts.clone(), krate, &dir_opts, run, opts, deps.to_vec(), cx);
+    }
+
+    // Each revision gets its own output directory since the artifacts of revisions would
+    // otherwise clobber each other or get picked up by subsequent revisions. It's placed
+    // inside of the output directory if there is one.
+    let stem = CrateName::parse_source_file_relaxed(path)
+        .map_or_else(|()| CrateName::FALLBACK.to_owned(), CrateName::into_inner);
+    let outcomes = cx.jobs().try_map(&revisions, |&revision| {
+        info(fmt!("revision `{revision}`")).done();
+
+        let dir_opts =
+            DirectiveOptions { revision: Some(Revision(revision.to_owned())), ..dir_opts.clone() };
+        let mut opts = opts.clone();
+        let out_dir = opts.b_opts.artifact_path(format!("{stem}.{revision}"));
+        // If the directory already existed, it might contain unrelated files.
+        let created = !out_dir.exists();
+        fs::create_dir_all(&out_dir)?;
+        opts.b_opts.out_dir = Some(out_dir.clone());
+        let e_opts = &mut e_opts.clone();
+        let outcome = match build_revision(e_opts, krate, &dir_opts, run, opts, deps.to_vec(), cx) {
+            Ok(outcome) => Outcome::Passed(outcome),
+            Err(Error::Ignored) => Outcome::Ignored(revision),
+            Err(error) => {
+                error.emit();
+                Outcome::Failed(revision)
+            }
+        };
+
+        // The artifacts of passing tests are of no further interest but we keep them for failing
+        // ones to aid debugging. Note that `-T` is incompatible with `-r` and `-o` which would
+        // otherwise need them.
+        if let Test::Yes(_) = dir_opts.test
+            && created
+            && !matches!(outcome, Outcome::Failed(_))
+        {
+            fs::remove_dir_all(&out_dir)?;
+        }
+
+        Ok::<_, Error>(outcome)
+    })?;
+
+    let mut result = None;
+    let mut failures = Vec::new();
+    let mut ignored = Vec::new();
+    for outcome in outcomes {
+        match outcome {
+            Outcome::Passed(outcome) => result = Some(outcome),
+            Outcome::Failed(revision) => failures.push(revision),
+            Outcome::Ignored(revision) => ignored.push(revision),
+        }
+    }
+
+    let list = |revisions: &[&str]| {
+        revisions.iter().map(|revision| format!("`{revision}`")).list(Conjunction::And)
+    };
+    let note_ignored = |diagnostic: Diagnostic| {
+        if ignored.is_empty() {
+            diagnostic
+        } else {
+            diagnostic.note(fmt!("ignored revisions: {}", list(&ignored)))
+        }
+    };
+    match result {
+        Some(result) if failures.is_empty() => {
+            let passed = revisions.len() - ignored.len();
+            let diagnostic = if ignored.is_empty() {
+                info(fmt!("all {passed} revisions passed"))
+            } else {
+                info(fmt!("{passed} of {} revisions passed", revisions.len()))
+            };
+            note_ignored(diagnostic).done();
+            Ok(result)
+        }
+        None if failures.is_empty() => Err(Error::Ignored),
+        _ => Err(note_ignored(
+            error(fmt!("{} of {} revisions failed", failures.len(), revisions.len()))
+                .note(fmt!("failing revisions: {}", list(&failures))),
+        )
+        .done()
+        .into()),
+    }
+}
+
+enum Outcome<'r, T> {
+    Passed(T),
+    Failed(&'r str),
+    Ignored(&'r str),
+}
+
+fn build_revision<'a>(
+    e_opts: &mut EngineOptions<'a>,
+    krate: Crate<'a, ExtEdition<'a>>,
+    dir_opts: &DirectiveOptions,
+    run: Run,
+    mut opts: Options<'a>,
+    deps: Vec<Dependency>,
+    cx: Context<'a>,
+) -> Result<(Crate<'a>, Options<'a>)> {
+    // unwrap: set by the caller.
+    let path = krate.path.unwrap();
+
     let directives = directive::gather(
         Spanned::sham(path),
         scope(e_opts),
@@ -353,41 +608,151 @@ fn build_directive_driven<'a>(
         cx,
     )?;
 
-    let aux_base_path = LazyCell::new(|| {
-        match path {
-            // FIXME: unwrap
-            SourcePath::Regular(path) => path.parent().unwrap(),
-            SourcePath::Stdin => Path::new(""),
+    // The conditions concern the target the crate actually gets compiled for.
+    let arguments = opts.v_opts.arguments.iter().chain(&directives.v_opts.arguments);
+    let target = build::explicit_target(arguments.copied());
+    if let Some(ignored) = compiletest::condition::evaluate(&directives.conditions, target, cx)? {
+        match dir_opts.test {
+            Test::Yes(_) => {
+                info(fmt!("ignoring the test"))
+                    .highlight(ignored.span, cx)
+                    .note(fmt!("{}", ignored.note))
+                    .done();
+                return Err(Error::Ignored);
+            }
+            // Outside of test mode, the user presumably wants to build the crate regardless.
+            Test::No => {
+                warn(fmt!("the test would get ignored under `-T`"))
+                    .highlight(ignored.span, cx)
+                    .note(fmt!("{}", ignored.note))
+                    .done();
+            }
         }
-        .join("auxiliary")
-    });
-    let mut extern_crates = Vec::new();
+    }
 
-    directives.auxes.iter().try_for_each(|aux| {
-        compile_auxiliary(
-            aux,
-            &aux_base_path,
-            e_opts,
-            dir_opts.clone(),
-            // FIXME: This awful!
-            opts.clone(),
-            directives.build_aux_docs,
+    compile_deps(deps, Some(path), Some(dir_opts), &mut opts.b_opts, cx)?;
+
+    // Like compiletest, let the engine normalize the gutter of diagnostics if possible.
+    // Otherwise, the checker has to emulate it.
+    let ui_testing = build::allows_unstable_options(e_opts.engine(), &opts, cx);
+    let checker = match dir_opts.test {
+        Test::Yes(bless) => Some(compiletest::Checker::new(
+            path,
+            dir_opts.revision.as_ref().map(|Revision(rev)| rev.as_str()),
+            bless,
+            &directives,
+            scope(e_opts),
+            opts.b_opts.out_dir.as_deref(),
+            ui_testing,
             cx,
-            &mut extern_crates,
-        )
-    })?;
+        )?),
+        Test::No => None,
+    };
+
+    // Pass/fail modes only get honored if they're not overwritten on the command line.
+    // This has to happen before the auxiliaries get compiled since they get checked only, too.
+    let run = match e_opts {
+        EngineOptions::Rustc(c_opts) if !c_opts.check_only && matches!(run, Run::No) => {
+            match directives.mode.map(|mode| mode.bare.phase) {
+                Some(Phase::Check) => {
+                    c_opts.check_only = true;
+                    Run::No
+                }
+                Some(Phase::Run) => Run::Yes,
+                Some(Phase::Build) | None => Run::No,
+            }
+        }
+        _ => run,
+    };
+
+    let aux_base_path = {
+        let path = aux_base_path(path);
+
+        // See also `compiletest::Checker::absolute_path`.
+        match dir_opts.test {
+            Test::Yes(_) => std::path::absolute(path)?,
+            Test::No => path,
+        }
+    };
+    let mut auxiliaries = Auxiliaries {
+        base_path: &aux_base_path,
+        e_opts,
+        dir_opts,
+        cx,
+        check_only: matches!(e_opts, EngineOptions::Rustc(CompileOptions { check_only: true, .. })),
+        compiled: &default(),
+        stack: Vec::new(),
+    };
+    let mut extern_crates = Vec::new();
+
+    auxiliaries.compile_all(
+        &directives.auxes,
+        &opts,
+        directives.build_aux_docs,
+        &mut extern_crates,
+    )?;
+
+    let source = if directives.inline_crates.is_empty() {
+        path
+    } else {
+        let file = cx.map().read(Spanned::sham(path), cx)?;
+        let base_path = match path {
+            SourcePath::Regular(path) | SourcePath::Virtual { path, .. } => path,
+            SourcePath::Stdin => Path::new(""),
+        };
+
+        // Inline crates get compiled from virtual files that are derived from the principal
+        // file by masking everything outside of the respective crate. This way, the locations
+        // in diagnostics remain meaningful.
+        for inline_crate in &directives.inline_crates {
+            let body = inline_crate.body.local(file).range();
+            let contents = mask(file.contents, |index| !body.contains(&index));
+            let name = inline_crate.name;
+            let file = cx.map().add(
+                SourcePathBuf::Virtual { path: base_path.to_owned(), name: name.bare.to_owned() },
+                contents,
+            )?;
+
+            let library = auxiliaries.compile_dependency(
+                Spanned::new(name.span, file.path),
+                None,
+                None,
+                None,
+                opts.clone(),
+                directives.build_aux_docs,
+            )?;
+
+            if !inline_crate.raw {
+                // FIXME: Don't to_owned, extern_crates should be a Cow
+                extern_crates
+                    .push(library.unwrap_or_else(|| ExternCrate::new(name.bare.to_owned())));
+            }
+        }
 
-    if let Some(rev) = dir_opts.revision {
-        opts.b_opts.cfgs.push(rev.into_cfg());
+        let bodies: Vec<_> =
+            directives.inline_crates.iter().map(|krate| krate.body.local(file).range()).collect();
+        let contents = mask(file.contents, |index| bodies.iter().any(|body| body.contains(&index)));
+        let name = match krate.name {
+            Some(name) => name.as_str().to_owned(),
+            None => CrateName::parse_source_file_relaxed(path)
+                .map_or_else(|()| CrateName::FALLBACK.to_owned(), CrateName::into_inner),
+        };
+        cx.map().add(SourcePathBuf::Virtual { path: base_path.to_owned(), name }, contents)?.path
+    };
+
+    if let Some(Revision(rev)) = &dir_opts.revision {
+        opts.b_opts.cfgs.push(Revision(rev).into_cfg());
+    }
+    if !directives.no_auto_check_cfg {
+        opts.b_opts.check_cfgs.push(check_cfg(&directives.revisions));
     }
 
     opts.b_opts.extern_crates.append(&mut extern_crates);
+    opts.b_opts.dependency_search_paths =
+        dependency_search_paths(&opts.b_opts, auxiliaries.check_only);
 
-    // Note that if `prefer_dylib` was `Yes` compiletest would now add `-Cprefer-dynamic`
-    // if this was `Engine::Rustc`. We might need to do that to at some point, tho I'm
-    // honestly not sure about all the consequences. Also note that we currently use a
-    // crate type of lib instead of dylib for auxiliaries by default.
-    _ = directives.prefer_dylib;
+    // Like compiletest, link the principal crate dynamically, too.
+    opts.b_opts.prefer_dynamic = directives.prefer_dylib.enabled();
 
     let edition = match krate.edition {
         // If the resolution of the CLI edition fails, we *don't*
@@ -399,7 +764,15 @@ fn build_directive_driven<'a>(
         None => directives.edition.map(|edition| Edition::Raw(edition.bare)),
     };
 
-    let krate = Crate { path: Some(path), edition, ..krate };
+    let krate = match source {
+        SourcePath::Virtual { name, .. } => Crate {
+            path: Some(source),
+            name: Some(CrateName::new_unchecked(name)),
+            edition,
+            ..krate
+        },
+        _ => Crate { path: Some(path), edition, ..krate },
+    };
 
     opts.v_opts.extend(directives.v_opts);
     match e_opts {
@@ -407,101 +780,479 @@ fn build_directive_driven<'a>(
         EngineOptions::Rustdoc(d_opts) => d_opts.v_opts.extend(directives.v_d_opts),
     }
 
-    build::perform(e_opts, krate, &opts, ImplyUnstableOptions::No, cx)?;
-    Ok((krate, opts, directives.run_v_opts))
+    // Like compiletest, enable the unstable JSON backend of rustdoc without further ado.
+    let imply_u_opts = match e_opts {
+        EngineOptions::Rustdoc(DocOptions { backend: DocBackend::Json, .. }) => {
+            ImplyUnstableOptions::Yes
+        }
+        _ => ImplyUnstableOptions::No,
+    };
+
+    if let Some(mut checker) = checker {
+        let tested = match source {
+            SourcePath::Virtual { .. } => krate,
+            _ => Crate { path: Some(SourcePath::Regular(checker.absolute_path())), ..krate },
+        };
+        opts.b_opts.json_diagnostics = true;
+        opts.b_opts.ui_testing = ui_testing;
+        // Like compiletest, don't litter the test directory with ICE reports.
+        opts.b_opts.no_ice_reports = true;
+
+        let output = build::perform_capturing_output(e_opts, tested, &opts, imply_u_opts, cx)?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = match source {
+            // The engine refers to source code read from STDIN as `<anon>`.
+            SourcePath::Virtual { .. } => {
+                stderr.replace("<anon>", &checker.absolute_path().display().to_string()).into()
+            }
+            _ => stderr,
+        };
+        let stderr = checker.check_diagnostics(&stderr, cx)?;
+        checker.compare(Stream::Stderr, &stderr)?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        checker.compare(Stream::Stdout, &stdout)?;
+        checker.check_output(&stdout, &stderr, cx);
+
+        // The fixed file doesn't have a valid crate name if a revision is active.
+        let name = CrateName::parse_source_file_relaxed(path).ok();
+        let name = krate.name.or_else(|| Some(CrateName::new_unchecked(name.as_ref()?.as_str())));
+
+        if checker.check_compilation(output.status, &stderr, cx) {
+            if let Run::Yes = run {
+                let path = executable_path(krate, &opts, cx)?;
+                let library_paths = build::library_paths(&opts, cx)?;
+                let output =
+                    build::run_capturing_output(&path, &directives.run_v_opts, &library_paths, cx)
+                        .map_err(|error| run_error(&path, &error))?;
+                checker.check_execution(&output, cx)?;
+            }
+
+            // See also `open`.
+            let doc = &opts.b_opts.artifact_path("doc");
+            checker.check_html(&directives.html_checks, doc, cx)?;
+            let json = name.as_ref().map_or(CrateName::FALLBACK, CrateName::as_str);
+            let json = doc.join(format!("{json}.json"));
+            checker.check_json(&directives.json_checks, &json, cx);
+        }
+
+        if let Some(fixed) = checker.check_fix(cx)? {
+            let fixed = Crate { path: Some(SourcePath::Regular(&fixed)), name, ..krate };
+            let output = build::perform_capturing_output(e_opts, fixed, &opts, imply_u_opts, cx)?;
+            checker.check_fixed_compilation(&output)?;
+        }
+
+        checker.finish()?;
+    } else {
+        build::perform(e_opts, krate, &opts, imply_u_opts, cx)?;
+
+        if let Run::Yes = run {
+            self::run(krate, &opts, &directives.run_v_opts, cx)?;
+        }
+    }
+
+    Ok((krate, opts))
 }
 
-// FIXME: Support nested auxiliaries!
-// FIXME: Detect and reject circular/cyclic auxiliaries.
-fn compile_auxiliary<'a>(
-    &directive::Auxiliary { ref prefix, path, typ }: &directive::Auxiliary<'a>,
-    base_path: &Path,
-    e_opts: &EngineOptions<'_>,
-    dir_opts: DirectiveOptions,
-    // FIXME: Do we actually want to pass along *all* of these opts?
-    //        Arguably some of them belong to the root crate only (e.g. crate name).
-    //        On top of that, the status quo is inconsistent because
-    //        we don't honor the edition (which is also just an "option").
-    //        Some options should however be inherited: toolchain, cfgs, rev,
-    //        debug. Should subset vs. all be a CLI option?
-    mut opts: Options<'a>,
-    doc: bool,
+/// The directory relative to which the paths of auxiliaries are resolved.
+fn aux_base_path(path: SourcePath<'_>) -> PathBuf {
+    match path {
+        // FIXME: unwrap
+        SourcePath::Regular(path) | SourcePath::Virtual { path, .. } => path.parent().unwrap(),
+        SourcePath::Stdin => Path::new(""),
+    }
+    .join("auxiliary")
+}
+
+/// The compilation of the graph of auxiliaries.
+#[derive(Clone)]
+struct Auxiliaries<'a, 'e> {
+    base_path: &'e Path,
+    e_opts: &'e EngineOptions<'e>,
+    dir_opts: &'e DirectiveOptions,
     cx: Context<'a>,
-    parent_extern_crates: &mut Vec<String>,
-) -> Result<()> {
-    let path = path.map(|path| base_path.join(path));
+    /// Whether to only check the auxiliaries, i.e., to only emit metadata.
+    check_only: bool,
+    /// The auxiliaries that have already been compiled (or are being compiled) by any job.
+    compiled: &'e Compiled,
+    /// The auxiliaries that are currently being compiled and the spans of the directives that
+    /// requested them.
+    stack: Vec<(PathBuf, Span)>,
+}
 
-    let directives = directive::gather(
-        path.as_deref().map(SourcePath::Regular),
-        scope(e_opts),
-        directive::Role::Auxiliary,
-        dir_opts.flavor,
-        dir_opts.revision.as_ref().map(|Revision(rev)| Revision(rev.as_str())),
-        cx,
-    )?;
+impl<'a> Auxiliaries<'a, '_> {
+    /// Compile the given auxiliaries, concurrently if possible.
+    fn compile_all(
+        &self,
+        auxes: &[directive::Auxiliary<'_>],
+        opts: &Options<'a>,
+        doc: bool,
+        parent_extern_crates: &mut Vec<ExternCrate>,
+    ) -> Result<()> {
+        let extern_crates = self.cx.jobs().try_map(auxes, |aux| {
+            let mut extern_crates = Vec::new();
+            self.clone()
+                .compile(aux, opts.clone(), doc, &mut extern_crates)
+                .map_err(Error::emit)
+                .map(|()| extern_crates)
+        })?;
+        parent_extern_crates.extend(extern_crates.into_iter().flatten());
+        Ok(())
+    }
+
+    /// Compile the given auxiliary unless it was compiled before.
+    fn compile(
+        &mut self,
+        &directive::Auxiliary { ref name, modifiers, path, typ }: &directive::Auxiliary<'_>,
+        opts: Options<'a>,
+        doc: bool,
+        parent_extern_crates: &mut Vec<ExternCrate>,
+    ) -> Result<()> {
+        let path = path.map(|path| self.base_path.join(path));
+
+        // This is only used for identifying auxiliaries, so we don't need to canonicalize.
+        let key = std::path::absolute(&path.bare)?;
+
+        let cyclic = |spans: &mut dyn Iterator<Item = Span>| {
+            let it = error(fmt!("cyclic auxiliaries"));
+            let it = spans.fold(it, |it, span| it.highlight(span, self.cx));
+            Err(it.note(fmt!("auxiliaries cannot depend on themselves")).done().into())
+        };
+
+        if let Some(index) = self.stack.iter().position(|(ancestor, _)| *ancestor == key) {
+            let spans = self.stack[index + 1..].iter().map(|&(_, span)| span).chain([path.span]);
+            return cyclic(&mut spans.into_iter());
+        }
+        let ancestors: Vec<_> = self.stack.iter().map(|(ancestor, _)| ancestor.clone()).collect();
+        let library = match self.compiled.claim(&(key.clone(), self.check_only), &ancestors) {
+            Claim::Compiled(library) => library,
+            Claim::Failed(error) => return Err(error.into()),
+            // The other participants of the cycle are being compiled by other jobs.
+            Claim::Cyclic => return cyclic(&mut [path.span].into_iter()),
+            Claim::Claimed(claimed) => {
+                self.stack.push((key, path.span));
+                let result = self.compile_dependency(
+                    path.as_deref().map(SourcePath::Regular),
+                    None,
+                    typ,
+                    None,
+                    opts,
+                    doc,
+                );
+                self.stack.pop();
+                let result = result.map_err(Error::emit);
+                claimed.finish(result.clone());
+                result?
+            }
+        };
 
-    if let Some(revision) = dir_opts.revision {
-        opts.b_opts.cfgs.push(revision.into_cfg());
+        // Even if the auxiliary isn't referred to under a different name, we pass it explicitly
+        // since the engine might otherwise pick up stale artifacts of the same crate.
+        let krate = match (name, library) {
+            (Some(name), library) => Some(ExternCrate {
+                name: name.clone().into_owned(),
+                path: library.and_then(|library| library.path),
+                modifiers,
+            }),
+            (None, library) => library.map(|library| ExternCrate { modifiers, ..library }),
+        };
+        parent_extern_crates.extend(krate);
+
+        Ok(())
     }
 
-    let directive::InstantiatedDirectives {
-        edition,
-        v_opts,
-        prefer_dylib,
-        // FIXME
-        build_aux_docs: _,
-        // FIXME
-        auxes: _,
-        v_d_opts: _,
-        run_v_opts: _,
-    } = directives;
+    /// Compile an auxiliary or an inline crate together with its own auxiliaries.
+    ///
+    /// Returns the extern crate referring to the library artifact if there is one.
+    fn compile_dependency(
+        &mut self,
+        path: Spanned<SourcePath<'_>>,
+        name: Option<CrateName<&str>>,
+        typ: Option<CrateType>,
+        // Overrides the edition specified via directives.
+        edition: Option<ExtEdition<'_>>,
+        // FIXME: Do we actually want to pass along *all* of these opts?
+        //        Arguably some of them belong to the root crate only (e.g. crate name).
+        //        On top of that, the status quo is inconsistent because
+        //        we don't honor the edition (which is also just an "option").
+        //        Some options should however be inherited: toolchain, cfgs, rev,
+        //        debug. Should subset vs. all be a CLI option?
+        mut opts: Options<'a>,
+        doc: bool,
+    ) -> Result<Option<ExternCrate>> {
+        let (e_opts, cx) = (self.e_opts, self.cx);
+
+        let directives = directive::gather(
+            path,
+            scope(e_opts),
+            directive::Role::Auxiliary,
+            self.dir_opts.flavor,
+            self.dir_opts.revision.as_ref().map(|Revision(rev)| Revision(rev.as_str())),
+            cx,
+        )?;
 
-    opts.v_opts.extend(v_opts);
+        let directive::InstantiatedDirectives {
+            revisions,
+            edition: directive_edition,
+            v_opts,
+            prefer_dylib,
+            no_auto_check_cfg,
+            build_aux_docs,
+            auxes,
+            v_d_opts: _,
+            run_v_opts: _,
+            // Only the principal crate gets tested.
+            normalizations: _,
+            mode: _,
+            check_run_results: _,
+            failure_status: _,
+            should_ice: _,
+            patterns: _,
+            rustfix: _,
+            html_checks: _,
+            json_checks: _,
+            // FIXME: Support nested inline crates.
+            inline_crates: _,
+            // Only the principal crate can get ignored.
+            conditions: _,
+        } = directives;
+
+        // Proc macros get executed, so they and their dependencies have to be built fully.
+        let check_only = self.check_only && typ != Some(CrateType::PROC_MACRO);
+        let parent_check_only = mem::replace(&mut self.check_only, check_only);
+
+        // Like in compiletest, the paths of nested auxiliaries are relative to
+        // the auxiliary directory of the principal crate.
+        let mut extern_crates = Vec::new();
+        let result = self.compile_all(&auxes, &opts, build_aux_docs, &mut extern_crates);
+        self.check_only = parent_check_only;
+        result?;
+        opts.b_opts.extern_crates.append(&mut extern_crates);
+
+        if let Some(Revision(rev)) = &self.dir_opts.revision {
+            opts.b_opts.cfgs.push(Revision(rev).into_cfg());
+        }
+        if !no_auto_check_cfg {
+            opts.b_opts.check_cfgs.push(check_cfg(&revisions));
+        }
 
-    // Note that if `prefer_dylib` was `Yes` compiletest would now add `-Cprefer-dynamic`
-    // if this was `Engine::Rustc`. Please see note in `build_directive_driven`.
+        opts.v_opts.extend(v_opts);
 
-    let krate = Crate {
-        path: Some(SourcePath::Regular(&path.bare)),
-        name: None,
-        typ: prefer_dylib.apply(typ),
-        edition: edition.map(|edition| Edition::Raw(edition.bare)),
-    };
+        // This isn't inherited from the parent crate.
+        opts.b_opts.prefer_dynamic = prefer_dylib.enabled();
 
-    // FIXME: Also register library search paths properly
-    if let Some(prefix) = prefix {
-        parent_extern_crates.push(prefix.to_string());
-    }
+        opts.b_opts.dependency_search_paths = dependency_search_paths(&opts.b_opts, check_only);
+        if check_only {
+            opts.b_opts.out_dir = Some(opts.b_opts.search_path().join(CHECKED_DIR));
+        }
 
-    populate_extern_prelude(krate.typ, &mut opts.b_opts.extern_crates);
+        let krate = Crate {
+            path: Some(path.bare),
+            // Virtual files are fed to the engine, so it can't infer the crate name from the path.
+            name: name.or(match path.bare {
+                SourcePath::Virtual { name, .. } => Some(CrateName::new_unchecked(name)),
+                _ => None,
+            }),
+            typ: prefer_dylib.apply(
+                typ,
+                build::explicit_target(opts.v_opts.arguments.iter().copied()),
+                cx,
+            )?,
+            edition: match edition {
+                Some(edition) => edition.resolve(Engine::Rustc, cx),
+                None => directive_edition.map(|edition| Edition::Raw(edition.bare)),
+            },
+        };
 
-    build::perform(
-        match e_opts {
-            // FIXME: Does this actually work as expected wrt. to check-only?
-            //        Does this lead to all crates in the dependency graph to
-            //        get checked-only and everything working out (linking correctly etc)?
-            //        I suspect is doesn't because we need to s%/rlib/rmeta/
-            EngineOptions::Rustc(..) => e_opts,
-            EngineOptions::Rustdoc(_) => const { &EngineOptions::Rustc(CompileOptions::default()) },
-        },
-        krate,
-        &opts,
-        ImplyUnstableOptions::No,
-        cx,
-    )?;
+        populate_extern_prelude(krate.typ, &mut opts.b_opts.extern_crates);
 
-    if doc && let EngineOptions::Rustdoc(d_opts) = e_opts {
+        // Only the principal crate gets dumped or built shallowly.
         build::perform(
-            // FIXME: Do we actually want to forward these doc opts from the parent crate??
-            &EngineOptions::Rustdoc(d_opts.clone()),
+            &EngineOptions::Rustc(CompileOptions { check_only, ..default() }),
             krate,
             &opts,
             ImplyUnstableOptions::No,
             cx,
         )?;
+
+        if doc && let EngineOptions::Rustdoc(d_opts) = e_opts {
+            build::perform(
+                // FIXME: Do we actually want to forward these doc opts from the parent crate??
+                &EngineOptions::Rustdoc(d_opts.clone()),
+                krate,
+                &opts,
+                ImplyUnstableOptions::No,
+                cx,
+            )?;
+        }
+
+        let name =
+            build::query_crate_name(krate, &opts, cx).map_err(build::QueryCrateNameError::emit)?;
+        Ok(library_path(name.as_str(), krate.typ, check_only)
+            .map(|path| opts.b_opts.artifact_path(path))
+            .filter(|path| path.exists())
+            .map(|path| ExternCrate {
+                name: name.into_inner().into_owned(),
+                path: Some(path),
+                modifiers: default(),
+            }))
     }
+}
 
-    Ok(())
+/// The auxiliaries that have been compiled or are being compiled by any job.
+#[derive(Default)]
+struct Compiled {
+    state: Mutex<CompiledState>,
+    finished: Condvar,
+}
+
+/// An auxiliary is identified by its path and by whether it's only checked since it might be
+/// checked for one dependent but built fully for another (namely a proc macro).
+type CompiledKey = (PathBuf, bool);
+
+#[derive(Default)]
+struct CompiledState {
+    /// The libraries of the compiled auxiliaries (if successful).
+    slots: HashMap<CompiledKey, Slot>,
+    /// The auxiliary each blocked job is waiting for together with the auxiliaries it's part of.
+    waiting: HashMap<ThreadId, (Vec<PathBuf>, PathBuf)>,
+}
+
+enum Slot {
+    Compiling,
+    Finished(Result<Option<ExternCrate>, EmittedError>),
+}
+
+enum Claim<'c> {
+    /// The auxiliary has to be compiled by the current job.
+    Claimed(Claimed<'c>),
+    Compiled(Option<ExternCrate>),
+    Failed(EmittedError),
+    /// The auxiliary is being compiled by another job which (transitively) waits for
+    /// one of the given ancestors.
+    Cyclic,
+}
+
+impl Compiled {
+    /// Claim the auxiliary for compilation unless it's been compiled already.
+    ///
+    /// If another job is currently compiling it, wait for that job to finish.
+    fn claim(&self, key: &CompiledKey, ancestors: &[PathBuf]) -> Claim<'_> {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            match state.slots.get(key) {
+                None => {
+                    state.slots.insert(key.clone(), Slot::Compiling);
+                    return Claim::Claimed(Claimed { compiled: self, key: Some(key.clone()) });
+                }
+                Some(Slot::Finished(Ok(artifact))) => return Claim::Compiled(artifact.clone()),
+                Some(&Slot::Finished(Err(error))) => return Claim::Failed(error),
+                Some(Slot::Compiling) => {
+                    // Waiting for a job that's waiting for us would result in a deadlock.
+                    if state.depends_on(&key.0, ancestors) {
+                        return Claim::Cyclic;
+                    }
+                    let current = thread::current().id();
+                    state.waiting.insert(current, (ancestors.to_vec(), key.0.clone()));
+                    state = self.finished.wait(state).unwrap();
+                    state.waiting.remove(&current);
+                }
+            }
+        }
+    }
+
+    fn finish(&self, key: CompiledKey, result: Result<Option<ExternCrate>, EmittedError>) {
+        self.state.lock().unwrap().slots.insert(key, Slot::Finished(result));
+        self.finished.notify_all();
+    }
+}
+
+/// An auxiliary claimed for compilation by the current job.
+///
+/// If this gets dropped before the outcome is recorded (e.g., because the job panicked),
+/// the compilation is considered to have failed, so the jobs waiting for it don't block forever.
+struct Claimed<'c> {
+    compiled: &'c Compiled,
+    key: Option<CompiledKey>,
+}
+
+impl Claimed<'_> {
+    fn finish(mut self, result: Result<Option<ExternCrate>, EmittedError>) {
+        // unwrap: only taken here and on drop.
+        self.compiled.finish(self.key.take().unwrap(), result);
+    }
+}
+
+impl Drop for Claimed<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            let error = error(fmt!("failed to compile the auxiliary `{}`", key.0.display()))
+                .note(fmt!("its compilation was aborted"))
+                .done();
+            self.compiled.finish(key, Err(error));
+        }
+    }
+}
+
+impl CompiledState {
+    /// Whether the compilation of the given auxiliary waits for any of the given auxiliaries.
+    fn depends_on(&self, path: &Path, others: &[PathBuf]) -> bool {
+        let mut worklist = vec![path];
+        let mut visited = Vec::new();
+
+        while let Some(path) = worklist.pop() {
+            if others.iter().any(|other| other == path) {
+                return true;
+            }
+            if visited.contains(&path) {
+                continue;
+            }
+            visited.push(path);
+
+            // Any job that's part of the compilation of the auxiliary is a transitive dependency.
+            worklist.extend(
+                self.waiting
+                    .values()
+                    .filter(|(ancestors, _)| ancestors.iter().any(|ancestor| ancestor == path))
+                    .map(|(_, awaited)| awaited.as_path()),
+            );
+        }
+
+        false
+    }
+}
+
+/// The subdirectory of the output directory that checked auxiliaries get placed in.
+const CHECKED_DIR: &str = "check";
+
+/// The directories to search for the dependencies of the dependencies of a crate in.
+///
+/// Checked auxiliaries are kept apart from fully built ones, so that the latter never come
+/// across the metadata of the former (or vice versa) which the engine would consider to be
+/// multiple candidates for the same crate.
+fn dependency_search_paths(b_opts: &BuildOptions, check_only: bool) -> Vec<PathBuf> {
+    let path = b_opts.search_path();
+    // Checked crates may depend on fully built ones, namely proc macros and their dependencies.
+    if check_only { vec![path.join(CHECKED_DIR), path.to_owned()] } else { vec![path.to_owned()] }
+}
+
+/// The path to the library artifact of the given crate if it's of a library type.
+///
+/// If the crate type is absent, we assume it's `lib` (since the source file might declare
+/// a different one, the caller should check that the artifact actually exists).
+fn library_path(name: &str, typ: Option<CrateType>, check_only: bool) -> Option<PathBuf> {
+    match typ {
+        None | Some(CrateType::LIB | CrateType::DYLIB) if check_only => {
+            Some(format!("lib{name}.rmeta"))
+        }
+        None | Some(CrateType::LIB) => Some(format!("lib{name}.rlib")),
+        Some(CrateType::DYLIB | CrateType::PROC_MACRO) => {
+            Some(format!("{DLL_PREFIX}{name}{DLL_SUFFIX}"))
+        }
+        _ => None,
+    }
+    .map(PathBuf::from)
 }
 
 fn scope(e_opts: &EngineOptions<'_>) -> directive::Scope {
@@ -517,24 +1268,34 @@ fn scope(e_opts: &EngineOptions<'_>) -> directive::Scope {
 }
 
 impl directive::PreferDylib {
-    fn apply(self, typ: Option<CrateType>) -> Option<CrateType> {
-        match (self, typ) {
+    fn apply(
+        self,
+        typ: Option<CrateType>,
+        target: Option<&str>,
+        cx: Context<'_>,
+    ) -> Result<Option<CrateType>> {
+        Ok(match (self, typ) {
             (_, typ @ Some(_)) => typ,
-            // Compiletest defaults to `dylib` unless the target architecture
-            // doesn't support dynamic linking in which case it also uses `lib`.
-            // Since we don't have any "infrastructure" in place for checking
-            // target architectures, let's fall back to the "safer" option.
+            // Compiletest defaults to `dylib` unless the target
+            // doesn't support dynamic linking in which case it uses `lib`.
+            (Self::Yes, None) if build::target_info(target, cx)?.supports_dynamic_linking() => {
+                Some(CrateType::DYLIB)
+            }
             (Self::Yes, None) => Some(CrateType::LIB),
             (Self::No, None) => None,
-        }
+        })
+    }
+
+    fn enabled(self) -> bool {
+        matches!(self, Self::Yes)
     }
 }
 
-fn populate_extern_prelude(typ: Option<CrateType>, extern_crates: &mut Vec<String>) {
+fn populate_extern_prelude(typ: Option<CrateType>, extern_crates: &mut Vec<ExternCrate>) {
     match typ {
         // For convenience and just like Cargo we add `proc_macro` to the external prelude.
         // FIXME: Don't to_string, use Cow
-        Some(CrateType::PROC_MACRO) => extern_crates.push("proc_macro".to_string()),
+        Some(CrateType::PROC_MACRO) => extern_crates.push(ExternCrate::new("proc_macro".into())),
         _ => {}
     }
 }
@@ -573,6 +1334,14 @@ impl<S: AsRef<str>> Revision<S> {
     }
 }
 
+/// The cfgs to expect in a crate with the given revisions, namely the revisions themselves.
+///
+/// Like compiletest, we also expect `test` and `FALSE` since they're commonly used in tests.
+fn check_cfg(revisions: &BTreeSet<&str>) -> String {
+    let revisions = revisions.iter().map(|&revision| format!(",{}", Revision(revision).into_cfg()));
+    format!("cfg(test,FALSE{})", revisions.collect::<String>())
+}
+
 pub(crate) enum Operation {
     Compile { mode: CompileMode, run: Run, options: CompileOptions },
     Document { mode: DocMode, open: Open, options: DocOptions<'static> },
@@ -594,13 +1363,13 @@ pub(crate) enum DocMode {
 pub(crate) struct DirectiveOptions {
     pub(crate) flavor: directive::Flavor,
     pub(crate) revision: Option<Revision<String>>,
-    #[expect(dead_code)] // FIXME
+    /// Whether to enable each declared revision in turn.
+    pub(crate) all_revisions: bool,
     pub(crate) test: Test,
 }
 
 #[derive(Clone, Copy)]
 pub(crate) enum Test {
-    #[expect(dead_code)] // FIXME
     Yes(Bless),
     No,
 }
@@ -622,3 +1391,48 @@ pub(crate) enum Open {
     Yes,
     No,
 }
+
+/// Normalize the given path for the purpose of identifying crates.
+fn identify(path: SourcePath<'_>) -> Option<PathBuf> {
+    match path {
+        SourcePath::Regular(path) => {
+            Some(fs::canonicalize(path).or_else(|_| std::path::absolute(path)).ok()?)
+        }
+        SourcePath::Virtual { .. } | SourcePath::Stdin => None,
+    }
+}
+
+/// An extern crate passed on the command line together with the options specific to it.
+#[derive(Clone)]
+pub(crate) struct Dependency {
+    pub(crate) path: SourcePathBuf,
+    pub(crate) name: Option<CrateName<String>>,
+    pub(crate) typ: Option<CrateType>,
+    pub(crate) edition: Option<ExtEdition<'static>>,
+    pub(crate) cfgs: Vec<String>,
+    pub(crate) unstable_features: Vec<String>,
+    pub(crate) verbatim: Vec<String>,
+    /// The indices of the dependencies of this crate which all precede it (cf. `-G`).
+    pub(crate) dependencies: Vec<usize>,
+}
+
+impl Dependency {
+    fn has_flags(&self) -> bool {
+        let Self {
+            path: _,
+            name,
+            typ,
+            edition,
+            cfgs,
+            unstable_features,
+            verbatim,
+            dependencies: _,
+        } = self;
+        name.is_some()
+            || typ.is_some()
+            || edition.is_some()
+            || !cfgs.is_empty()
+            || !unstable_features.is_empty()
+            || !verbatim.is_empty()
+    }
+}
@@ -0,0 +1,699 @@
+use super::{Claim, Compiled, library_path};
+use crate::data::CrateType;
+use std::{
+    env::consts::{DLL_PREFIX, DLL_SUFFIX},
+    path::PathBuf,
+    thread,
+};
+#[cfg(unix)]
+use {
+    super::{Bless, CompileMode, Dependency, DirectiveOptions, Operation, Run, Test},
+    crate::{
+        build::{CompileOptions, DebugOptions, LIBRARY_PATH_VAR, Options},
+        context,
+        data::{Crate, Edition, ExtEdition},
+        directive::Flavor,
+        error::{Error, Result},
+        source::{SourcePath, SourcePathBuf},
+        utility::{
+            default, jobs,
+            testing::{FakeToolchain, Invocation, TempDir},
+        },
+    },
+    std::{env, fs, num::NonZero, path::Path},
+};
+
+fn key(path: &str) -> (PathBuf, bool) {
+    (PathBuf::from(path), false)
+}
+
+/// A directive-driven compilation of a test file.
+#[cfg(unix)]
+struct Compilation {
+    test: Test,
+    run: Run,
+    check_only: bool,
+    flavor: Flavor,
+    all_revisions: bool,
+    deps: Vec<Dependency>,
+    jobs: NonZero<usize>,
+}
+
+#[cfg(unix)]
+impl Default for Compilation {
+    fn default() -> Self {
+        Self {
+            test: Test::No,
+            run: Run::No,
+            check_only: false,
+            flavor: Flavor::Vanilla,
+            all_revisions: false,
+            deps: Vec::new(),
+            jobs: NonZero::<usize>::MIN,
+        }
+    }
+}
+
+/// Perform the given compilation of the given test file under the given toolchain.
+///
+/// The artifacts get placed in the directory `out` next to the test file.
+#[cfg(unix)]
+fn compile(path: &Path, compilation: Compilation, toolchain: &FakeToolchain) -> Result<()> {
+    let cx = context::new!(context::Options {
+        toolchain: Some(toolchain.toolchain()),
+        dbg_opts: DebugOptions { verbose: false },
+        jobs: compilation.jobs,
+    });
+    let op = Operation::Compile {
+        mode: CompileMode::DirectiveDriven(DirectiveOptions {
+            flavor: compilation.flavor,
+            revision: None,
+            all_revisions: compilation.all_revisions,
+            test: compilation.test,
+        }),
+        run: compilation.run,
+        options: CompileOptions { check_only: compilation.check_only, ..default() },
+    };
+    let krate =
+        Crate { path: Some(SourcePath::Regular(path)), name: None, typ: None, edition: None };
+    let mut opts = Options::default();
+    opts.b_opts.out_dir = Some(out_dir(path));
+    super::perform(op, krate, opts, compilation.deps, cx)
+}
+
+#[cfg(unix)]
+fn compile_test(path: &Path, test: Test, toolchain: &FakeToolchain) -> Result<()> {
+    compile(path, Compilation { test, ..default() }, toolchain)
+}
+
+/// The output directory of the given test file.
+#[cfg(unix)]
+fn out_dir(path: &Path) -> PathBuf {
+    path.with_file_name("out")
+}
+
+/// The invocation compiling the given source file.
+#[cfg(unix)]
+fn invocation<'a>(invocations: &'a [Invocation], source: &str) -> &'a Invocation {
+    invocations.iter().find(|invocation| invocation.source().ends_with(source)).unwrap()
+}
+
+#[test]
+fn claimed_auxiliary_fails_if_its_job_panics() {
+    let compiled = Compiled::default();
+    let Claim::Claimed(claimed) = compiled.claim(&key("/a.rs"), &[]) else { panic!() };
+
+    thread::scope(|scope| {
+        let waiter = scope.spawn(|| compiled.claim(&key("/a.rs"), &[]));
+        let panicking = scope.spawn(move || {
+            let _claimed = claimed;
+            panic!("the job panicked");
+        });
+
+        assert!(panicking.join().is_err());
+        assert!(matches!(waiter.join().unwrap(), Claim::Failed(_)));
+    });
+}
+
+#[test]
+fn cycle_across_jobs_is_reported() {
+    let compiled = Compiled::default();
+
+    thread::scope(|scope| {
+        // The first job compiles `a.rs` which depends on `b.rs`.
+        let first = scope.spawn(|| {
+            let Claim::Claimed(claimed) = compiled.claim(&key("/a.rs"), &[]) else { panic!() };
+            // Wait until the second job has claimed `b.rs`.
+            while !compiled.state.lock().unwrap().slots.contains_key(&key("/b.rs")) {
+                thread::yield_now();
+            }
+            let claim = compiled.claim(&key("/b.rs"), &[PathBuf::from("/a.rs")]);
+            claimed.finish(Ok(None));
+            claim
+        });
+
+        // The second job compiles `b.rs` which depends on `a.rs`.
+        let Claim::Claimed(claimed) = compiled.claim(&key("/b.rs"), &[]) else { panic!() };
+        // Wait until the first job is waiting for `b.rs`.
+        while compiled.state.lock().unwrap().waiting.is_empty() {
+            thread::yield_now();
+        }
+        let claim = compiled.claim(&key("/a.rs"), &[PathBuf::from("/b.rs")]);
+        assert!(matches!(claim, Claim::Cyclic));
+        drop(claimed);
+
+        assert!(matches!(first.join().unwrap(), Claim::Failed(_)));
+    });
+}
+
+#[test]
+fn library_paths_of_crate_types() {
+    let path = |typ, check_only| library_path("dep", typ, check_only);
+    let dylib = format!("{DLL_PREFIX}dep{DLL_SUFFIX}");
+
+    assert_eq!(path(None, false), Some("libdep.rlib".into()));
+    assert_eq!(path(Some(CrateType::LIB), false), Some("libdep.rlib".into()));
+    assert_eq!(path(Some(CrateType::DYLIB), false), Some(dylib.clone().into()));
+    assert_eq!(path(Some(CrateType::PROC_MACRO), false), Some(dylib.clone().into()));
+    assert_eq!(path(Some(CrateType::BIN), false), None);
+
+    assert_eq!(path(None, true), Some("libdep.rmeta".into()));
+    assert_eq!(path(Some(CrateType::LIB), true), Some("libdep.rmeta".into()));
+    assert_eq!(path(Some(CrateType::DYLIB), true), Some("libdep.rmeta".into()));
+    // Proc macros get built fully regardless.
+    assert_eq!(path(Some(CrateType::PROC_MACRO), true), Some(dylib.into()));
+    assert_eq!(path(Some(CrateType::BIN), true), None);
+}
+
+#[test]
+#[cfg(unix)]
+fn auxiliaries_are_linked_dynamically_by_default() {
+    let toolchain = FakeToolchain::new("target_family=\"unix\"\ntarget_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ aux-build: dep.rs\n");
+    dir.write("auxiliary/dep.rs", "");
+
+    assert!(compile_test(&path, Test::No, &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    assert_eq!(invocations.len(), 2);
+    let dep = invocation(&invocations, "dep.rs");
+    assert!(dep.has(&["--crate-type", "dylib"]) && dep.has(&["-Cprefer-dynamic"]));
+    let main = invocation(&invocations, "main.rs");
+    assert!(main.has(&["-Cprefer-dynamic"]));
+
+    // The engine has to be able to find the dynamic standard library and dependencies.
+    let existing = env::var_os(LIBRARY_PATH_VAR);
+    let expected = [toolchain.libdir(), out_dir(&path)]
+        .into_iter()
+        .chain(existing.iter().flat_map(env::split_paths));
+    let expected = env::join_paths(expected).unwrap();
+    assert_eq!(main.library_path, expected.to_str().unwrap());
+    assert_eq!(dep.library_path, main.library_path);
+}
+
+#[test]
+#[cfg(unix)]
+fn prefer_dynamic_is_not_inherited_by_auxiliaries() {
+    let toolchain = FakeToolchain::new("target_family=\"unix\"\ntarget_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write(
+        "main.rs",
+        "//@ no-prefer-dynamic\n//@ aux-build: dynamic.rs\n//@ aux-build: static.rs\n",
+    );
+    dir.write("auxiliary/dynamic.rs", "");
+    dir.write("auxiliary/static.rs", "//@ no-prefer-dynamic\n");
+
+    assert!(compile_test(&path, Test::No, &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    let main = invocation(&invocations, "main.rs");
+    assert!(!main.has(&["-Cprefer-dynamic"]));
+    let dynamic = invocation(&invocations, "dynamic.rs");
+    assert!(dynamic.has(&["--crate-type", "dylib"]) && dynamic.has(&["-Cprefer-dynamic"]));
+    let static_ = invocation(&invocations, "static.rs");
+    assert!(!static_.has(&["--crate-type"]) && !static_.has(&["-Cprefer-dynamic"]));
+}
+
+#[test]
+#[cfg(unix)]
+fn auxiliaries_fall_back_to_lib_without_dynamic_linking() {
+    let toolchain = FakeToolchain::new("target_family=\"wasm\"\ntarget_os=\"unknown\"\n");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ aux-build: dep.rs\n");
+    dir.write("auxiliary/dep.rs", "");
+
+    assert!(compile_test(&path, Test::No, &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    let dep = invocation(&invocations, "dep.rs");
+    assert!(dep.has(&["--crate-type", "lib"]));
+}
+
+#[test]
+#[cfg(unix)]
+fn ui_testing_is_enabled_if_unstable_options_are_allowed() {
+    let toolchain = FakeToolchain::new("target_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ check-pass\n//@ aux-build: dep.rs\n");
+    dir.write("auxiliary/dep.rs", "");
+
+    assert!(compile_test(&path, Test::Yes(Bless::No), &toolchain).is_ok());
+    let invocations = toolchain.invocations();
+    assert!(invocation(&invocations, "main.rs").has(&["-Zui-testing"]));
+    // Only the output of the principal crate gets checked.
+    assert!(!invocation(&invocations, "dep.rs").has(&["-Zui-testing"]));
+
+    // Without `-T`, the output is presented as is.
+    assert!(compile_test(&path, Test::No, &toolchain).is_ok());
+    assert!(!toolchain.invocations().last().unwrap().has(&["-Zui-testing"]));
+}
+
+#[test]
+#[cfg(unix)]
+fn ui_testing_is_emulated_on_stable() {
+    let toolchain = FakeToolchain::new("target_os=\"linux\"\n");
+    toolchain.set_version("rustc 1.88.0 (6b00bc388 2025-06-23)");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ check-pass\n");
+
+    assert!(compile_test(&path, Test::Yes(Bless::No), &toolchain).is_ok());
+    assert!(!toolchain.invocations()[0].has(&["-Zui-testing"]));
+}
+
+#[test]
+#[cfg(unix)]
+fn cyclic_auxiliaries() {
+    let toolchain = FakeToolchain::new("");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ aux-build: a.rs\n");
+    dir.write("auxiliary/a.rs", "//@ aux-build: b.rs\n");
+    dir.write("auxiliary/b.rs", "\n//@ aux-build: a.rs\n");
+
+    let (result, stderr) = jobs::capture_stderr(|| compile_test(&path, Test::No, &toolchain));
+    assert!(result.is_err());
+    // Each auxiliary that's part of the cycle gets highlighted.
+    assert_eq!(
+        stderr.replace(dir.path().to_str().unwrap(), "$DIR"),
+        "\
+error: cyclic auxiliaries   $DIR/auxiliary/a.rs:1:16
+//@ aux-build: b.rs
+               ^^^^
+   $DIR/auxiliary/b.rs:2:16
+//@ aux-build: a.rs
+               ^^^^ note: auxiliaries cannot depend on themselves
+"
+    );
+    assert!(toolchain.invocations().is_empty());
+}
+
+#[test]
+#[cfg(unix)]
+fn shared_auxiliaries_are_compiled_once() {
+    for jobs in [1, 4] {
+        let toolchain = FakeToolchain::new("");
+        let dir = TempDir::new();
+        let path = dir.write("main.rs", "//@ aux-build: b.rs\n//@ aux-build: c.rs\n");
+        dir.write("auxiliary/b.rs", "//@ aux-build: d.rs\n");
+        dir.write("auxiliary/c.rs", "//@ aux-build: d.rs\n");
+        dir.write("auxiliary/d.rs", "");
+
+        let jobs = NonZero::new(jobs).unwrap();
+        assert!(compile(&path, Compilation { jobs, ..default() }, &toolchain).is_ok());
+
+        let invocations = toolchain.invocations();
+        let sources: Vec<_> = invocations.iter().map(Invocation::source).collect();
+        assert_eq!(sources.len(), 4, "{sources:?}");
+        for source in ["b.rs", "c.rs", "d.rs", "main.rs"] {
+            assert_eq!(sources.iter().filter(|path| path.ends_with(source)).count(), 1);
+        }
+        // The shared auxiliary gets compiled before its dependents.
+        let position = |source| sources.iter().position(|path| path.ends_with(source)).unwrap();
+        assert!(position("d.rs") < position("b.rs") && position("d.rs") < position("c.rs"));
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn auxiliaries_are_passed_explicitly() {
+    let toolchain = FakeToolchain::new("target_family=\"unix\"\ntarget_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ aux-build: plain.rs\n//@ aux-crate: renamed=dep.rs\n");
+    dir.write("auxiliary/plain.rs", "//@ aux-build: nested.rs\n");
+    dir.write("auxiliary/dep.rs", "");
+    dir.write("auxiliary/nested.rs", "");
+    let out = out_dir(&path);
+    let out = out.to_str().unwrap();
+    let dylib = |name| format!("{out}/{DLL_PREFIX}{name}{DLL_SUFFIX}");
+
+    assert!(compile_test(&path, Test::No, &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    let main = invocation(&invocations, "main.rs");
+    assert!(main.has(&["--extern", &format!("plain={}", dylib("plain"))]));
+    assert!(main.has(&["--extern", &format!("renamed={}", dylib("dep"))]));
+    let plain = invocation(&invocations, "plain.rs");
+    assert!(plain.has(&["--extern", &format!("nested={}", dylib("nested"))]));
+    for invocation in &invocations {
+        let search_paths: Vec<_> =
+            invocation.args.iter().filter(|arg| arg.starts_with("-Ldependency=")).collect();
+        assert_eq!(search_paths, [&format!("-Ldependency={out}")]);
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn checked_auxiliaries_are_kept_apart() {
+    let toolchain = FakeToolchain::new("target_family=\"unix\"\ntarget_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ aux-build: dep.rs\n");
+    dir.write("auxiliary/dep.rs", "//@ aux-build: nested.rs\n");
+    dir.write("auxiliary/nested.rs", "");
+    let out = out_dir(&path);
+    let out = out.to_str().unwrap();
+    let checked = format!("{out}/check");
+
+    let full = Compilation::default;
+    let check = || Compilation { check_only: true, ..default() };
+    // Alternating between full builds and checks mustn't let either come across
+    // the artifacts of the other.
+    for compilation in [full(), check(), full()] {
+        let check_only = compilation.check_only;
+        let from = toolchain.invocations().len();
+        assert!(compile(&path, compilation, &toolchain).is_ok());
+
+        let invocations = &toolchain.invocations()[from..];
+        let main = invocation(invocations, "main.rs");
+        let dep = invocation(invocations, "dep.rs");
+        if check_only {
+            assert!(main.has(&["--extern", &format!("dep={checked}/libdep.rmeta")]));
+            assert!(dep.has(&["--extern", &format!("nested={checked}/libnested.rmeta")]));
+            assert!(dep.has(&["--out-dir", &checked]));
+            for invocation in [main, dep] {
+                assert!(
+                    invocation
+                        .has(&[&format!("-Ldependency={checked}"), &format!("-Ldependency={out}")])
+                );
+            }
+        } else {
+            assert!(main.has(&["--extern", &format!("dep={out}/{DLL_PREFIX}dep{DLL_SUFFIX}")]));
+            for invocation in invocations {
+                assert!(!invocation.args.iter().any(|arg| arg.contains(&checked)));
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn check_only_propagates_to_auxiliaries() {
+    let toolchain = FakeToolchain::new("target_family=\"unix\"\ntarget_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ aux-build: dep.rs\n//@ proc-macro: mac.rs\n");
+    let check_pass = dir.write("check-pass.rs", "//@ check-pass\n//@ aux-build: dep.rs\n");
+    dir.write("auxiliary/dep.rs", "//@ aux-build: nested.rs\n");
+    dir.write("auxiliary/nested.rs", "");
+    dir.write("auxiliary/mac.rs", "//@ aux-build: helper.rs\n");
+    dir.write("auxiliary/helper.rs", "");
+    let out = out_dir(&path);
+    let out = out.to_str().unwrap();
+
+    assert!(compile(&path, Compilation { check_only: true, ..default() }, &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    let main = invocation(&invocations, "main.rs");
+    assert!(main.has(&["--emit=metadata"]));
+    assert!(main.has(&["--extern", &format!("dep={out}/check/libdep.rmeta")]));
+    let dep = invocation(&invocations, "dep.rs");
+    assert!(dep.has(&["--emit=metadata"]));
+    assert!(dep.has(&["--extern", &format!("nested={out}/check/libnested.rmeta")]));
+    assert!(invocation(&invocations, "nested.rs").has(&["--emit=metadata"]));
+
+    // Proc macros get executed, so they and their dependencies have to be built fully.
+    let mac = invocation(&invocations, "mac.rs");
+    assert!(mac.has(&["--crate-type", "proc-macro"]) && !mac.has(&["--emit=metadata"]));
+    let helper = format!("{out}/{DLL_PREFIX}helper{DLL_SUFFIX}");
+    assert!(mac.has(&["--extern", &format!("helper={helper}")]));
+    assert!(!invocation(&invocations, "helper.rs").has(&["--emit=metadata"]));
+    assert!(main.has(&["--extern", &format!("mac={out}/{DLL_PREFIX}mac{DLL_SUFFIX}")]));
+
+    // The pass mode of a test has the same effect.
+    let from = invocations.len();
+    assert!(compile_test(&check_pass, Test::Yes(Bless::No), &toolchain).is_ok());
+
+    let invocations = &toolchain.invocations()[from..];
+    let main = invocation(invocations, "check-pass.rs");
+    assert!(main.has(&["--emit=metadata"]));
+    assert!(main.has(&["--extern", &format!("dep={out}/check/libdep.rmeta")]));
+    assert!(invocation(invocations, "dep.rs").has(&["--emit=metadata"]));
+}
+
+#[test]
+#[cfg(unix)]
+fn check_pass_emits_metadata_only() {
+    let toolchain = FakeToolchain::new("");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ check-pass\n");
+
+    assert!(compile_test(&path, Test::Yes(Bless::No), &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    assert_eq!(invocations.len(), 1);
+    assert!(invocations[0].has(&["--emit=metadata"]));
+    assert!(out_dir(&path).join("libmain.rmeta").exists());
+    assert!(!out_dir(&path).join("main").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn run_pass_runs_the_binary() {
+    let toolchain = FakeToolchain::new("");
+    let dir = TempDir::new();
+    let marker = dir.path().join("ran");
+    let path =
+        dir.write("main.rs", &format!("//@ run-pass\n// fake: run touch {}\n", marker.display()));
+    let failing = dir.write("failing.rs", "//@ run-pass\n// fake: run exit 1\n");
+
+    assert!(compile_test(&path, Test::Yes(Bless::No), &toolchain).is_ok());
+    assert!(!toolchain.invocations()[0].has(&["--emit=metadata"]));
+    assert!(marker.exists());
+
+    let (result, stderr) =
+        jobs::capture_stderr(|| compile_test(&failing, Test::Yes(Bless::No), &toolchain));
+    assert!(result.is_err());
+    assert!(
+        stderr.starts_with("error: the test binary failed but it was expected to succeed"),
+        "{stderr}"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn build_fail_expects_the_build_to_fail() {
+    let toolchain = FakeToolchain::new("");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ build-fail\n// fake: error\n");
+    dir.write("main.stderr", "error: requested by the test\n");
+    let passing = dir.write("passing.rs", "//@ build-fail\n");
+
+    let (result, stderr) =
+        jobs::capture_stderr(|| compile_test(&path, Test::Yes(Bless::No), &toolchain));
+    assert!(result.is_ok(), "{stderr}");
+    assert!(!toolchain.invocations()[0].has(&["--emit=metadata"]));
+
+    let (result, stderr) =
+        jobs::capture_stderr(|| compile_test(&passing, Test::Yes(Bless::No), &toolchain));
+    assert!(result.is_err());
+    assert!(!toolchain.invocations()[1].has(&["--emit=metadata"]));
+    assert!(
+        stderr.starts_with("error: the test compiled successfully but it was expected to fail"),
+        "{stderr}"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn inline_crates_are_compiled_separately() {
+    let toolchain = FakeToolchain::new("target_family=\"unix\"\ntarget_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write(
+        "main.rs",
+        "\
+//@ crate dep {
+//@ edition: 2021
+pub fn f() {}
+//@ }
+//@ raw-crate raw {
+pub fn g() {}
+//@ }
+fn main() { dep::f(); }
+",
+    );
+    let out = out_dir(&path);
+    let out = out.to_str().unwrap();
+
+    let compilation = Compilation { flavor: Flavor::Rruxwry, ..default() };
+    assert!(compile(&path, compilation, &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    let [dep, raw, main] = &invocations[..] else { panic!("{}", invocations.len()) };
+    // Each crate gets compiled into its own artifact from its own virtual file.
+    for (invocation, name) in [(dep, "dep"), (raw, "raw"), (main, "main")] {
+        assert!(invocation.has(&["-", "--crate-name", name]));
+    }
+    assert!(dep.has(&["--crate-type", "dylib"]) && dep.has(&["--edition", "2021"]));
+    assert!(!raw.has(&["--edition", "2021"]));
+    assert!(main.has(&["--extern", &format!("dep={out}/{DLL_PREFIX}dep{DLL_SUFFIX}")]));
+    // Raw crates don't get added to the extern prelude but they can still be loaded explicitly.
+    assert!(!main.args.iter().any(|arg| arg.starts_with("raw")));
+    assert!(main.has(&[&format!("-Lcrate={out}")]));
+    assert!(out_dir(&path).join(format!("{DLL_PREFIX}raw{DLL_SUFFIX}")).exists());
+
+    // Everything outside of the respective crate gets masked, so the locations in diagnostics
+    // remain the same.
+    let contents = fs::read_to_string(&path).unwrap();
+    let inputs = [toolchain.input("dep"), toolchain.input("raw"), toolchain.input("main")];
+    for input in &inputs {
+        assert_eq!(input.len(), contents.len());
+        assert_eq!(input.lines().count(), contents.lines().count());
+    }
+    fn lines(input: &str) -> Vec<&str> {
+        input.lines().map(str::trim_end).filter(|line| !line.is_empty()).collect()
+    }
+    let [dep, raw, main] = inputs.each_ref().map(|input| lines(input));
+    assert_eq!(dep, ["//@ edition: 2021", "pub fn f() {}"]);
+    assert_eq!(raw, ["pub fn g() {}"]);
+    assert_eq!(
+        main,
+        ["//@ crate dep {", "//@ }", "//@ raw-crate raw {", "//@ }", "fn main() { dep::f(); }"]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn directives_of_dependencies_are_honored() {
+    let toolchain = FakeToolchain::new("target_family=\"unix\"\ntarget_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "");
+    let dep = dir.write("dep.rs", "//@ edition: 2021\n//@ compile-flags: --cfg from_directive\n");
+    let overridden = dir.write("overridden.rs", "//@ edition: 2021\n");
+    let dependency = |path, edition| Dependency {
+        path: SourcePathBuf::Regular(path),
+        name: None,
+        typ: None,
+        edition,
+        cfgs: Vec::new(),
+        unstable_features: Vec::new(),
+        verbatim: Vec::new(),
+        dependencies: Vec::new(),
+    };
+    let deps = vec![
+        dependency(dep, None),
+        // The edition passed on the command line takes precedence.
+        dependency(overridden, Some(ExtEdition::Fixed(Edition::Rust2015))),
+    ];
+    let out = out_dir(&path);
+    let out = out.to_str().unwrap();
+
+    assert!(compile(&path, Compilation { deps, ..default() }, &toolchain).is_ok());
+
+    let invocations = toolchain.invocations();
+    let dep = invocation(&invocations, "dep.rs");
+    assert!(dep.has(&["--crate-type", "lib", "--edition", "2021"]));
+    assert!(dep.has(&["--cfg", "from_directive"]));
+    let overridden = invocation(&invocations, "overridden.rs");
+    assert!(overridden.has(&["--edition", "2015"]));
+    // The directives of the dependency don't leak into the principal crate.
+    let main = invocation(&invocations, "main.rs");
+    assert!(!main.has(&["--edition", "2021"]) && !main.has(&["--cfg", "from_directive"]));
+    assert!(main.has(&["--extern", &format!("dep={out}/libdep.rlib")]));
+}
+
+#[test]
+#[cfg(unix)]
+fn all_revisions_are_built_despite_failures() {
+    let toolchain = FakeToolchain::new("");
+    let dir = TempDir::new();
+    let path =
+        dir.write("main.rs", "//@ check-pass\n//@ revisions: bad good\n// fake: error bad\n");
+    let out = out_dir(&path);
+
+    let compilation = Compilation { test: Test::Yes(Bless::No), all_revisions: true, ..default() };
+    let (result, stderr) = jobs::capture_stderr(|| compile(&path, compilation, &toolchain));
+    assert!(result.is_err());
+
+    // The failing revision doesn't prevent the other one from getting built.
+    let invocations = toolchain.invocations();
+    let [bad, good] = &invocations[..] else { panic!("{}", invocations.len()) };
+    let revision_dir = |revision| out.join(format!("main.{revision}"));
+    assert!(bad.has(&["--cfg", "bad"]));
+    assert!(bad.has(&["--out-dir", revision_dir("bad").to_str().unwrap()]));
+    assert!(good.has(&["--cfg", "good"]));
+    assert!(good.has(&["--out-dir", revision_dir("good").to_str().unwrap()]));
+    // Only the artifacts of failing revisions are kept.
+    assert!(revision_dir("bad").exists() && !revision_dir("good").exists());
+
+    assert!(stderr.starts_with("info: revision `bad`"), "{stderr}");
+    assert!(stderr.contains("info: revision `good`"), "{stderr}");
+    assert!(stderr.contains("error: test `"), "{stderr}");
+    assert!(
+        stderr.ends_with("error: 1 of 2 revisions failed\n  note: failing revisions: `bad`\n"),
+        "{stderr}"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn all_revisions_can_be_ignored() {
+    let toolchain = FakeToolchain::new("target_os=\"linux\"\n");
+    let dir = TempDir::new();
+    let partially = dir.write(
+        "partially.rs",
+        "//@ check-pass\n//@ revisions: first second\n//@[first] ignore-linux\n",
+    );
+    let entirely =
+        dir.write("entirely.rs", "//@ check-pass\n//@ revisions: first second\n//@ ignore-linux\n");
+    let compilation =
+        || Compilation { test: Test::Yes(Bless::No), all_revisions: true, ..default() };
+
+    let (result, stderr) = jobs::capture_stderr(|| compile(&partially, compilation(), &toolchain));
+    assert!(result.is_ok());
+    assert_eq!(toolchain.invocations().len(), 1);
+    assert!(
+        stderr.ends_with("info: 1 of 2 revisions passed\n  note: ignored revisions: `first`\n"),
+        "{stderr}"
+    );
+
+    // If no revision passed or failed, the test as a whole was ignored.
+    let (result, stderr) = jobs::capture_stderr(|| compile(&entirely, compilation(), &toolchain));
+    assert!(matches!(result, Err(Error::Ignored)));
+    assert_eq!(toolchain.invocations().len(), 1);
+    assert_eq!(stderr.matches("info: ignoring the test").count(), 2, "{stderr}");
+}
+
+#[test]
+#[cfg(unix)]
+fn check_cfg_is_gated_on_the_engine_version() {
+    let dir = TempDir::new();
+    let path = dir.write("main.rs", "//@ revisions: a b-c\n");
+    let opted_out = dir.write("opted-out.rs", "//@ revisions: a b-c\n//@ no-auto-check-cfg\n");
+    let compilation = || Compilation { all_revisions: true, ..default() };
+
+    // Whether `--check-cfg` is expected and whether it has to be paired with `-Zunstable-options`.
+    for (version, expected) in [
+        ("rustc 1.80.0 (051478957 2024-07-21)", Some(false)),
+        ("rustc 1.90.0-nightly (0123456789 2025-07-01)", Some(false)),
+        ("rustc 1.79.0-nightly (0123456789 2024-04-01)", Some(true)),
+        ("rustc 1.75.0-nightly (0123456789 2023-10-15)", Some(true)),
+        ("rustc 1.74.0-nightly (0123456789 2023-09-01)", None),
+        // Unstable options aren't allowed on stable.
+        ("rustc 1.79.0 (129f3b996 2024-06-10)", None),
+    ] {
+        let toolchain = FakeToolchain::new("");
+        toolchain.set_version(version);
+
+        let (result, _) = jobs::capture_stderr(|| compile(&path, compilation(), &toolchain));
+        assert!(result.is_ok(), "{version}");
+
+        let invocations = toolchain.invocations();
+        assert_eq!(invocations.len(), 2, "{version}");
+        for (invocation, revision) in invocations.iter().zip(["a", "b_c"]) {
+            assert!(invocation.has(&["--cfg", revision]), "{version}");
+            let check_cfg = invocation.has(&["--check-cfg", "cfg(test,FALSE,a,b_c)"]);
+            let unstable = invocation.has(&["-Zunstable-options"]);
+            match expected {
+                Some(true) => assert!(check_cfg && unstable, "{version}"),
+                Some(false) => assert!(check_cfg && !unstable, "{version}"),
+                None => assert!(!invocation.has(&["--check-cfg"]) && !unstable, "{version}"),
+            }
+        }
+
+        let (result, _) = jobs::capture_stderr(|| compile(&opted_out, compilation(), &toolchain));
+        assert!(result.is_ok(), "{version}");
+        let invocations = toolchain.invocations();
+        assert_eq!(invocations.len(), 4, "{version}");
+        for invocation in &invocations[2..] {
+            assert!(!invocation.has(&["--check-cfg"]), "{version}");
+        }
+    }
+}
@@ -14,11 +14,6 @@ pub(crate) struct SourceMap {
 }
 
 impl SourceMap {
-    fn offset(&self) -> u32 {
-        const PADDING: u32 = 1;
-        self.files.last().map(|file| file.span.end).unwrap_or_default() + PADDING
-    }
-
     // FIXME: Detect circular/cyclic imports here.
     pub(crate) fn read(
         &self,
@@ -40,6 +35,7 @@ impl SourceMap {
                     .done()
             })?,
             SourcePath::Stdin => io::read_to_string(io::stdin())?,
+            SourcePath::Virtual { .. } => unreachable!(), // Virtual files get added upfront.
         };
 
         // FIXME: Ideally, we would take an owned path to avoid the clone.
@@ -51,11 +47,15 @@ impl SourceMap {
         path: SourcePathBuf,
         contents: String,
     ) -> crate::error::Result<SourceFile<'_>> {
-        let file = SourceFileBuf::new(path, contents, self.offset());
-        // FIXME: Safety comment.
-        let result = unsafe { file.as_ref() };
-        self.files.push(file);
-        Ok(result)
+        const PADDING: u32 = 1;
+
+        Ok(self.files.push_with(|last| {
+            let offset = last.map(|file| file.span.end).unwrap_or_default() + PADDING;
+            let file = SourceFileBuf::new(path, contents, offset);
+            // FIXME: Safety comment.
+            let result = unsafe { file.as_ref() };
+            (file, result)
+        }))
     }
 
     // FIXME: Use this comment again:
@@ -100,6 +100,10 @@ impl SourceFileBuf {
                 SourcePathBuf::Regular(ref path) => {
                     SourcePath::Regular(unsafe { &*std::ptr::from_ref::<Path>(path) })
                 }
+                SourcePathBuf::Virtual { ref path, ref name } => SourcePath::Virtual {
+                    path: unsafe { &*std::ptr::from_ref::<Path>(path) },
+                    name: unsafe { &*std::ptr::from_ref(name.as_str()) },
+                },
                 SourcePathBuf::Stdin => SourcePath::Stdin,
             },
             contents: unsafe { &*std::ptr::from_ref(self.contents.as_str()) },
@@ -108,8 +112,10 @@ impl SourceFileBuf {
     }
 }
 
+#[derive(Clone)]
 pub(crate) enum SourcePathBuf {
     Regular(PathBuf),
+    Virtual { path: PathBuf, name: String },
     Stdin,
 }
 
@@ -123,11 +129,27 @@ impl SourcePathBuf {
     pub(crate) fn as_ref(&self) -> SourcePath<'_> {
         match self {
             Self::Regular(path) => SourcePath::Regular(&path),
+            Self::Virtual { path, name } => SourcePath::Virtual { path, name },
             Self::Stdin => SourcePath::Stdin,
         }
     }
 }
 
+/// Replace the characters at the masked byte positions with spaces.
+///
+/// This preserves line breaks and byte offsets.
+pub(crate) fn mask(contents: &str, masked: impl Fn(usize) -> bool) -> String {
+    let mut output = String::with_capacity(contents.len());
+    for (index, char) in contents.char_indices() {
+        if char != '\n' && masked(index) {
+            output.extend(std::iter::repeat_n(' ', char.len_utf8()));
+        } else {
+            output.push(char);
+        }
+    }
+    output
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct SourceFile<'a> {
     pub(crate) path: SourcePath<'a>,
@@ -138,6 +160,13 @@ pub(crate) struct SourceFile<'a> {
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub(crate) enum SourcePath<'a> {
     Regular(&'a Path),
+    /// A file that only exists in memory and that gets fed to the engine via STDIN.
+    ///
+    /// It's derived from the file at `path` and contains the crate `name`.
+    Virtual {
+        path: &'a Path,
+        name: &'a str,
+    },
     Stdin,
 }
 
@@ -145,6 +174,9 @@ impl SourcePath<'_> {
     pub(crate) fn to_owned(self) -> SourcePathBuf {
         match self {
             Self::Regular(path) => SourcePathBuf::Regular(path.to_owned()),
+            Self::Virtual { path, name } => {
+                SourcePathBuf::Virtual { path: path.to_owned(), name: name.to_owned() }
+            }
             Self::Stdin => SourcePathBuf::Stdin,
         }
     }
@@ -213,7 +245,6 @@ impl<const L: Locality> fmt::Debug for Span<L> {
 pub(crate) type LocalSpan = Span<{ Locality::Local }>;
 
 impl LocalSpan {
-    #[allow(dead_code)] // FIXME: actually use it
     pub(crate) fn global(self, file: SourceFile<'_>) -> Span {
         self.shift(file.span.start).reinterpret()
     }
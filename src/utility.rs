@@ -1,8 +1,12 @@
 use std::{ascii::Char, ffi::OsStr};
 
+pub(crate) mod jobs;
+pub(crate) mod json;
 pub(crate) mod monotonic;
 pub(crate) mod paint;
 pub(crate) mod small_fixed_map;
+#[cfg(test)]
+pub(crate) mod testing;
 
 pub(crate) use core::direct_const_arg as lift;
 pub(crate) use rustc_hash::FxHashMap as HashMap;
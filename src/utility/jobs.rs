@@ -0,0 +1,200 @@
+//! Running jobs concurrently without interleaving their output.
+
+use std::{
+    cell::RefCell,
+    io::{self, Write as _},
+    mem,
+    num::NonZero,
+    panic,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[cfg(test)]
+mod test;
+
+/// A pool of threads to run jobs on.
+pub(crate) struct Jobs {
+    /// The number of threads that may still be spawned.
+    available: Mutex<usize>,
+    parallel: bool,
+}
+
+impl Jobs {
+    pub(crate) fn new(count: NonZero<usize>) -> Self {
+        Self { available: Mutex::new(count.get() - 1), parallel: count.get() > 1 }
+    }
+
+    /// Run the given job for each item, concurrently if possible, and return the results in order.
+    ///
+    /// Without parallelism, this stops at the first error. Otherwise, the first error gets
+    /// returned once all jobs have finished. Jobs run on the current thread if no other thread
+    /// is available which prevents nested invocations from starving. Under parallelism, the output
+    /// of each job gets buffered and written out in one go once the job has finished.
+    pub(crate) fn try_map<T: Send, R: Send, E: Send>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        job: impl Fn(T) -> Result<R, E> + Sync,
+    ) -> Result<Vec<R>, E> {
+        if !self.parallel {
+            return items.into_iter().map(job).collect();
+        }
+
+        let job = &job;
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = items
+                .into_iter()
+                .map(|item| {
+                    let parent = SINK.with_borrow(Clone::clone);
+                    if self.acquire() {
+                        Handle::Spawned(scope.spawn(move || {
+                            let result = capture(parent, || job(item));
+                            self.release();
+                            result
+                        }))
+                    } else {
+                        Handle::Finished(capture(parent, || job(item)))
+                    }
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| match handle {
+                    Handle::Spawned(handle) => {
+                        handle.join().unwrap_or_else(|payload| panic::resume_unwind(payload))
+                    }
+                    Handle::Finished(result) => result,
+                })
+                .collect()
+        });
+
+        results.into_iter().collect()
+    }
+
+    fn acquire(&self) -> bool {
+        let mut available = self.available.lock().unwrap();
+        let acquired = *available > 0;
+        if acquired {
+            *available -= 1;
+        }
+        acquired
+    }
+
+    fn release(&self) {
+        *self.available.lock().unwrap() += 1;
+    }
+}
+
+enum Handle<'scope, R> {
+    Spawned(thread::ScopedJoinHandle<'scope, R>),
+    /// The job ran on the current thread since no other thread was available.
+    Finished(R),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// The buffered output of a job.
+#[derive(Default)]
+struct Output {
+    chunks: Vec<(Stream, Vec<u8>)>,
+}
+
+type Sink = Option<Arc<Mutex<Output>>>;
+
+thread_local! {
+    /// The output of the job running on the current thread if it gets buffered.
+    static SINK: RefCell<Sink> = const { RefCell::new(None) };
+}
+
+/// Buffer the output of the given job and append it to the parent sink once it has finished.
+fn capture<R>(parent: Sink, job: impl FnOnce() -> R) -> R {
+    let sink = Arc::new(Mutex::new(Output::default()));
+    let previous = SINK.replace(Some(sink.clone()));
+    let result = job();
+    SINK.set(previous);
+
+    let Output { chunks } = mem::take(&mut *sink.lock().unwrap());
+    if let Some(parent) = parent {
+        parent.lock().unwrap().chunks.extend(chunks);
+    } else {
+        // Lock both streams upfront, so the output of other jobs can't get in between.
+        let (mut stdout, mut stderr) = (io::stdout().lock(), io::stderr().lock());
+        for (stream, bytes) in chunks {
+            // There's nothing sensible we can do on failure.
+            let _ = match stream {
+                Stream::Stdout => stdout.write_all(&bytes).and_then(|()| stdout.flush()),
+                Stream::Stderr => stderr.write_all(&bytes),
+            };
+        }
+    }
+
+    result
+}
+
+/// Run the given job and return its output to stderr (without any styling) instead of emitting it.
+#[cfg(test)]
+pub(crate) fn capture_stderr<R>(job: impl FnOnce() -> R) -> (R, String) {
+    let sink = Arc::new(Mutex::new(Output::default()));
+    let result = capture(Some(sink.clone()), job);
+
+    let Output { chunks } = mem::take(&mut *sink.lock().unwrap());
+    let stderr: Vec<_> = chunks
+        .into_iter()
+        .filter(|(stream, _)| matches!(stream, Stream::Stderr))
+        .flat_map(|(_, bytes)| bytes)
+        .collect();
+    (result, anstream::adapter::strip_str(&String::from_utf8_lossy(&stderr)).to_string())
+}
+
+/// Whether the output of the current thread gets buffered.
+pub(crate) fn buffered() -> bool {
+    SINK.with_borrow(Option::is_some)
+}
+
+/// Write to the given stream unless the output of the current thread gets buffered.
+pub(crate) fn write(stream: Stream, bytes: &[u8]) -> io::Result<()> {
+    if let Some(sink) = SINK.with_borrow(Clone::clone) {
+        sink.lock().unwrap().chunks.push((stream, bytes.to_owned()));
+        return Ok(());
+    }
+
+    match stream {
+        Stream::Stdout => io::stdout().lock().write_all(bytes),
+        Stream::Stderr => io::stderr().lock().write_all(bytes),
+    }
+}
+
+/// A writer that collects everything and writes it to the stream in one go once flushed.
+pub(crate) struct Writer {
+    stream: Stream,
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn new(stream: Stream) -> Self {
+        Self { stream, buffer: Vec::new() }
+    }
+}
+
+impl io::Write for Writer {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        write(self.stream, &mem::take(&mut self.buffer))
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        // There's nothing sensible we can do on failure.
+        let _ = self.flush();
+    }
+}
@@ -0,0 +1,95 @@
+use super::{Jobs, Output, Stream, capture, write};
+use std::{
+    num::NonZero,
+    sync::{Arc, Barrier, Mutex},
+    thread,
+    time::Duration,
+};
+
+fn jobs(count: usize) -> Jobs {
+    Jobs::new(NonZero::new(count).unwrap())
+}
+
+#[test]
+fn results_are_in_order() {
+    let results = jobs(4).try_map(0..8u64, |item| {
+        // Let later jobs finish first.
+        thread::sleep(Duration::from_millis(8 - item));
+        Ok::<_, ()>(item * 2)
+    });
+    assert_eq!(results, Ok(vec![0, 2, 4, 6, 8, 10, 12, 14]));
+}
+
+#[test]
+fn first_error_is_returned_after_all_jobs_finished() {
+    let finished = Mutex::new(Vec::new());
+    let result = jobs(4).try_map(0..6, |item| {
+        finished.lock().unwrap().push(item);
+        if item % 2 == 1 { Err(item) } else { Ok(item) }
+    });
+    assert_eq!(result, Err(1));
+    assert_eq!(finished.into_inner().unwrap().len(), 6);
+}
+
+#[test]
+fn first_error_stops_sequential_jobs() {
+    let finished = Mutex::new(Vec::new());
+    let result = jobs(1).try_map(0..6, |item| {
+        finished.lock().unwrap().push(item);
+        if item % 2 == 1 { Err(item) } else { Ok(item) }
+    });
+    assert_eq!(result, Err(1));
+    assert_eq!(finished.into_inner().unwrap(), [0, 1]);
+}
+
+#[test]
+fn jobs_run_inline_if_no_thread_is_available() {
+    let current = thread::current().id();
+    // The first job occupies the only other thread until the last one has started.
+    let barrier = Barrier::new(2);
+    let threads = jobs(2)
+        .try_map(0..3, |item| {
+            if item != 1 {
+                barrier.wait();
+            }
+            Ok::<_, ()>(thread::current().id())
+        })
+        .unwrap();
+    assert_ne!(threads[0], current);
+    assert_eq!(threads[1..], [current, current]);
+}
+
+#[test]
+fn nested_output_is_contiguous() {
+    let root = Arc::new(Mutex::new(Output::default()));
+    let jobs = jobs(3);
+
+    capture(Some(root.clone()), || {
+        jobs.try_map(0..3, |outer| {
+            write(Stream::Stderr, format!("<{outer}").as_bytes())?;
+            jobs.try_map(0..3, |inner| {
+                thread::sleep(Duration::from_millis(3 - inner));
+                write(Stream::Stderr, format!("{outer}.{inner}").as_bytes())
+            })?;
+            write(Stream::Stderr, format!("{outer}>").as_bytes())
+        })
+    })
+    .unwrap();
+
+    let chunks: Vec<_> = root
+        .lock()
+        .unwrap()
+        .chunks
+        .iter()
+        .map(|(_, bytes)| String::from_utf8(bytes.clone()).unwrap())
+        .collect();
+    assert_eq!(chunks.len(), 15);
+    for job in chunks.chunks(5) {
+        let outer = &job[0][1..];
+        assert_eq!(job[4], format!("{outer}>"));
+        // The nested jobs themselves may finish in any order.
+        let mut nested = job[1..4].to_vec();
+        nested.sort();
+        assert_eq!(nested, (0..3).map(|inner| format!("{outer}.{inner}")).collect::<Vec<_>>());
+    }
+}
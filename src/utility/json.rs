@@ -0,0 +1,336 @@
+//! A minimal JSON parser.
+//!
+//! It's only meant for consuming the machine-readable output of rust{,do}c.
+
+use std::{
+    fmt::{self, Write as _},
+    iter::Peekable,
+    str::CharIndices,
+};
+
+#[cfg(test)]
+mod test;
+
+pub(crate) fn parse(source: &str) -> Result<Value, Error> {
+    let mut parser = Parser { chars: source.char_indices().peekable(), source };
+    let value = parser.parse_value()?;
+    parser.parse_whitespace();
+    match parser.chars.next() {
+        Some((index, _)) => Err(Error::TrailingInput(index)),
+        None => Ok(value),
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// An object whose entries are kept in source order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub(crate) fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(entries) => {
+                entries.iter().find(|(candidate, _)| candidate == key).map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Self::Number(number) if number >= 0.0 && number.fract() == 0.0 => Some(number as usize),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    /// Structural equality where the order of object entries is irrelevant.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null) => true,
+            (Self::Bool(this), Self::Bool(other)) => this == other,
+            (Self::Number(this), Self::Number(other)) => this == other,
+            (Self::String(this), Self::String(other)) => this == other,
+            (Self::Array(this), Self::Array(other)) => this == other,
+            (Self::Object(this), Self::Object(other)) => {
+                this.len() == other.len()
+                    && this.iter().all(|(key, value)| {
+                        other.iter().any(|(candidate, other)| candidate == key && value == other)
+                    })
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Render the value as compact JSON.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_string(string: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_char('"')?;
+            for char in string.chars() {
+                match char {
+                    '"' => f.write_str("\\\"")?,
+                    '\\' => f.write_str("\\\\")?,
+                    '\n' => f.write_str("\\n")?,
+                    '\r' => f.write_str("\\r")?,
+                    '\t' => f.write_str("\\t")?,
+                    char if char < ' ' => write!(f, "\\u{:04x}", u32::from(char))?,
+                    char => f.write_char(char)?,
+                }
+            }
+            f.write_char('"')
+        }
+
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(bool) => write!(f, "{bool}"),
+            Self::Number(number) => write!(f, "{number}"),
+            Self::String(string) => write_string(string, f),
+            Self::Array(elements) => {
+                f.write_char('[')?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index != 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(entries) => {
+                f.write_char('{')?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index != 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(key, f)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+struct Parser<'src> {
+    chars: Peekable<CharIndices<'src>>,
+    source: &'src str,
+}
+
+impl Parser<'_> {
+    fn parse_value(&mut self) -> Result<Value, Error> {
+        self.parse_whitespace();
+
+        let Some(&(index, char)) = self.chars.peek() else {
+            return Err(Error::UnexpectedEndOfInput);
+        };
+
+        match char {
+            'n' => self.parse_keyword("null", Value::Null),
+            't' => self.parse_keyword("true", Value::Bool(true)),
+            'f' => self.parse_keyword("false", Value::Bool(false)),
+            '"' => self.parse_string().map(Value::String),
+            '[' => self.parse_array(),
+            '{' => self.parse_object(),
+            '-' | '0'..='9' => self.parse_number(),
+            _ => Err(Error::UnexpectedChar(index, char)),
+        }
+    }
+
+    fn parse_keyword(&mut self, keyword: &str, value: Value) -> Result<Value, Error> {
+        for expected in keyword.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, Error> {
+        let start = self.index();
+        while self
+            .chars
+            .next_if(|&(_, char)| matches!(char, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+            .is_some()
+        {}
+        let end = self.index();
+
+        self.source[start..end].parse().map(Value::Number).map_err(|_| Error::InvalidNumber(start))
+    }
+
+    fn parse_string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+
+        let mut string = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => break,
+                Some((_, '\\')) => string.push(self.parse_escape_sequence()?),
+                Some((index, char)) if char < ' ' => {
+                    return Err(Error::UnexpectedChar(index, char));
+                }
+                Some((_, char)) => string.push(char),
+                None => return Err(Error::UnexpectedEndOfInput),
+            }
+        }
+
+        Ok(string)
+    }
+
+    fn parse_escape_sequence(&mut self) -> Result<char, Error> {
+        let Some((index, char)) = self.chars.next() else {
+            return Err(Error::UnexpectedEndOfInput);
+        };
+
+        Ok(match char {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.parse_code_unit()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high.into()).ok_or(Error::InvalidEscape(index));
+                }
+
+                // We've encountered a high surrogate, it has to be followed by a low one.
+                self.expect('\\')?;
+                self.expect('u')?;
+                let low = self.parse_code_unit()?;
+                char::decode_utf16([high, low])
+                    .next()
+                    .unwrap()
+                    .map_err(|_| Error::InvalidEscape(index))?
+            }
+            _ => return Err(Error::InvalidEscape(index)),
+        })
+    }
+
+    fn parse_code_unit(&mut self) -> Result<u16, Error> {
+        let start = self.index();
+        for _ in 0..4 {
+            if self.chars.next_if(|(_, char)| char.is_ascii_hexdigit()).is_none() {
+                return Err(Error::InvalidEscape(start));
+            }
+        }
+        Ok(u16::from_str_radix(&self.source[start..start + 4], 16).unwrap())
+    }
+
+    fn parse_array(&mut self) -> Result<Value, Error> {
+        self.expect('[')?;
+
+        let mut elements = Vec::new();
+
+        self.parse_whitespace();
+        if self.chars.next_if(|&(_, char)| char == ']').is_some() {
+            return Ok(Value::Array(elements));
+        }
+
+        loop {
+            elements.push(self.parse_value()?);
+            self.parse_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, ']')) => break,
+                Some((index, char)) => return Err(Error::UnexpectedChar(index, char)),
+                None => return Err(Error::UnexpectedEndOfInput),
+            }
+        }
+
+        Ok(Value::Array(elements))
+    }
+
+    fn parse_object(&mut self) -> Result<Value, Error> {
+        self.expect('{')?;
+
+        let mut entries = Vec::new();
+
+        self.parse_whitespace();
+        if self.chars.next_if(|&(_, char)| char == '}').is_some() {
+            return Ok(Value::Object(entries));
+        }
+
+        loop {
+            self.parse_whitespace();
+            let key = self.parse_string()?;
+            self.parse_whitespace();
+            self.expect(':')?;
+            entries.push((key, self.parse_value()?));
+            self.parse_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, '}')) => break,
+                Some((index, char)) => return Err(Error::UnexpectedChar(index, char)),
+                None => return Err(Error::UnexpectedEndOfInput),
+            }
+        }
+
+        Ok(Value::Object(entries))
+    }
+
+    fn parse_whitespace(&mut self) {
+        while self.chars.next_if(|&(_, char)| matches!(char, ' ' | '\t' | '\n' | '\r')).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        match self.chars.next() {
+            Some((_, char)) if char == expected => Ok(()),
+            Some((index, char)) => Err(Error::UnexpectedChar(index, char)),
+            None => Err(Error::UnexpectedEndOfInput),
+        }
+    }
+
+    fn index(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |&(index, _)| index)
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub(crate) enum Error {
+    UnexpectedChar(usize, char),
+    UnexpectedEndOfInput,
+    InvalidNumber(usize),
+    InvalidEscape(usize),
+    TrailingInput(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::UnexpectedChar(index, char) => {
+                write!(f, "unexpected character `{}` at byte {index}", char.escape_debug())
+            }
+            Self::UnexpectedEndOfInput => write!(f, "unexpected end of input"),
+            Self::InvalidNumber(index) => write!(f, "invalid number at byte {index}"),
+            Self::InvalidEscape(index) => write!(f, "invalid escape sequence at byte {index}"),
+            Self::TrailingInput(index) => write!(f, "trailing input at byte {index}"),
+        }
+    }
+}
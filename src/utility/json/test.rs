@@ -0,0 +1,67 @@
+use super::*;
+
+fn string(string: &str) -> Value {
+    Value::String(string.into())
+}
+
+#[test]
+fn keywords() {
+    assert_eq!(parse("null"), Ok(Value::Null));
+    assert_eq!(parse(" true "), Ok(Value::Bool(true)));
+    assert_eq!(parse("false"), Ok(Value::Bool(false)));
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(parse("0"), Ok(Value::Number(0.0)));
+    assert_eq!(parse("-12"), Ok(Value::Number(-12.0)));
+    assert_eq!(parse("1.5e3"), Ok(Value::Number(1500.0)));
+    assert_eq!(parse("1.2.3"), Err(Error::InvalidNumber(0)));
+}
+
+#[test]
+fn strings() {
+    assert_eq!(parse(r#""""#), Ok(string("")));
+    assert_eq!(parse(r#""a\"b\\c\nd""#), Ok(string("a\"b\\c\nd")));
+    assert_eq!(parse(r#""é🦀""#), Ok(string("é🦀")));
+    assert_eq!(parse(r#""\ud83e""#), Err(Error::UnexpectedChar(7, '"')));
+    assert_eq!(parse(r#""\x""#), Err(Error::InvalidEscape(2)));
+    assert_eq!(parse("\"a\nb\""), Err(Error::UnexpectedChar(2, '\n')));
+    assert_eq!(parse(r#""abc"#), Err(Error::UnexpectedEndOfInput));
+}
+
+#[test]
+fn arrays() {
+    assert_eq!(parse("[]"), Ok(Value::Array(Vec::new())));
+    assert_eq!(
+        parse(r#"[1, "two" , [null]]"#),
+        Ok(Value::Array(vec![Value::Number(1.0), string("two"), Value::Array(vec![Value::Null])]))
+    );
+    assert_eq!(parse("[1,]"), Err(Error::UnexpectedChar(3, ']')));
+    assert_eq!(parse("[1 2]"), Err(Error::UnexpectedChar(3, '2')));
+}
+
+#[test]
+fn objects() {
+    assert_eq!(parse("{ }"), Ok(Value::Object(Vec::new())));
+
+    let value = parse(r#"{"b": 1, "a": {"c": true}}"#).unwrap();
+    assert_eq!(
+        value,
+        Value::Object(vec![
+            ("b".into(), Value::Number(1.0)),
+            ("a".into(), Value::Object(vec![("c".into(), Value::Bool(true))])),
+        ])
+    );
+    assert_eq!(value.get("b").and_then(Value::as_usize), Some(1));
+    assert_eq!(value.get("a").and_then(|value| value.get("c")), Some(&Value::Bool(true)));
+    assert_eq!(value.get("d"), None);
+
+    assert_eq!(parse(r#"{"a" 1}"#), Err(Error::UnexpectedChar(5, '1')));
+    assert_eq!(parse("{1: 2}"), Err(Error::UnexpectedChar(1, '1')));
+}
+
+#[test]
+fn trailing_input() {
+    assert_eq!(parse("null x"), Err(Error::TrailingInput(5)));
+}
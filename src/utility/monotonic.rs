@@ -1,24 +1,26 @@
 use crate::utility::default;
-use std::cell::{Ref, RefCell};
+use std::sync::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
 
 pub(crate) struct MonotonicVec<T> {
-    items: RefCell<Vec<T>>,
+    items: RwLock<Vec<T>>,
 }
 
 impl<T> MonotonicVec<T> {
-    pub(crate) fn push(&self, item: T) {
-        self.items.borrow_mut().push(item);
+    /// Push the item constructed from the last item.
+    ///
+    /// This happens atomically, so the last item can't change in the meantime.
+    pub(crate) fn push_with<R>(&self, construct: impl FnOnce(Option<&T>) -> (T, R)) -> R {
+        let mut items = self.items.write().unwrap();
+        let (item, result) = construct(items.last());
+        items.push(item);
+        result
     }
 
-    pub(crate) fn get(&self, index: usize) -> Option<Ref<'_, T>> {
-        Ref::filter_map(self.items.borrow(), |items| items.get(index)).ok()
+    pub(crate) fn get(&self, index: usize) -> Option<MappedRwLockReadGuard<'_, T>> {
+        RwLockReadGuard::filter_map(self.items.read().unwrap(), |items| items.get(index)).ok()
     }
 
-    pub(crate) fn last(&self) -> Option<Ref<'_, T>> {
-        Ref::filter_map(self.items.borrow(), |items| items.last()).ok()
-    }
-
-    pub(crate) fn iter(&self) -> impl Iterator<Item = Ref<'_, T>> {
+    pub(crate) fn iter(&self) -> impl Iterator<Item = MappedRwLockReadGuard<'_, T>> {
         (0..).map(|index| self.get(index)).take_while(Option::is_some).flatten()
     }
 }
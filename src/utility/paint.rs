@@ -4,7 +4,7 @@ use anstyle::{AnsiColor, Effects, Style};
 use smallvec::SmallVec;
 use std::io;
 
-fn colorize(stream: &impl RawStream) -> bool {
+pub(crate) fn colorize(stream: &impl RawStream) -> bool {
     AutoStream::choice(stream) != ColorChoice::Never
 }
 
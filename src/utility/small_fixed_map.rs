@@ -28,6 +28,14 @@ pub(crate) trait SmallKey: Copy {
     fn index(self) -> usize;
 }
 
+impl SmallKey for () {
+    type const LEN: usize = 1;
+
+    fn index(self) -> usize {
+        0
+    }
+}
+
 pub(crate) macro SmallKey {
     derive() ($vis:vis enum $name:ident { $($variant:ident),* $(,)? }) => {
         impl SmallKey for $name {
@@ -0,0 +1,194 @@
+//! Helpers shared by the unit tests.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+#[cfg(unix)]
+use {crate::data::PlusPrefixedToolchain, std::sync::LazyLock};
+
+/// A temporary directory that gets removed on drop.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("rruxwry-test-{}-{count}", process::id()));
+        // Get rid of leftovers of a previous process that happened to have the same ID.
+        _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write the given file, creating any missing parent directories.
+    pub(crate) fn write(&self, path: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A toolchain whose `rustc` records its invocations instead of compiling anything.
+///
+/// It answers the queries for the version, the target and the crate name on its own.
+/// Otherwise, it creates empty artifacts of the requested kind. A source line `// fake: error`
+/// makes it fail (`// fake: error CFG` only if `--cfg CFG` is passed) and binaries execute
+/// the shell commands given via `// fake: run COMMANDS`.
+#[cfg(unix)]
+pub(crate) struct FakeToolchain(TempDir);
+
+#[cfg(unix)]
+impl FakeToolchain {
+    /// Create a toolchain whose target has the given cfgs (in the format of `--print=cfg`).
+    pub(crate) fn new(cfgs: &str) -> Self {
+        // We write the script only once and link to it since executing a file that another
+        // thread is writing to at the same time (or rather, that a concurrently spawned
+        // process inherited a writable handle to) fails with `ETXTBSY`. It's shared across
+        // test runs since statics don't get dropped, so we couldn't clean it up anyway.
+        static SCRIPT: LazyLock<PathBuf> = LazyLock::new(|| {
+            use std::{
+                hash::{BuildHasher as _, BuildHasherDefault, DefaultHasher},
+                os::unix::fs::PermissionsExt as _,
+            };
+
+            let script = FAKE_RUSTC
+                .replace("{VAR}", crate::build::LIBRARY_PATH_VAR)
+                .replace("{DLL_PREFIX}", env::consts::DLL_PREFIX)
+                .replace("{DLL_SUFFIX}", env::consts::DLL_SUFFIX);
+            let hash = BuildHasherDefault::<DefaultHasher>::default().hash_one(&script);
+            let path = env::temp_dir().join(format!("rruxwry-test-rustc-{hash:016x}"));
+            if !path.exists() {
+                // Concurrent test runs may race to create it, so make that atomic.
+                let temporary = path.with_extension(process::id().to_string());
+                fs::write(&temporary, script).unwrap();
+                fs::set_permissions(&temporary, fs::Permissions::from_mode(0o755)).unwrap();
+                fs::rename(&temporary, &path).unwrap();
+            }
+            path
+        });
+
+        let dir = TempDir::new();
+        dir.write("version", "rustc 1.90.0-nightly (0123456789 2025-07-01)");
+        dir.write("cfg", cfgs);
+        fs::create_dir(dir.path().join("bin")).unwrap();
+        std::os::unix::fs::symlink(&*SCRIPT, dir.path().join("bin/rustc")).unwrap();
+        Self(dir)
+    }
+
+    /// Overwrite the version (in the format of `-V`) which is nightly by default.
+    pub(crate) fn set_version(&self, version: &str) {
+        self.0.write("version", version);
+    }
+
+    pub(crate) fn toolchain(&self) -> PlusPrefixedToolchain {
+        let mut toolchain = std::ffi::OsString::from("+");
+        toolchain.push(self.0.path());
+        PlusPrefixedToolchain::new(toolchain).unwrap()
+    }
+
+    pub(crate) fn libdir(&self) -> PathBuf {
+        self.0.path().join("lib")
+    }
+
+    /// The source code fed via STDIN to the last invocation compiling the given crate.
+    pub(crate) fn input(&self, crate_name: &str) -> String {
+        fs::read_to_string(self.0.path().join(format!("{crate_name}.stdin"))).unwrap()
+    }
+
+    /// The invocations of `rustc` in order, excluding the queries it answered on its own.
+    pub(crate) fn invocations(&self) -> Vec<Invocation> {
+        let log = fs::read_to_string(self.0.path().join("log")).unwrap_or_default();
+        log.lines()
+            .map(|line| {
+                let mut fields = line.split('\t').map(str::to_owned);
+                let library_path = fields.next().unwrap();
+                // The last field is empty since each field is followed by a tab.
+                let mut args: Vec<_> = fields.collect();
+                args.pop();
+                Invocation { library_path, args }
+            })
+            .collect()
+    }
+}
+
+#[cfg(unix)]
+pub(crate) struct Invocation {
+    /// The search path of the dynamic linker.
+    pub(crate) library_path: String,
+    pub(crate) args: Vec<String>,
+}
+
+#[cfg(unix)]
+impl Invocation {
+    /// Whether the given arguments appear consecutively.
+    pub(crate) fn has(&self, args: &[&str]) -> bool {
+        self.args.windows(args.len()).any(|window| window == args)
+    }
+
+    /// The path to the source file.
+    pub(crate) fn source(&self) -> &str {
+        self.args.iter().find(|arg| Path::new(arg).extension() == Some("rs".as_ref())).unwrap()
+    }
+}
+
+#[cfg(unix)]
+const FAKE_RUSTC: &str = r#"#!/bin/sh
+root=$(cd "$(dirname "$0")/.." && pwd)
+version=$(cat "$root/version")
+[ "$1" = -V ] && { echo "$version"; exit; }
+name= type= emit= out=. cfgs= previous=
+for arg in "$@"; do
+    case $previous in
+        --crate-name) name=$arg;;
+        --crate-type) type=$arg;;
+        --out-dir) out=$arg;;
+        --cfg) cfgs="$cfgs $arg";;
+    esac
+    case $arg in
+        -vV) printf '%s\nhost: fake-unknown-none\n' "$version"; exit;;
+        --print=cfg) cat "$root/cfg"; exit;;
+        --print=target-libdir) echo "$root/lib"; exit;;
+        --emit=*) emit=${arg#--emit=};;
+        *.rs | -) source=$arg;;
+    esac
+    previous=$arg
+done
+[ -n "$name" ] || name=$(basename "$source" .rs)
+for arg in "$@"; do
+    [ "$arg" = --print=crate-name ] && { echo "$name"; exit; }
+done
+printf '%s\t' "$(printenv {VAR})" "$@" >> "$root/log"
+echo >> "$root/log"
+if [ "$source" = - ]; then
+    source=$root/$name.stdin
+    cat > "$source"
+fi
+for cfg in '' $cfgs; do
+    grep -qx "// fake: error${cfg:+ $cfg}" "$source" && { echo 'error: requested by the test' >&2; exit 1; }
+done
+[ -n "$type" ] || type=$(sed -n 's/^#!\[crate_type = "\(.*\)"\]$/\1/p' "$source")
+mkdir -p "$out"
+case $emit,$type in
+    metadata,*) : > "$out/lib$name.rmeta";;
+    *,lib | *,rlib) : > "$out/lib$name.rlib";;
+    *,dylib | *,proc-macro) : > "$out/{DLL_PREFIX}$name{DLL_SUFFIX}";;
+    *)
+        { echo '#!/bin/sh'; sed -n 's|^// fake: run ||p' "$source"; } > "$out/$name"
+        chmod +x "$out/$name";;
+esac
+"#;